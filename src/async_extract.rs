@@ -0,0 +1,409 @@
+//! Async extraction engine (`async` feature)
+//!
+//! Firebird access and Parquet encoding are blocking, so the fetch and write
+//! stages run on tokio's blocking pool; the stages of each partition are
+//! connected by async channels instead of dedicated threads.
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use tokio::{sync::{mpsc, Semaphore}, task};
+use tracing::{field, info_span, instrument, warn, Instrument, Span};
+
+use crate::cancel::{self, CancellationToken};
+use crate::extractor::{
+    build_arrow_batch, check_rows_windows, merge_parquet_files, null_key_rows,
+    rows_per_sec, ExtractionStats, Extractor, PartitionResult, PartitionStats, TableMetadata,
+    DEFAULT_PREFETCH_DEPTHS,
+};
+use crate::error::ExtractError;
+use crate::manifest::{schema_fields, Manifest, MissingPartition};
+use crate::memory::{estimate_rows_bytes, BatchSizer, MemoryBudget};
+use crate::partition::{self, PartitionSpec};
+use crate::source::{Row, Source};
+use crate::transform::{self, BatchTransform};
+
+impl Extractor {
+    /// Async counterpart of [`Extractor::extract_table`] for callers already
+    /// running inside a tokio runtime. Uses the same partition plan, output
+    /// layout and manifest as the sync engine, and honours its retry policy,
+    /// `allow_partial` and `verify_unique`.
+    pub async fn extract_table_async(&self, table_name: &str) -> Result<ExtractionStats, ExtractError> {
+        self.for_table(table_name)
+            .extract_overridden_async(table_name)
+            .await
+            .map_err(|e| ExtractError::classify(e, ExtractError::PartitionFailed))
+    }
+
+    #[instrument(name = "extract_table", skip_all, fields(table = table_name, rows = field::Empty))]
+    async fn extract_overridden_async(&self, table_name: &str) -> Result<ExtractionStats> {
+        self.cancel.check()?;
+        let start = Instant::now();
+        let output_path = self.output_path(table_name)?;
+        let replaced = self.replaced_appends(table_name, &output_path);
+
+        let this = self.clone();
+        let table = table_name.to_string();
+        let (meta, plan) = task::spawn_blocking(move || -> Result<_> {
+            let meta = this.load_metadata(&table)?;
+            // An empty table still gets its (empty) output file and manifest
+            let plan = if meta.row_count.is_empty() { vec![PartitionSpec::full()] } else { this.plan_for(&meta)? };
+            anyhow::ensure!(!plan.is_empty(), "Partition plan for {} is empty", table);
+            Ok((meta, plan))
+        })
+        .await?
+        .map_err(|e| ExtractError::tag(ExtractError::Metadata, e))?;
+        let meta = Arc::new(meta);
+        self.check_schema_drift(table_name, &meta, &output_path)?;
+        let merge_key = self.merge_key(table_name, &meta)?;
+
+        let sizer = self.batch_sizer(&meta);
+        let depth = self.config.prefetch_depth;
+        let schema = self.output_schema(&meta)?;
+        let props = if plan.len() == 1 { self.output_writer_props(&schema) } else { self.temp_writer_props() };
+
+        // A single partition is written in place; otherwise temp files + merge
+        let targets: Vec<PathBuf> = if plan.len() == 1 {
+            vec![output_path.clone()]
+        } else {
+            (0..plan.len()).map(|i| self.partition_temp_path(&output_path, i)).collect()
+        };
+
+        // As many partitions in flight as the sync engine runs workers, largest
+        // first, so a plan with hundreds of partitions doesn't open hundreds of
+        // connections at once
+        let slots = Arc::new(Semaphore::new(self.config.parallelism.clamp(1, plan.len())));
+        let mut tasks = task::JoinSet::new();
+        for i in partition::largest_first(&plan) {
+            let slot = Arc::clone(&slots).acquire_owned().await?;
+            let spec = plan[i].clone();
+            let source = Arc::clone(&self.source);
+            let budget = Arc::clone(&self.budget);
+            let meta = Arc::clone(&meta);
+            let path = targets[i].clone();
+            let props = props.clone();
+            let sizer = sizer.clone();
+            let schema = Arc::clone(&schema);
+            let cancel = self.cancel.clone();
+            let transform = self.transform.clone();
+            let retry = self.config.retry.clone();
+            let span = info_span!("partition", partition = i);
+            tasks.spawn(
+                async move {
+                    let _slot = slot;
+                    let started = Instant::now();
+                    let mut attempts = 0;
+                    // Every attempt rewrites its file from the start
+                    let result = retry
+                        .run_async(&format!("Partition {}", i), || {
+                            attempts += 1;
+                            let attempt = extract_partition_async(
+                                Arc::clone(&source),
+                                Arc::clone(&budget),
+                                Arc::clone(&meta),
+                                spec.clone(),
+                                sizer.clone(),
+                                depth,
+                                path.clone(),
+                                Arc::clone(&schema),
+                                props.clone(),
+                                cancel.clone(),
+                                transform.clone(),
+                            );
+                            let cancel = cancel.clone();
+                            async move {
+                                cancel.check()?;
+                                attempt.await
+                            }
+                        })
+                        .await;
+                    let retries = attempts.saturating_sub(1);
+                    (i, result, retries, started.elapsed().as_secs_f64())
+                }
+                .instrument(span),
+            );
+        }
+
+        let mut partition_stats: Vec<PartitionStats> =
+            plan.iter()
+                .enumerate()
+                .map(|(index, spec)| PartitionStats { index, range: spec.to_string(), ..PartitionStats::default() })
+                .collect();
+        let mut totals = PartitionResult::default();
+        let mut first_error = None;
+        let mut missing = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let (i, result, retries, duration_secs) = joined.context("partition task panicked")?;
+            partition_stats[i].duration_secs = duration_secs;
+            partition_stats[i].retries = retries;
+            match result {
+                Ok(part) => {
+                    partition_stats[i].rows = part.rows;
+                    partition_stats[i].rows_per_sec = rows_per_sec(part.rows, duration_secs);
+                    partition_stats[i].bytes_read = part.bytes;
+                    partition_stats[i].batches = part.batches;
+                    totals.absorb(&part);
+                }
+                Err(e) => {
+                    warn!("  Partition {} failed: {:#}", i, e);
+                    partition_stats[i].error = Some(format!("{:#}", e));
+                    missing.push(MissingPartition { index: i, partition: plan[i].clone(), error: format!("{:#}", e) });
+                    first_error.get_or_insert(e.context(format!("Partition {} failed", i)));
+                }
+            }
+        }
+        missing.sort_by_key(|m| m.index);
+
+        if self.cancel.is_cancelled() {
+            for path in &targets {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(cancel::cancelled());
+        }
+        // A single partition is written in place, so there's no output without it
+        let partial = !missing.is_empty();
+        if let Some(e) = first_error.filter(|_| !self.config.allow_partial || plan.len() == 1) {
+            for path in &targets {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e.context(format!("Async extraction of {} failed; no output written", meta.table_name)));
+        }
+
+        if plan.len() > 1 {
+            let inputs: Vec<PathBuf> = targets
+                .iter()
+                .zip(&partition_stats)
+                .filter(|(_, stats)| stats.rows > 0)
+                .map(|(path, _)| path.clone())
+                .collect();
+            let merge_output = output_path.clone();
+            let output_props = self.output_writer_props(&schema);
+            let merged = task::spawn_blocking(move || merge_parquet_files(&inputs, &merge_output, output_props)).await;
+            for path in &targets {
+                let _ = std::fs::remove_file(path);
+            }
+            merged?.map_err(|e| ExtractError::tag(ExtractError::Merge, e))?;
+        }
+
+        let total_rows = totals.rows;
+        Span::current().record("rows", total_rows);
+        if !partial && self.transform.is_none() {
+            check_rows_windows(table_name, &plan, total_rows)?;
+        }
+        let (this, verify_meta, verify_path) = (self.clone(), Arc::clone(&meta), output_path.clone());
+        let source_rows = task::spawn_blocking(move || {
+            if this.config.verify_unique {
+                this.verify_unique_output(&verify_meta, &verify_path, partial)?;
+            }
+            this.verify_row_count(&verify_meta, total_rows, partial, None)
+        })
+        .await??;
+        let failed_partitions = missing.len();
+        let mut manifest = self.manifest(&meta.table_name, &output_path);
+        manifest.rows = total_rows;
+        manifest.partitions = plan.len();
+        manifest.partial = partial;
+        manifest.source_rows = source_rows;
+        manifest.schema = schema_fields(&schema);
+        manifest.missing_partitions = missing;
+        manifest.partition_stats = partition_stats.clone();
+        manifest
+            .write()
+            .and_then(|()| self.record_output(table_name, &output_path, &replaced))
+            .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        if partial {
+            warn!(
+                "  ⚠ Partial output: {} partitions missing, see {}",
+                failed_partitions,
+                Manifest::path_for(&output_path).display()
+            );
+        }
+
+        let file_size_mb = std::fs::metadata(&output_path)
+            .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+            .unwrap_or(0.0);
+
+        let mut stats = ExtractionStats {
+            rows_extracted: total_rows,
+            duration_secs: start.elapsed().as_secs_f64(),
+            file_size_mb,
+            failed_partitions,
+            bytes_read: totals.bytes,
+            batches: totals.batches,
+            null_counts: totals.null_counts(&schema),
+            output_files: vec![output_path.clone(), Manifest::path_for(&output_path)],
+            null_key_rows: null_key_rows(&plan, &partition_stats),
+            special_floats: meta.special_floats_seen.load(Ordering::Relaxed),
+            text_issues: meta.text_issues(),
+            partitions: partition_stats,
+            tombstones: 0,
+            merge: None,
+        };
+        let this = self.clone();
+        let table = table_name.to_string();
+        task::spawn_blocking(move || -> Result<ExtractionStats> {
+            let mut stats = stats;
+            if this.config.validate {
+                this.validate_output(&meta, &output_path, &mut stats)?;
+            }
+            if this.config.pk_gap_report {
+                this.report_pk_gaps(&meta, &output_path, &mut stats)?;
+            }
+            this.finish_outputs(&table, &meta, merge_key.as_deref(), &output_path, true, &mut stats)
+                .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+            Ok(stats)
+        })
+        .await?
+    }
+}
+
+/// Fetch → convert → write pipeline for one partition.
+#[allow(clippy::too_many_arguments)]
+async fn extract_partition_async(
+    source: Arc<dyn Source>,
+    budget: Arc<MemoryBudget>,
+    meta: Arc<TableMetadata>,
+    spec: PartitionSpec,
+    mut sizer: BatchSizer,
+    prefetch_depth: Option<usize>,
+    output_path: PathBuf,
+    schema: SchemaRef,
+    props: WriterProperties,
+    cancel: CancellationToken,
+    transform: Option<BatchTransform>,
+) -> Result<PartitionResult> {
+    let (fetch_depth, convert_depth) =
+        prefetch_depth.map_or(DEFAULT_PREFETCH_DEPTHS, |depth| (depth.max(1), depth.max(1)));
+    let (row_tx, mut row_rx) = mpsc::channel::<Vec<Row>>(fetch_depth);
+    let (batch_tx, mut batch_rx) = mpsc::channel::<RecordBatch>(convert_depth);
+
+    // Fetch stage: stream rows off the cursor in adaptively sized chunks
+    let fetch_meta = Arc::clone(&meta);
+    let fetch_budget = Arc::clone(&budget);
+    let fetch_cancel = cancel.clone();
+    let fetcher = task::spawn_blocking(move || -> Result<usize> {
+        let mut conn = source.connect_until(&fetch_cancel)?;
+        let mut bytes = 0;
+        let select = fetch_meta.select(false, &spec);
+        let mut chunk: Vec<Row> = Vec::with_capacity(sizer.rows());
+        let mut receiver_gone = false;
+        conn.fetch(&select, &mut |row| {
+            fetch_cancel.check()?;
+            chunk.push(row);
+            if chunk.len() >= sizer.rows() {
+                let row_bytes = estimate_rows_bytes(&chunk);
+                bytes += row_bytes;
+                sizer.observe(chunk.len(), row_bytes);
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(sizer.rows()));
+                fetch_budget.reserve(row_bytes);
+                if row_tx.blocking_send(full).is_err() {
+                    // Nobody will convert these rows, so nobody else releases them
+                    fetch_budget.release(row_bytes);
+                    receiver_gone = true;
+                }
+            }
+            Ok(!receiver_gone)
+        })?;
+        if receiver_gone {
+            return Ok(bytes);
+        }
+        if !chunk.is_empty() {
+            let row_bytes = estimate_rows_bytes(&chunk);
+            bytes += row_bytes;
+            fetch_budget.reserve(row_bytes);
+            if row_tx.blocking_send(chunk).is_err() {
+                fetch_budget.release(row_bytes);
+            }
+        }
+        Ok(bytes)
+    });
+
+    // Convert stage: Arrow conversion is CPU-bound, keep it off the async workers
+    let convert_meta = Arc::clone(&meta);
+    let convert_budget = Arc::clone(&budget);
+    let convert_cancel = cancel.clone();
+    let converter = task::spawn(async move {
+        let converted = async {
+            while let Some(rows) = row_rx.recv().await {
+                if convert_cancel.is_cancelled() {
+                    convert_budget.release(estimate_rows_bytes(&rows));
+                    return Err(cancel::cancelled());
+                }
+                let meta = Arc::clone(&convert_meta);
+                let budget = Arc::clone(&convert_budget);
+                let transform = transform.clone();
+                let batch = task::spawn_blocking(move || -> Result<Option<RecordBatch>> {
+                    let row_bytes = estimate_rows_bytes(&rows);
+                    let batch =
+                        build_arrow_batch(&meta, &rows).and_then(|batch| transform::apply(transform.as_ref(), batch));
+                    match batch {
+                        Ok(Some(ref batch)) => budget.transfer(row_bytes, batch.get_array_memory_size()),
+                        _ => budget.release(row_bytes),
+                    }
+                    batch
+                })
+                .await??;
+                let Some(batch) = batch else {
+                    continue;
+                };
+                if let Err(unsent) = batch_tx.send(batch).await {
+                    convert_budget.release(unsent.0.get_array_memory_size());
+                    break;
+                }
+            }
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+        // Rows still queued when conversion stops early were reserved by the fetcher
+        row_rx.close();
+        while let Ok(rows) = row_rx.try_recv() {
+            convert_budget.release(estimate_rows_bytes(&rows));
+        }
+        converted
+    });
+
+    // Write stage
+    let writer = task::spawn_blocking(move || -> Result<PartitionResult> {
+        let write = || -> Result<PartitionResult> {
+            let file = File::create(&output_path)?;
+            let buf = BufWriter::with_capacity(128 * 1024 * 1024, file);
+            let mut writer = ArrowWriter::try_new(buf, schema, Some(props))?;
+            let mut result = PartitionResult::default();
+            while let Some(batch) = batch_rx.blocking_recv() {
+                if cancel.is_cancelled() {
+                    budget.release(batch.get_array_memory_size());
+                    break;
+                }
+                result.rows += batch.num_rows();
+                result.count_batch(&batch);
+                let written = writer.write(&batch);
+                budget.release(batch.get_array_memory_size());
+                written?;
+            }
+            writer.close()?;
+            Ok(result)
+        };
+        let written = write();
+        // Batches still queued when writing stops early were reserved by the converter
+        batch_rx.close();
+        while let Ok(batch) = batch_rx.try_recv() {
+            budget.release(batch.get_array_memory_size());
+        }
+        written
+    });
+
+    let (fetched, converted, written) = tokio::join!(fetcher, converter, writer);
+    let bytes = fetched??;
+    converted??;
+    let mut result = written??;
+    result.bytes = bytes;
+    Ok(result)
+}
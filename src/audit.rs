@@ -0,0 +1,139 @@
+//! Audit log of extraction runs, written back to the source database
+//!
+//! [`Extractor::record_audit`] inserts a row per table into an audit table,
+//! creating it on first use, so DBAs can see who exported what and when
+//! with plain SQL. It uses a connection of its own, the only one allowed to
+//! write; extraction connections stay read-only. `EXPORTED_BY` and
+//! `CLIENT_ADDRESS` are taken from the attachment by the server.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use rsfbclient::{Execute, Queryable};
+use tracing::{debug, info};
+
+use crate::error::ExtractError;
+use crate::extractor::{ConnectionPool, ExtractionStats, Extractor};
+use crate::partition::quote_ident;
+
+/// Audit table used when none is named.
+pub const DEFAULT_AUDIT_TABLE: &str = "PEREGRINE_AUDIT";
+
+const CREATE_COLUMNS: &str = "(
+    STARTED_AT TIMESTAMP NOT NULL,
+    TABLE_NAME VARCHAR(252) NOT NULL,
+    STATUS VARCHAR(16) NOT NULL,
+    ROW_COUNT BIGINT,
+    DURATION_SECS DOUBLE PRECISION,
+    WATERMARK VARCHAR(64),
+    OUTPUT_PATH VARCHAR(1024),
+    ERROR_MESSAGE VARCHAR(2000),
+    EXPORTED_BY VARCHAR(63) NOT NULL,
+    OS_USER VARCHAR(255),
+    CLIENT_ADDRESS VARCHAR(255),
+    RECORDED_AT TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+)";
+
+/// One table's outcome in a run.
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    pub table: String,
+    /// When the run started
+    pub started_at: DateTime<Local>,
+    /// `succeeded`, `failed` or `cancelled`
+    pub status: &'static str,
+    pub rows: Option<usize>,
+    pub duration_secs: Option<f64>,
+    /// Point up to which the output holds the source's data. Extraction is
+    /// always full, so this is the run's start time
+    pub watermark: Option<String>,
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// The entry for `table`'s result in a run started at `started_at`.
+    pub fn new(table: &str, result: &Result<ExtractionStats, ExtractError>, started_at: DateTime<Local>) -> Self {
+        let watermark = Some(started_at.to_rfc3339());
+        match result {
+            Ok(stats) => Self {
+                table: table.to_string(),
+                started_at,
+                status: "succeeded",
+                rows: Some(stats.rows_extracted),
+                duration_secs: Some(stats.duration_secs),
+                watermark,
+                output_path: stats.output_files.first().map(|path| path.display().to_string()),
+                error: None,
+            },
+            Err(e) => Self {
+                table: table.to_string(),
+                started_at,
+                status: if matches!(e, ExtractError::Cancelled(_)) { "cancelled" } else { "failed" },
+                rows: None,
+                duration_secs: None,
+                watermark: None,
+                output_path: None,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+impl Extractor {
+    /// Insert `entries` into `audit_table` (an unquoted name, upper-cased
+    /// like SQL does), creating it if it doesn't exist.
+    pub fn record_audit(&self, audit_table: &str, entries: &[AuditEntry]) -> Result<(), ExtractError> {
+        self.write_audit(audit_table, entries).map_err(|e| ExtractError::classify(e, ExtractError::WriterIo))
+    }
+
+    fn write_audit(&self, audit_table: &str, entries: &[AuditEntry]) -> Result<()> {
+        let name = audit_table.trim().to_uppercase();
+        let table = quote_ident(&name);
+        let mut conn = ConnectionPool::connect_read_write(&self.config)?;
+
+        let exists_sql = "SELECT COUNT(*) FROM rdb$relations WHERE rdb$relation_name = ?";
+        let exists = |conn: &mut rsfbclient::SimpleConnection| -> Result<bool> {
+            let counts: Vec<(i64,)> = conn.query(exists_sql, (name.clone(),))?;
+            Ok(counts.first().is_some_and(|c| c.0 > 0))
+        };
+        if !exists(&mut conn)? {
+            let created = conn.execute(&format!("CREATE TABLE {} {}", table, CREATE_COLUMNS), ());
+            match created {
+                Ok(_) => info!("Created audit table {}", name),
+                // Another run created it in the meantime
+                Err(_) if exists(&mut conn)? => debug!("Audit table {} appeared concurrently", name),
+                Err(e) => return Err(e).with_context(|| format!("Failed to create audit table {}", name)),
+            }
+        }
+
+        let sql = format!(
+            "INSERT INTO {} (STARTED_AT, TABLE_NAME, STATUS, ROW_COUNT, DURATION_SECS, WATERMARK, OUTPUT_PATH, \
+             ERROR_MESSAGE, EXPORTED_BY, OS_USER, CLIENT_ADDRESS) \
+             VALUES (CAST(? AS TIMESTAMP), ?, ?, ?, ?, ?, ?, ?, CURRENT_USER, ?, \
+             RDB$GET_CONTEXT('SYSTEM', 'CLIENT_ADDRESS'))",
+            table
+        );
+        let os_user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok();
+        for entry in entries {
+            let params = (
+                entry.started_at.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+                truncate(&entry.table, 252),
+                entry.status.to_string(),
+                entry.rows.map(|rows| rows as i64),
+                entry.duration_secs,
+                entry.watermark.clone(),
+                entry.output_path.as_deref().map(|path| truncate(path, 1024)),
+                entry.error.as_deref().map(|error| truncate(error, 2000)),
+                os_user.as_deref().map(|user| truncate(user, 255)),
+            );
+            conn.execute(&sql, params).with_context(|| format!("Failed to write the audit row of {}", entry.table))?;
+        }
+        debug!("Recorded {} audit rows in {}", entries.len(), name);
+        Ok(())
+    }
+}
+
+/// `s` cut to `max` characters, to fit a VARCHAR column.
+fn truncate(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
@@ -0,0 +1,137 @@
+//! Synthetic tables for `benchmark`
+//!
+//! [`Extractor::create_synthetic_table`] creates a table of the requested
+//! shape and fills it on the server with `EXECUTE BLOCK` loops, so no rows
+//! cross the network, then `benchmark` extracts it under several settings.
+//! Values are derived from the row number: integers and doubles differ per
+//! row, text columns are the number padded to their length, and every
+//! `1 / blob_ratio`-th row has a text blob of `blob_bytes`.
+
+use anyhow::{Context, Result};
+use rsfbclient::{Execute, Queryable};
+use tracing::info;
+
+use crate::error::ExtractError;
+use crate::extractor::{ConnectionPool, Extractor};
+use crate::partition::quote_ident;
+
+/// Rows inserted per `EXECUTE BLOCK`, each its own transaction.
+const ROWS_PER_BLOCK: u64 = 100_000;
+
+/// Shape of a synthetic table: a BIGINT primary key `ID` followed by the
+/// requested columns.
+#[derive(Clone, Debug)]
+pub struct SyntheticTable {
+    pub rows: u64,
+    /// BIGINT columns `I1`, `I2`, ...
+    pub int_columns: usize,
+    /// DOUBLE PRECISION columns `D1`, ...
+    pub double_columns: usize,
+    /// VARCHAR(`text_length`) columns `T1`, ...
+    pub text_columns: usize,
+    pub text_length: usize,
+    /// Share of rows, 0 to 1, with a value in the text blob column `B`;
+    /// 0 leaves the column out
+    pub blob_ratio: f64,
+    pub blob_bytes: usize,
+}
+
+impl Default for SyntheticTable {
+    fn default() -> Self {
+        Self {
+            rows: 1_000_000,
+            int_columns: 4,
+            double_columns: 2,
+            text_columns: 4,
+            text_length: 32,
+            blob_ratio: 0.0,
+            blob_bytes: 4096,
+        }
+    }
+}
+
+impl SyntheticTable {
+    fn create_sql(&self, table: &str) -> String {
+        let mut columns = vec!["ID BIGINT NOT NULL PRIMARY KEY".to_string()];
+        columns.extend((1..=self.int_columns).map(|i| format!("I{} BIGINT", i)));
+        columns.extend((1..=self.double_columns).map(|i| format!("D{} DOUBLE PRECISION", i)));
+        columns.extend((1..=self.text_columns).map(|i| format!("T{} VARCHAR({})", i, self.text_length.max(1))));
+        if self.blob_ratio > 0.0 {
+            columns.push("B BLOB SUB_TYPE TEXT".to_string());
+        }
+        format!("CREATE TABLE {} ({})", quote_ident(table), columns.join(", "))
+    }
+
+    /// An `EXECUTE BLOCK` inserting rows `first..=last`.
+    fn fill_sql(&self, table: &str, first: u64, last: u64) -> String {
+        let mut names = vec!["ID".to_string()];
+        let mut values = vec![":i".to_string()];
+        for i in 1..=self.int_columns {
+            names.push(format!("I{}", i));
+            values.push(format!("MOD(:i * {}, 1000003)", 2 * i + 1));
+        }
+        for i in 1..=self.double_columns {
+            names.push(format!("D{}", i));
+            values.push(format!(":i / {}.0e0", i + 2));
+        }
+        let length = self.text_length.max(1);
+        for i in 1..=self.text_columns {
+            names.push(format!("T{}", i));
+            values.push(format!("LEFT(RPAD(:i + {}, {}, '-abcdefghij'), {})", i, length, length));
+        }
+        if self.blob_ratio > 0.0 {
+            // Every n-th row gets a blob
+            let every = (1.0 / self.blob_ratio.min(1.0)).round().max(1.0) as u64;
+            names.push("B".to_string());
+            values.push(format!(
+                "IIF(MOD(:i, {}) = 0, RPAD(CAST('' AS BLOB SUB_TYPE TEXT), {}, 'lorem ipsum '), NULL)",
+                every, self.blob_bytes
+            ));
+        }
+        format!(
+            "EXECUTE BLOCK AS DECLARE i BIGINT = {}; BEGIN WHILE (i <= {}) DO BEGIN \
+             INSERT INTO {} ({}) VALUES ({}); i = i + 1; END END",
+            first,
+            last,
+            quote_ident(table),
+            names.join(", "),
+            values.join(", ")
+        )
+    }
+}
+
+impl Extractor {
+    /// Create `table` shaped as `spec` and fill it; fails if it exists.
+    pub fn create_synthetic_table(&self, table: &str, spec: &SyntheticTable) -> Result<(), ExtractError> {
+        let create = || -> Result<()> {
+            let mut conn = ConnectionPool::connect_read_write(&self.config)?;
+            conn.execute(&spec.create_sql(table), ()).with_context(|| format!("Failed to create {}", table))?;
+            info!("Filling {} with {} rows...", table, spec.rows);
+            let mut first = 1;
+            while first <= spec.rows {
+                self.cancel.check()?;
+                let last = (first + ROWS_PER_BLOCK - 1).min(spec.rows);
+                conn.execute(&spec.fill_sql(table, first, last), ())
+                    .with_context(|| format!("Failed to insert rows {} to {} into {}", first, last, table))?;
+                first = last + 1;
+            }
+            Ok(())
+        };
+        create().map_err(|e| ExtractError::classify(e, ExtractError::WriterIo))
+    }
+
+    /// Drop a table made by [`Extractor::create_synthetic_table`].
+    pub fn drop_synthetic_table(&self, table: &str) -> Result<(), ExtractError> {
+        let drop = || -> Result<()> {
+            let mut conn = ConnectionPool::connect_read_write(&self.config)?;
+            let counts: Vec<(i64,)> =
+                conn.query("SELECT COUNT(*) FROM rdb$relations WHERE rdb$relation_name = ?", (table.to_string(),))?;
+            if counts.first().is_some_and(|c| c.0 > 0) {
+                conn.execute(&format!("DROP TABLE {}", quote_ident(table)), ())
+                    .with_context(|| format!("Failed to drop {}", table))?;
+            }
+            Ok(())
+        };
+        drop().map_err(|e| ExtractError::classify(e, ExtractError::WriterIo))
+    }
+}
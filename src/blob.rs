@@ -0,0 +1,167 @@
+//! Blob columns fetched by a separate worker pool
+//!
+//! Every blob costs the driver extra round trips, so a scan that reads blobs
+//! inline spends most of its time waiting on them. With blob workers the main
+//! scan selects only the scalar columns plus `RDB$DB_KEY`; each chunk's keys
+//! are then split into groups that workers look up on their own connections
+//! to the same [`Source`] (`WHERE RDB$DB_KEY IN (...)`), and the payloads are spliced back into the
+//! rows in table column order. DB_KEYs are physical record addresses, so like
+//! `ROWS` paging this assumes the table isn't modified during the extraction.
+
+use std::{collections::HashMap, sync::Arc, thread};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+
+use crate::extractor::TableMetadata;
+use crate::partition::PartitionSpec;
+use crate::source::{Row, Select, Source, SourceConnection, Value};
+
+/// Keys per lookup query; well under Firebird's IN-list limit.
+const KEYS_PER_QUERY: usize = 128;
+
+struct BlobJob {
+    select: Select,
+    reply: Sender<Result<Vec<Row>>>,
+}
+
+pub(crate) struct BlobFetcher {
+    jobs: Option<Sender<BlobJob>>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl BlobFetcher {
+    /// Start `workers` workers, each on a connection taken from `source` now:
+    /// taken lazily, partition fetchers could hold every connection while
+    /// waiting on lookups that wait for one.
+    pub(crate) fn new(workers: usize, source: Arc<dyn Source>) -> Result<Self> {
+        let workers = workers.max(1);
+        let connections = (0..workers)
+            .map(|_| source.connect())
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to reserve connections for the blob workers")?;
+        let (jobs, queue): (Sender<BlobJob>, Receiver<BlobJob>) = bounded(workers * 2);
+        let handles = connections
+            .into_iter()
+            .map(|conn| {
+                let queue = queue.clone();
+                let source = Arc::clone(&source);
+                thread::spawn(move || {
+                    let mut conn = Some(conn);
+                    for job in queue.iter() {
+                        let result = Self::lookup(source.as_ref(), &mut conn, &job.select);
+                        if result.is_err() {
+                            // Let the source probe or replace it before the next job
+                            conn = None;
+                        }
+                        let _ = job.reply.send(result);
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self { jobs: Some(jobs), handles })
+    }
+
+    fn lookup(
+        source: &dyn Source,
+        conn: &mut Option<Box<dyn SourceConnection>>,
+        select: &Select,
+    ) -> Result<Vec<Row>> {
+        if conn.is_none() {
+            *conn = Some(source.connect()?);
+        }
+        conn.as_mut().unwrap().fetch_all(select)
+    }
+
+    /// Turn rows of a [`TableMetadata::select`] with blobs apart (scalar
+    /// columns followed by `RDB$DB_KEY`) into full rows in table column order.
+    pub(crate) fn complete_rows(&self, meta: &TableMetadata, rows: Vec<Row>) -> Result<Vec<Row>> {
+        let keys: Vec<Vec<u8>> = rows.iter().map(db_key).collect::<Result<_>>()?;
+
+        let blob_columns: Vec<String> = meta.columns.iter().filter(|c| c.is_blob()).map(|c| c.name.clone()).collect();
+
+        let jobs = self.jobs.as_ref().expect("blob fetcher shut down");
+        let (reply_tx, reply_rx) = unbounded();
+        let mut pending = 0;
+        for group in keys.chunks(KEYS_PER_QUERY) {
+            let literals: Vec<String> = group.iter().map(|k| hex_literal(k)).collect();
+            // The key comes last, as in the scan's rows
+            let select = Select {
+                table: meta.table_name.clone(),
+                columns: blob_columns.clone(),
+                db_key: true,
+                partition: PartitionSpec::predicate(format!("RDB$DB_KEY IN ({})", literals.join(", "))),
+                order_by: Vec::new(),
+            };
+            jobs.send(BlobJob { select, reply: reply_tx.clone() })
+                .map_err(|_| anyhow::anyhow!("blob workers exited"))?;
+            pending += 1;
+        }
+
+        let mut blobs: HashMap<Vec<u8>, Vec<Value>> = HashMap::with_capacity(keys.len());
+        let mut first_error = None;
+        for _ in 0..pending {
+            match reply_rx.recv() {
+                Ok(Ok(found)) => {
+                    for mut row in found {
+                        let key = db_key(&row)?;
+                        row.values.pop();
+                        blobs.insert(key, row.values);
+                    }
+                }
+                Ok(Err(e)) => {
+                    first_error.get_or_insert(e);
+                }
+                Err(_) => break,
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e.context(format!("Blob lookup on {} failed", meta.table_name)));
+        }
+
+        rows.into_iter()
+            .zip(keys)
+            .map(|(row, key)| {
+                let mut blob_values = blobs
+                    .remove(&key)
+                    .with_context(|| format!("Row {} of {} vanished before its blobs were read", hex_literal(&key), meta.table_name))?
+                    .into_iter();
+                let mut scalar_values = row.values.into_iter();
+                let values = meta
+                    .columns
+                    .iter()
+                    .map(|c| {
+                        let value = if c.is_blob() { blob_values.next() } else { scalar_values.next() };
+                        value.unwrap_or(Value::Null)
+                    })
+                    .collect();
+                Ok(Row::new(values))
+            })
+            .collect()
+    }
+}
+
+impl Drop for BlobFetcher {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// `RDB$DB_KEY` is CHAR(8) OCTETS; the driver may surface it as binary or text.
+fn db_key(row: &Row) -> Result<Vec<u8>> {
+    match row.values.last() {
+        Some(Value::Binary(bytes)) => Ok(bytes.clone()),
+        Some(Value::Text(text)) => Ok(text.as_bytes().to_vec()),
+        _ => anyhow::bail!("row without RDB$DB_KEY"),
+    }
+}
+
+/// `x'0000008A00000001'`
+fn hex_literal(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("x'{}'", hex)
+}
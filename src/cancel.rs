@@ -0,0 +1,53 @@
+//! Cooperative cancellation of running extractions
+//!
+//! Fetch loops, converters and writers check the extractor's token between
+//! rows and batches. Once it's cancelled they stop, writers are closed, temp
+//! and partial output files are removed and the extraction fails with
+//! [`ExtractError::Cancelled`].
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::Result;
+
+use crate::error::ExtractError;
+
+/// A shared stop flag; clones cancel together.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every extraction holding this token to stop as soon as it can.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err` tagged [`ExtractError::Cancelled`] once cancelled.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+}
+
+/// For callers that already have a flag, e.g. one set by a signal handler.
+impl From<Arc<AtomicBool>> for CancellationToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
+
+pub(crate) fn cancelled() -> anyhow::Error {
+    ExtractError::tag(ExtractError::Cancelled, anyhow::anyhow!("Extraction cancelled"))
+}
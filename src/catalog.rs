@@ -0,0 +1,98 @@
+//! Table definitions for Hive-style catalogs
+//!
+//! A [`CatalogTable`] describes an extracted table as an external Parquet
+//! table: its columns in Hive types and the location query engines read its
+//! files from. [`CatalogTable::ddl`] renders it for a Hive Metastore (run it
+//! with beeline or Trino's Hive connector); with the `glue` feature,
+//! [`GlueCatalog`](crate::GlueCatalog) registers it in AWS Glue for Athena.
+//!
+//! Output isn't hive-partitioned, so tables are defined without partition
+//! columns. The location is where the table's files are uploaded to, one
+//! prefix per table: Athena and Trino read every file under it.
+
+use anyhow::Result;
+use arrow::datatypes::{DataType, Schema, TimeUnit};
+
+use crate::extractor::output_stem;
+
+/// An external Parquet table to create or replace in a catalog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogTable {
+    pub database: String,
+    /// Lowercase, as Hive and Glue store it
+    pub name: String,
+    /// Column names and Hive types, in file order
+    pub columns: Vec<(String, String)>,
+    /// Directory URI of the table's files, ending in `/`
+    pub location: String,
+}
+
+impl CatalogTable {
+    /// The definition of `table_name` with output `schema`. `location` may
+    /// contain `{table}`, replaced by the table's file name stem
+    /// (`s3://lake/firebird/{table}/`).
+    pub fn new(database: &str, table_name: &str, schema: &Schema, location: &str) -> Result<Self> {
+        let name = catalog_name(table_name);
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                let hive_type = hive_type(field.data_type()).ok_or_else(|| {
+                    anyhow::anyhow!("{}.{} is {}, which has no Hive type", table_name, field.name(), field.data_type())
+                })?;
+                Ok((field.name().to_lowercase(), hive_type))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut location = location.replace("{table}", &output_stem(table_name));
+        if !location.ends_with('/') {
+            location.push('/');
+        }
+        Ok(Self { database: database.to_string(), name, columns, location })
+    }
+
+    /// HiveQL replacing the table's definition. Dropping an external table
+    /// leaves its files alone.
+    pub fn ddl(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|(name, ty)| format!("  `{}` {}", name, ty)).collect();
+        format!(
+            "DROP TABLE IF EXISTS `{db}`.`{name}`;\nCREATE EXTERNAL TABLE `{db}`.`{name}` (\n{columns}\n)\n\
+             STORED AS PARQUET\nLOCATION '{location}';\n",
+            db = self.database,
+            name = self.name,
+            columns = columns.join(",\n"),
+            location = self.location.replace('\'', "\\'"),
+        )
+    }
+}
+
+/// Hive and Glue allow lowercase letters, digits and underscores.
+fn catalog_name(table_name: &str) -> String {
+    output_stem(table_name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// The Hive type a Parquet column of `data_type` is read as; `None` for
+/// types Hive can't read, such as times of day.
+pub(crate) fn hive_type(data_type: &DataType) -> Option<String> {
+    Some(match data_type {
+        DataType::Boolean => "boolean".to_string(),
+        DataType::Int8 => "tinyint".to_string(),
+        DataType::Int16 | DataType::UInt8 => "smallint".to_string(),
+        DataType::Int32 | DataType::UInt16 => "int".to_string(),
+        DataType::Int64 | DataType::UInt32 => "bigint".to_string(),
+        DataType::Float32 => "float".to_string(),
+        DataType::Float64 => "double".to_string(),
+        DataType::Decimal128(precision, scale) if *precision <= 38 => format!("decimal({},{})", precision, scale),
+        DataType::Utf8 | DataType::LargeUtf8 => "string".to_string(),
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => "binary".to_string(),
+        DataType::Date32 => "date".to_string(),
+        DataType::Timestamp(TimeUnit::Millisecond | TimeUnit::Microsecond | TimeUnit::Nanosecond, _) => {
+            "timestamp".to_string()
+        }
+        DataType::Dictionary(_, values) => return hive_type(values),
+        DataType::List(item) | DataType::LargeList(item) => format!("array<{}>", hive_type(item.data_type())?),
+        _ => return None,
+    })
+}
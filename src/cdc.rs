@@ -0,0 +1,332 @@
+//! Trigger-based change capture, for Firebird versions without native CDC
+//!
+//! [`Extractor::cdc_install`] creates, for one table:
+//!
+//! - a changelog table `CDC$<TABLE>` of `CDC_SEQ` (from the shared sequence
+//!   `CDC$SEQ`), `CDC_OP` (`I`, `U` or `D`), `CDC_AT` and the primary key
+//! - an `AFTER INSERT OR UPDATE OR DELETE` trigger `CDC$TR_<TABLE>` logging
+//!   every change; an update of the key logs a delete of the old key too
+//! - a view `CDC$V_<TABLE>` of the changelog joined to the table's current
+//!   rows, null but for the key where the row is gone
+//!
+//! [`Extractor::cdc_extract`] extracts that view in append mode, ordered by
+//! `CDC_SEQ`, then deletes the changelog rows it wrote out. Values are the
+//! row's state when drained, not at each change, which is what replaying
+//! the log as upserts and deletes needs. Rows logged by transactions still
+//! running are left for the next drain; a drain interrupted before its
+//! deletes repeats rows, so consumers should skip `CDC_SEQ`s already seen.
+
+use std::{collections::HashMap, fs::File, path::Path};
+
+use anyhow::{Context, Result};
+use arrow::{array::AsArray, datatypes::Int64Type};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask};
+use rsfbclient::{Execute, Queryable, SimpleConnection};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::config::OutputMode;
+use crate::error::ExtractError;
+use crate::extractor::{resolve_table, ConnectionPool, ExtractionStats, Extractor};
+use crate::partition::quote_ident;
+
+/// Sequence numbering the changes of every captured table.
+const SEQUENCE: &str = "CDC$SEQ";
+
+/// Firebird 3's limit on object names, in bytes.
+const MAX_NAME_LEN: usize = 31;
+
+/// Objects capturing one table's changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CdcObjects {
+    pub table: String,
+    pub changelog: String,
+    pub trigger: String,
+    pub view: String,
+}
+
+impl CdcObjects {
+    /// Names for `table`, shortened with a hash where they'd be too long.
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            changelog: object_name("CDC$", table),
+            trigger: object_name("CDC$TR_", table),
+            view: object_name("CDC$V_", table),
+        }
+    }
+}
+
+fn object_name(prefix: &str, table: &str) -> String {
+    let name = format!("{}{}", prefix, table);
+    if name.len() <= MAX_NAME_LEN {
+        return name;
+    }
+    let hash: String = Sha256::digest(table.as_bytes())[..4].iter().map(|b| format!("{:02X}", b)).collect();
+    let mut short: String = prefix.to_string();
+    for c in table.chars() {
+        if short.len() + c.len_utf8() + 1 + hash.len() > MAX_NAME_LEN {
+            break;
+        }
+        short.push(c);
+    }
+    format!("{}_{}", short, hash)
+}
+
+impl Extractor {
+    /// Create or update the changelog, trigger and view capturing
+    /// `table_name`. Safe to re-run, e.g. after columns were added; the
+    /// changelog keeps its rows.
+    pub fn cdc_install(&self, table_name: &str) -> Result<CdcObjects, ExtractError> {
+        self.install_cdc(table_name).map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
+
+    /// Drop the trigger, view and changelog of `table_name`, changes not yet
+    /// drained included. The shared sequence stays.
+    pub fn cdc_uninstall(&self, table_name: &str) -> Result<(), ExtractError> {
+        self.uninstall_cdc(table_name).map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
+
+    /// Write the changes logged for `table_name` since the last drain to a
+    /// new file and remove them from the changelog; `None` if there were
+    /// none. Runs in append mode whatever the configured output mode.
+    pub fn cdc_extract(&self, table_name: &str) -> Result<Option<ExtractionStats>, ExtractError> {
+        let (objects, mut conn) = self
+            .cdc_objects(table_name)
+            .and_then(|objects| Ok((objects, ConnectionPool::connect_read_write(&self.config)?)))
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))?;
+        let pending: Vec<(i64,)> = conn
+            .query(&format!("SELECT FIRST 1 CDC_SEQ FROM {}", quote_ident(&objects.changelog)), ())
+            .map_err(|e| ExtractError::Metadata(e.into()))?;
+        if pending.is_empty() {
+            info!("{}: no changes since the last drain", objects.table);
+            return Ok(None);
+        }
+
+        let mut extractor = self.clone();
+        extractor.config.output_mode = OutputMode::Append;
+        // One scan ordered by every column, so by CDC_SEQ first
+        extractor.config.deterministic = true;
+        let stats = extractor.extract_table(&objects.view)?;
+        if let Some(path) = stats.output_files.first() {
+            let drained = drain(&mut conn, &objects.changelog, path)
+                .map_err(|e| ExtractError::classify(e, ExtractError::WriterIo))?;
+            info!("{}: drained {} changes into {}", objects.table, drained, path.display());
+        }
+        Ok(Some(stats))
+    }
+
+    /// The CDC objects of `table_name`, which must exist.
+    fn cdc_objects(&self, table_name: &str) -> Result<CdcObjects> {
+        let mut source = self.source.connect()?;
+        let table = resolve_table(&mut *source, table_name)?;
+        let objects = CdcObjects::new(&table);
+        if !source.has_table(&objects.view)? {
+            let e = anyhow::anyhow!("Change capture isn't installed for {}; run `cdc install` first", table);
+            return Err(ExtractError::tag(ExtractError::Validation, e));
+        }
+        Ok(objects)
+    }
+
+    fn install_cdc(&self, table_name: &str) -> Result<CdcObjects> {
+        let (table, fields, pk) = {
+            let mut source = self.source.connect()?;
+            let table = resolve_table(&mut *source, table_name)?;
+            let fields = source.fields(&table)?;
+            let pk = source.primary_key(&table)?;
+            (table, fields, pk)
+        };
+        if pk.is_empty() {
+            let e = anyhow::anyhow!("{} has no primary key to log changes by", table);
+            return Err(ExtractError::tag(ExtractError::Validation, e));
+        }
+        let objects = CdcObjects::new(&table);
+        let mut conn = ConnectionPool::connect_read_write(&self.config)?;
+        let types = column_types(&mut conn, &table)?;
+
+        let exists = |conn: &mut SimpleConnection, sql: &str, name: &str| -> Result<bool> {
+            let counts: Vec<(i64,)> = conn.query(sql, (name.to_string(),))?;
+            Ok(counts.first().is_some_and(|c| c.0 > 0))
+        };
+        if !exists(&mut conn, "SELECT COUNT(*) FROM rdb$generators WHERE rdb$generator_name = ?", SEQUENCE)? {
+            conn.execute(&format!("CREATE SEQUENCE {}", quote_ident(SEQUENCE)), ())
+                .context("Failed to create the change sequence")?;
+        }
+
+        let keys: Vec<String> = pk.iter().map(|c| quote_ident(c)).collect();
+        if !exists(&mut conn, "SELECT COUNT(*) FROM rdb$relations WHERE rdb$relation_name = ?", &objects.changelog)? {
+            let mut columns = vec![
+                "CDC_SEQ BIGINT NOT NULL PRIMARY KEY".to_string(),
+                "CDC_OP CHAR(1) NOT NULL".to_string(),
+                "CDC_AT TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL".to_string(),
+            ];
+            for (column, quoted) in pk.iter().zip(&keys) {
+                let ddl_type = types.get(column).with_context(|| {
+                    format!("{}.{} has a type change capture can't copy", table, column)
+                })?;
+                columns.push(format!("{} {}", quoted, ddl_type));
+            }
+            let sql = format!("CREATE TABLE {} ({})", quote_ident(&objects.changelog), columns.join(", "));
+            conn.execute(&sql, ()).with_context(|| format!("Failed to create changelog {}", objects.changelog))?;
+            info!("Created changelog {}", objects.changelog);
+        }
+
+        let log = |op: &str, row: &str| {
+            let values: Vec<String> = keys.iter().map(|k| format!("{}.{}", row, k)).collect();
+            format!(
+                "INSERT INTO {} (CDC_SEQ, CDC_OP, {}) VALUES (NEXT VALUE FOR {}, {}, {});",
+                quote_ident(&objects.changelog),
+                keys.join(", "),
+                quote_ident(SEQUENCE),
+                op,
+                values.join(", ")
+            )
+        };
+        let key_changed: Vec<String> = keys.iter().map(|k| format!("OLD.{0} IS DISTINCT FROM NEW.{0}", k)).collect();
+        let trigger = [
+            format!(
+                "CREATE OR ALTER TRIGGER {} FOR {} ACTIVE AFTER INSERT OR UPDATE OR DELETE POSITION 32000 AS",
+                quote_ident(&objects.trigger),
+                quote_ident(&table)
+            ),
+            "BEGIN".to_string(),
+            format!("  IF (DELETING OR (UPDATING AND ({}))) THEN", key_changed.join(" OR ")),
+            format!("    {}", log("'D'", "OLD")),
+            "  IF (NOT DELETING) THEN".to_string(),
+            format!("    {}", log("IIF(INSERTING, 'I', 'U')", "NEW")),
+            "END".to_string(),
+        ]
+        .join("\n");
+        conn.execute(&trigger, ()).with_context(|| format!("Failed to create trigger {}", objects.trigger))?;
+
+        let mut view_columns = vec!["CDC_SEQ".to_string(), "CDC_OP".to_string(), "CDC_AT".to_string()];
+        let mut selected = vec!["c.CDC_SEQ".to_string(), "c.CDC_OP".to_string(), "c.CDC_AT".to_string()];
+        for field in &fields {
+            let quoted = quote_ident(&field.column);
+            let side = if pk.contains(&field.column) { "c" } else { "t" };
+            selected.push(format!("{}.{}", side, quoted));
+            view_columns.push(quoted);
+        }
+        let join: Vec<String> = keys.iter().map(|k| format!("t.{0} = c.{0}", k)).collect();
+        let view = format!(
+            "CREATE OR ALTER VIEW {} ({}) AS SELECT {} FROM {} c LEFT JOIN {} t ON {}",
+            quote_ident(&objects.view),
+            view_columns.join(", "),
+            selected.join(", "),
+            quote_ident(&objects.changelog),
+            quote_ident(&table),
+            join.join(" AND ")
+        );
+        conn.execute(&view, ()).with_context(|| format!("Failed to create view {}", objects.view))?;
+        info!("Capturing changes of {} with {} into {}", table, objects.trigger, objects.changelog);
+        Ok(objects)
+    }
+
+    fn uninstall_cdc(&self, table_name: &str) -> Result<()> {
+        let objects = self.cdc_objects(table_name)?;
+        let mut conn = ConnectionPool::connect_read_write(&self.config)?;
+        for sql in [
+            format!("DROP TRIGGER {}", quote_ident(&objects.trigger)),
+            format!("DROP VIEW {}", quote_ident(&objects.view)),
+            format!("DROP TABLE {}", quote_ident(&objects.changelog)),
+        ] {
+            conn.execute(&sql, ()).with_context(|| format!("`{}` failed", sql))?;
+        }
+        info!("Removed change capture of {}", objects.table);
+        Ok(())
+    }
+}
+
+/// Delete the changelog rows whose `CDC_SEQ` is in `path`, a run of
+/// consecutive numbers per statement. Returns how many there were.
+fn drain(conn: &mut SimpleConnection, changelog: &str, path: &Path) -> Result<usize> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+    let index = builder.schema().index_of("CDC_SEQ")?;
+    let mask = ProjectionMask::roots(builder.parquet_schema(), [index]);
+    let mut seqs = Vec::new();
+    for batch in builder.with_projection(mask).build()? {
+        seqs.extend(batch?.column(0).as_primitive::<Int64Type>().iter().flatten());
+    }
+    seqs.sort_unstable();
+
+    let sql = format!("DELETE FROM {} WHERE CDC_SEQ BETWEEN ? AND ?", quote_ident(changelog));
+    let mut runs = 0;
+    let mut start = 0;
+    for i in 1..=seqs.len() {
+        if i == seqs.len() || seqs[i] != seqs[i - 1] + 1 {
+            conn.execute(&sql, (seqs[start], seqs[i - 1]))?;
+            runs += 1;
+            start = i;
+        }
+    }
+    debug!("Deleted {} changes from {} in {} statement(s)", seqs.len(), changelog, runs);
+    Ok(seqs.len())
+}
+
+/// DDL types of `table`'s columns, for those whose type can be copied.
+pub(crate) fn column_types(conn: &mut impl Queryable, table: &str) -> Result<HashMap<String, String>> {
+    let sql = r#"
+        SELECT TRIM(rf.rdb$field_name), f.rdb$field_type, COALESCE(f.rdb$field_sub_type, 0),
+            COALESCE(f.rdb$field_scale, 0), COALESCE(f.rdb$field_precision, 0),
+            COALESCE(f.rdb$character_length, 0), TRIM(cs.rdb$character_set_name)
+        FROM rdb$relation_fields rf
+        JOIN rdb$fields f ON f.rdb$field_name = rf.rdb$field_source
+        LEFT JOIN rdb$character_sets cs ON cs.rdb$character_set_id = f.rdb$character_set_id
+        WHERE rf.rdb$relation_name = ?
+    "#;
+    let rows: Vec<(String, i16, i16, i16, i16, i16, Option<String>)> = conn.query(sql, (table.to_string(),))?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(column, field_type, sub_type, scale, precision, length, charset)| {
+            let ddl_type = ddl_type(field_type, sub_type, scale, precision, length, charset.as_deref())?;
+            Some((column, ddl_type))
+        })
+        .collect())
+}
+
+/// The SQL type of a column from its `rdb$fields` entry; `None` for arrays
+/// and types this doesn't know.
+pub(crate) fn ddl_type(
+    field_type: i16,
+    sub_type: i16,
+    scale: i16,
+    precision: i16,
+    length: i16,
+    charset: Option<&str>,
+) -> Option<String> {
+    let text = |name: &str| match charset {
+        Some(charset) => format!("{}({}) CHARACTER SET {}", name, length, charset),
+        None => format!("{}({})", name, length),
+    };
+    let exact = |name: &str, max_precision: i16| {
+        if scale == 0 && sub_type == 0 {
+            return name.to_string();
+        }
+        let kind = if sub_type == 2 { "DECIMAL" } else { "NUMERIC" };
+        let precision = if precision > 0 { precision } else { max_precision };
+        format!("{}({}, {})", kind, precision, -scale)
+    };
+    Some(match field_type {
+        7 => exact("SMALLINT", 4),
+        8 => exact("INTEGER", 9),
+        16 => exact("BIGINT", 18),
+        26 => exact("INT128", 38),
+        10 => "FLOAT".to_string(),
+        27 => "DOUBLE PRECISION".to_string(),
+        12 => "DATE".to_string(),
+        13 => "TIME".to_string(),
+        35 => "TIMESTAMP".to_string(),
+        28 => "TIME WITH TIME ZONE".to_string(),
+        29 => "TIMESTAMP WITH TIME ZONE".to_string(),
+        23 => "BOOLEAN".to_string(),
+        14 => text("CHAR"),
+        37 => text("VARCHAR"),
+        261 if sub_type == 1 => match charset {
+            Some(charset) => format!("BLOB SUB_TYPE TEXT CHARACTER SET {}", charset),
+            None => "BLOB SUB_TYPE TEXT".to_string(),
+        },
+        261 if sub_type == 0 => "BLOB SUB_TYPE BINARY".to_string(),
+        261 => format!("BLOB SUB_TYPE {}", sub_type),
+        _ => return None,
+    })
+}
@@ -0,0 +1,119 @@
+//! `--config` files: TOML (or YAML) holding CLI settings plus per-table overrides
+//!
+//! Top-level keys are CLI flag names (`out_dir` or `out-dir`); they become
+//! arguments placed before the real command line, so anything given on the
+//! command line wins. Each subcommand only receives the flags it takes, so one
+//! file can serve `extract`, `validate` and `schema` alike. `[tables.<NAME>]` sections hold [`TableOverrides`], and
+//! unless a top-level `table` list says otherwise, every section is extracted.
+//!
+//! ```toml
+//! database = "fbserver:/data/erp.fdb"
+//! out_dir = "/exports"
+//! parallelism = 32
+//!
+//! [tables.ORDERS]
+//! parallelism = 16
+//! where = "ORDER_DATE >= '2020-01-01'"
+//!
+//! [tables.CUSTOMERS]
+//! columns = ["ID", "NAME", "PHONE"]
+//! types = { PHONE = "utf8" }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::read_to_string,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::TableOverrides;
+
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    /// Flag names and values from the top-level keys, except `table`; `None`
+    /// for a bare switch
+    pub settings: Vec<(String, Option<String>)>,
+    /// Tables to extract: the top-level `table` list, else every section
+    pub table_names: Vec<String>,
+    /// Per-table overrides keyed by upper-case table name
+    pub tables: HashMap<String, TableOverrides>,
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    #[serde(default)]
+    tables: BTreeMap<String, TableOverrides>,
+    #[serde(flatten)]
+    settings: BTreeMap<String, Value>,
+}
+
+impl ConfigFile {
+    /// Load `path`, as YAML for `.yaml` / `.yml` and TOML otherwise.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = read_to_string(path).with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let raw: RawConfig = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text)
+                .with_context(|| format!("Invalid YAML config {}", path.display()))?,
+            _ => toml::from_str(&text).with_context(|| format!("Invalid TOML config {}", path.display()))?,
+        };
+
+        let mut config = ConfigFile::default();
+        for (key, value) in raw.settings {
+            let flag = key.replace('_', "-");
+            match flag.as_str() {
+                "config" => anyhow::bail!("Config files can't include other config files ({})", path.display()),
+                "table" => config.table_names = string_values(&key, value)?,
+                _ => push_setting(&mut config.settings, &flag, value)?,
+            }
+        }
+
+        if config.table_names.is_empty() {
+            config.table_names = raw.tables.keys().cloned().collect();
+        }
+        config.tables = raw.tables.into_iter().map(|(name, o)| (name.to_uppercase(), o)).collect();
+        Ok(config)
+    }
+
+    /// `--flag value` arguments for the settings whose flag `accepts` allows.
+    pub fn args(&self, accepts: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, value) in self.settings.iter().filter(|(flag, _)| accepts(flag)) {
+            args.push(format!("--{}", flag));
+            args.extend(value.clone());
+        }
+        args
+    }
+}
+
+fn push_setting(settings: &mut Vec<(String, Option<String>)>, flag: &str, value: Value) -> Result<()> {
+    match value {
+        Value::Null | Value::Bool(false) => {}
+        Value::Bool(true) => settings.push((flag.to_string(), None)),
+        Value::Array(_) => {
+            let values = string_values(flag, value)?;
+            settings.push((flag.to_string(), Some(values.join(","))));
+        }
+        Value::Object(_) => anyhow::bail!("Config key '{}' must be a plain value, not a table", flag),
+        scalar => settings.push((flag.to_string(), Some(scalar_string(&scalar)))),
+    }
+    Ok(())
+}
+
+fn string_values(key: &str, value: Value) -> Result<Vec<String>> {
+    match value {
+        Value::Array(items) => Ok(items.iter().map(scalar_string).collect()),
+        Value::String(s) => Ok(s.split(',').map(|t| t.trim().to_string()).collect()),
+        _ => anyhow::bail!("Config key '{}' must be a string or a list", key),
+    }
+}
+
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
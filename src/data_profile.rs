@@ -0,0 +1,322 @@
+//! Per-column statistics over a sample of a table, for designing downstream
+//! schemas before the first extraction
+//!
+//! [`Extractor::profile_table`] reads the table's first rows through
+//! [`Extractor::batch_stream`], so type overrides, masks and transforms apply
+//! just as in an extraction, and reports per column: null rate, an estimate
+//! of distinct values (HyperLogLog, within about 2%), minimum and maximum,
+//! the most frequent values and the average length of text and binary
+//! values. The sample is the start of an unordered scan, not a random one.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::Instant,
+};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    datatypes::{DataType, Field, Float64Type, Int64Type},
+    record_batch::RecordBatchReader,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use serde::Serialize;
+use tracing::info;
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+use crate::stream::stream_error;
+
+/// Most frequent values reported per column.
+const TOP_VALUES: usize = 10;
+
+/// Distinct values counted per column for the top values; later new values
+/// are only counted by the distinct estimate.
+const MAX_TRACKED_VALUES: usize = 10_000;
+
+/// Characters of a top value shown.
+const MAX_VALUE_CHARS: usize = 100;
+
+/// HyperLogLog registers: 2^12, for a standard error of about 1.6%.
+const SKETCH_BITS: u32 = 12;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TableProfile {
+    pub table: String,
+    /// Rows the statistics cover
+    pub sampled_rows: usize,
+    /// The sample size asked for; 0 means the whole table
+    pub sample_limit: usize,
+    pub duration_secs: f64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// Output Arrow type
+    pub data_type: String,
+    pub nulls: usize,
+    /// Share of sampled rows that are null, 0-1
+    pub null_rate: f64,
+    pub distinct_estimate: u64,
+    /// Unset for binary columns and columns without a non-null value
+    pub min: Option<ProfileValue>,
+    pub max: Option<ProfileValue>,
+    /// Characters for text, bytes for binary
+    pub avg_length: Option<f64>,
+    /// Most frequent values, most frequent first
+    pub top_values: Vec<TopValue>,
+}
+
+/// A minimum or maximum.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(untagged)]
+pub enum ProfileValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: usize,
+}
+
+impl Extractor {
+    /// Profile the first `sample_rows` rows of `table_name` (0 for all).
+    pub fn profile_table(&self, table_name: &str, sample_rows: usize) -> Result<TableProfile, ExtractError> {
+        let start = Instant::now();
+        let stream = self.batch_stream(table_name)?;
+        let mut columns: Vec<ColumnStats> = stream.schema().fields().iter().map(|f| ColumnStats::new(f)).collect();
+        let mut rows = 0;
+        for batch in stream {
+            let batch = batch.map_err(stream_error)?;
+            let take = match sample_rows {
+                0 => batch.num_rows(),
+                limit => batch.num_rows().min(limit - rows),
+            };
+            for (stats, array) in columns.iter_mut().zip(batch.columns()) {
+                stats.observe(&array.slice(0, take)).map_err(ExtractError::TypeMapping)?;
+            }
+            rows += take;
+            if sample_rows > 0 && rows >= sample_rows {
+                // Dropping the stream ends the scan
+                break;
+            }
+        }
+        let duration_secs = start.elapsed().as_secs_f64();
+        info!("{}: profiled {} rows in {:.1}s", table_name, rows, duration_secs);
+        Ok(TableProfile {
+            table: table_name.to_string(),
+            sampled_rows: rows,
+            sample_limit: sample_rows,
+            duration_secs,
+            columns: columns.into_iter().map(|c| c.finish(rows)).collect(),
+        })
+    }
+}
+
+struct ColumnStats {
+    name: String,
+    data_type: DataType,
+    nulls: usize,
+    sketch: Sketch,
+    min: Option<ProfileValue>,
+    max: Option<ProfileValue>,
+    total_length: usize,
+    counts: HashMap<String, usize>,
+}
+
+impl ColumnStats {
+    fn new(field: &Field) -> Self {
+        Self {
+            name: field.name().clone(),
+            data_type: field.data_type().clone(),
+            nulls: 0,
+            sketch: Sketch::new(),
+            min: None,
+            max: None,
+            total_length: 0,
+            counts: HashMap::new(),
+        }
+    }
+
+    fn observe(&mut self, array: &ArrayRef) -> anyhow::Result<()> {
+        self.nulls += array.null_count();
+        match array.data_type() {
+            DataType::Int64 => {
+                for v in array.as_primitive::<Int64Type>().iter().flatten() {
+                    self.value(&v, Some(ProfileValue::Int(v)), v.to_string());
+                }
+            }
+            DataType::Float64 => {
+                for v in array.as_primitive::<Float64Type>().iter().flatten() {
+                    let ordered = (!v.is_nan()).then_some(ProfileValue::Float(v));
+                    self.value(&v.to_bits(), ordered, v.to_string());
+                }
+            }
+            DataType::Utf8 => {
+                for v in array.as_string::<i32>().iter().flatten() {
+                    self.total_length += v.chars().count();
+                    self.value(v, Some(ProfileValue::Text(v.to_string())), v.to_string());
+                }
+            }
+            DataType::Binary => {
+                for v in array.as_binary::<i32>().iter().flatten() {
+                    self.total_length += v.len();
+                    self.sketch.insert(v);
+                }
+            }
+            // Anything a transform produced: counted by its text form
+            _ => {
+                let formatter = ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default())?;
+                for i in (0..array.len()).filter(|&i| array.is_valid(i)) {
+                    let text = formatter.value(i).to_string();
+                    self.value(&text, None, text.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, hashed: &(impl Hash + ?Sized), ordered: Option<ProfileValue>, text: String) {
+        self.sketch.insert(hashed);
+        if let Some(value) = ordered {
+            if self.min.as_ref().map_or(true, |min| value < *min) {
+                self.min = Some(value.clone());
+            }
+            if self.max.as_ref().map_or(true, |max| value > *max) {
+                self.max = Some(value);
+            }
+        }
+        let tracked = self.counts.len();
+        match self.counts.get_mut(&text) {
+            Some(count) => *count += 1,
+            None if tracked < MAX_TRACKED_VALUES => {
+                self.counts.insert(text, 1);
+            }
+            None => {}
+        }
+    }
+
+    fn finish(self, rows: usize) -> ColumnProfile {
+        let values = rows - self.nulls;
+        let mut top: Vec<(String, usize)> = self.counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(TOP_VALUES);
+        let has_length = matches!(self.data_type, DataType::Utf8 | DataType::Binary);
+        ColumnProfile {
+            name: self.name,
+            data_type: self.data_type.to_string(),
+            nulls: self.nulls,
+            null_rate: if rows == 0 { 0.0 } else { self.nulls as f64 / rows as f64 },
+            distinct_estimate: self.sketch.estimate().min(values as u64),
+            min: self.min,
+            max: self.max,
+            avg_length: (has_length && values > 0).then(|| self.total_length as f64 / values as f64),
+            top_values: top.into_iter().map(|(value, count)| TopValue { value: shorten(value), count }).collect(),
+        }
+    }
+}
+
+fn shorten(value: String) -> String {
+    match value.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value,
+    }
+}
+
+/// A HyperLogLog distinct count sketch.
+struct Sketch {
+    registers: Vec<u8>,
+}
+
+impl Sketch {
+    fn new() -> Self {
+        Self { registers: vec![0; 1 << SKETCH_BITS] }
+    }
+
+    fn insert(&mut self, value: &(impl Hash + ?Sized)) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - SKETCH_BITS)) as usize;
+        // Position of the first 1 bit after the index bits; the guard bit caps it
+        let rank = ((hash << SKETCH_BITS) | (1 << (SKETCH_BITS - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|&&r| r == 0).count();
+        // Small cardinalities: linear counting is more accurate
+        let estimate = if raw <= 2.5 * m && empty > 0 { m * (m / empty as f64).ln() } else { raw };
+        estimate.round() as u64
+    }
+}
+
+impl TableProfile {
+    /// A standalone HTML page showing `profiles`, one section per table.
+    pub fn html(profiles: &[TableProfile]) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Data profile</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 2em; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }\n\
+             th { background: #f0f0f0; }\n\
+             td.num { text-align: right; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        for profile in profiles {
+            let sample = match profile.sample_limit {
+                0 => "all rows".to_string(),
+                limit => format!("sample of up to {} rows", limit),
+            };
+            html.push_str(&format!(
+                "<h2>{}</h2>\n<p>{} rows profiled ({})</p>\n<table>\n<tr><th>Column</th><th>Type</th>\
+                 <th>Null %</th><th>Distinct (est.)</th><th>Min</th><th>Max</th><th>Avg length</th>\
+                 <th>Top values</th></tr>\n",
+                escape(&profile.table),
+                profile.sampled_rows,
+                sample
+            ));
+            for column in &profile.columns {
+                let show = |value: &Option<ProfileValue>| match value {
+                    Some(ProfileValue::Int(v)) => v.to_string(),
+                    Some(ProfileValue::Float(v)) => v.to_string(),
+                    Some(ProfileValue::Text(v)) => escape(&shorten(v.clone())),
+                    None => String::new(),
+                };
+                let top: Vec<String> = column
+                    .top_values
+                    .iter()
+                    .map(|top| format!("{} ({})", escape(&top.value), top.count))
+                    .collect();
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td class=\"num\">{:.1}</td><td class=\"num\">{}</td><td>{}</td>\
+                     <td>{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+                    escape(&column.name),
+                    escape(&column.data_type),
+                    column.null_rate * 100.0,
+                    column.distinct_estimate,
+                    show(&column.min),
+                    show(&column.max),
+                    column.avg_length.map(|len| format!("{:.1}", len)).unwrap_or_default(),
+                    top.join("<br>")
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,315 @@
+//! Table definitions exported beside the data
+//!
+//! With `export_ddl` set, each extracted table gets `<table>.ddl.json` in the
+//! output directory: its columns (SQL type, domain, nullability, default or
+//! computed expression), primary key, unique and foreign key constraints,
+//! check constraints and its other indices, as the `RDB$` tables describe
+//! them. `ddl_sql` adds `<table>.ddl.sql`, Firebird DDL recreating the table,
+//! which other databases take as a starting point. Expressions (defaults,
+//! checks, computed columns and indices) are copied as Firebird stores their
+//! source, untranslated.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use rsfbclient::Queryable;
+use serde::Serialize;
+use tracing::info;
+
+use crate::cdc::ddl_type;
+use crate::error::ExtractError;
+use crate::extractor::{output_stem, resolve_table, Extractor};
+use crate::partition::quote_ident;
+
+/// A table's definition.
+#[derive(Clone, Debug, Serialize)]
+pub struct TableDdl {
+    pub table: String,
+    /// In table order
+    pub columns: Vec<DdlColumn>,
+    pub primary_key: Option<KeyConstraint>,
+    pub unique: Vec<KeyConstraint>,
+    pub foreign_keys: Vec<ForeignKey>,
+    pub checks: Vec<CheckConstraint>,
+    /// Indices no constraint created
+    pub indices: Vec<Index>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DdlColumn {
+    pub name: String,
+    /// Firebird SQL type, e.g. `VARCHAR(50) CHARACTER SET UTF8`
+    pub data_type: String,
+    /// User domain the column is based on; `None` for plain types
+    pub domain: Option<String>,
+    pub nullable: bool,
+    /// As stored, e.g. `DEFAULT 'NEW'`
+    pub default: Option<String>,
+    /// `COMPUTED BY` expression, e.g. `(PRICE * QTY)`
+    pub computed: Option<String>,
+}
+
+/// A primary key or unique constraint.
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyConstraint {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub references_table: String,
+    pub references_columns: Vec<String>,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckConstraint {
+    pub name: String,
+    /// As stored, e.g. `CHECK (QTY > 0)`
+    pub source: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Index {
+    pub name: String,
+    /// Empty for an expression index
+    pub columns: Vec<String>,
+    /// `COMPUTED BY` expression of an expression index
+    pub expression: Option<String>,
+    pub unique: bool,
+    pub descending: bool,
+    pub active: bool,
+}
+
+impl Extractor {
+    /// The definition of `table_name` from the `RDB$` tables.
+    pub fn table_ddl(&self, table_name: &str) -> Result<TableDdl, ExtractError> {
+        let load = || -> Result<TableDdl> {
+            let table = resolve_table(&mut *self.source.connect()?, table_name)?;
+            let mut conn = self.pool.acquire()?;
+            load_ddl(&mut *conn, &table)
+        };
+        load().map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
+
+    /// Write `<table>.ddl.json`, and with `ddl_sql` `<table>.ddl.sql`, to
+    /// the output directory; returns the files.
+    pub(crate) fn write_ddl(&self, table_name: &str) -> Result<Vec<PathBuf>> {
+        let ddl = self.table_ddl(table_name)?;
+        let stem = output_stem(table_name);
+        let mut files = vec![self.config.out_dir.join(format!("{}.ddl.json", stem))];
+        write(&files[0], serde_json::to_string_pretty(&ddl)? + "\n")?;
+        if self.config.ddl_sql {
+            files.push(self.config.out_dir.join(format!("{}.ddl.sql", stem)));
+            write(&files[1], ddl.sql())?;
+        }
+        info!("  DDL written to {}", files[0].display());
+        Ok(files)
+    }
+}
+
+fn write(path: &Path, contents: String) -> Result<()> {
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))
+        .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))
+}
+
+fn load_ddl(conn: &mut impl Queryable, table: &str) -> Result<TableDdl> {
+    let columns_sql = r#"
+        SELECT TRIM(rf.rdb$field_name), TRIM(rf.rdb$field_source), f.rdb$field_type,
+            COALESCE(f.rdb$field_sub_type, 0), COALESCE(f.rdb$field_scale, 0), COALESCE(f.rdb$field_precision, 0),
+            COALESCE(f.rdb$character_length, 0), TRIM(cs.rdb$character_set_name),
+            COALESCE(rf.rdb$null_flag, f.rdb$null_flag, 0), COALESCE(rf.rdb$default_source, f.rdb$default_source),
+            f.rdb$computed_source
+        FROM rdb$relation_fields rf
+        JOIN rdb$fields f ON f.rdb$field_name = rf.rdb$field_source
+        LEFT JOIN rdb$character_sets cs ON cs.rdb$character_set_id = f.rdb$character_set_id
+        WHERE rf.rdb$relation_name = ?
+        ORDER BY rf.rdb$field_position
+    "#;
+    type ColumnRow = (String, String, i16, i16, i16, i16, i16, Option<String>, i16, Option<String>, Option<String>);
+    let rows: Vec<ColumnRow> = conn.query(columns_sql, (table.to_string(),))?;
+    let columns = rows
+        .into_iter()
+        .map(|(name, source, field_type, sub_type, scale, precision, length, charset, not_null, default, computed)| {
+            DdlColumn {
+                data_type: ddl_type(field_type, sub_type, scale, precision, length, charset.as_deref())
+                    .unwrap_or_else(|| format!("UNKNOWN TYPE {}", field_type)),
+                // Columns declared with a plain type get a generated RDB$ domain
+                domain: (!source.starts_with("RDB$")).then_some(source),
+                nullable: not_null == 0,
+                default: default.map(|d| d.trim().to_string()),
+                computed: computed.map(|c| c.trim().to_string()),
+                name,
+            }
+        })
+        .collect();
+
+    let keys_sql = r#"
+        SELECT TRIM(rc.rdb$constraint_name), TRIM(rc.rdb$constraint_type), TRIM(s.rdb$field_name)
+        FROM rdb$relation_constraints rc
+        JOIN rdb$index_segments s ON s.rdb$index_name = rc.rdb$index_name
+        WHERE rc.rdb$relation_name = ? AND rc.rdb$constraint_type IN ('PRIMARY KEY', 'UNIQUE')
+        ORDER BY rc.rdb$constraint_name, s.rdb$field_position
+    "#;
+    let rows: Vec<(String, String, String)> = conn.query(keys_sql, (table.to_string(),))?;
+    let (mut primary_key, mut unique) = (None, Vec::new());
+    for ((name, kind), columns) in group(rows.into_iter().map(|(name, kind, column)| ((name, kind), column))) {
+        let constraint = KeyConstraint { name, columns };
+        match kind.as_str() {
+            "PRIMARY KEY" => primary_key = Some(constraint),
+            _ => unique.push(constraint),
+        }
+    }
+
+    let foreign_sql = r#"
+        SELECT TRIM(rc.rdb$constraint_name), TRIM(fk.rdb$update_rule), TRIM(fk.rdb$delete_rule),
+            TRIM(uq.rdb$relation_name), TRIM(s.rdb$field_name), TRIM(us.rdb$field_name)
+        FROM rdb$relation_constraints rc
+        JOIN rdb$ref_constraints fk ON fk.rdb$constraint_name = rc.rdb$constraint_name
+        JOIN rdb$relation_constraints uq ON uq.rdb$constraint_name = fk.rdb$const_name_uq
+        JOIN rdb$index_segments s ON s.rdb$index_name = rc.rdb$index_name
+        JOIN rdb$index_segments us
+            ON us.rdb$index_name = uq.rdb$index_name AND us.rdb$field_position = s.rdb$field_position
+        WHERE rc.rdb$relation_name = ? AND rc.rdb$constraint_type = 'FOREIGN KEY'
+        ORDER BY rc.rdb$constraint_name, s.rdb$field_position
+    "#;
+    type ForeignRow = (String, String, String, String, String, String);
+    let rows: Vec<ForeignRow> = conn.query(foreign_sql, (table.to_string(),))?;
+    let foreign_keys = group(rows.into_iter().map(|(name, on_update, on_delete, references, column, referenced)| {
+        ((name, on_update, on_delete, references), (column, referenced))
+    }))
+    .map(|((name, on_update, on_delete, references_table), pairs)| {
+        let (columns, references_columns) = pairs.into_iter().unzip();
+        ForeignKey { name, columns, references_table, references_columns, on_update, on_delete }
+    })
+    .collect();
+
+    // Each check constraint has a BEFORE INSERT (type 1) and a BEFORE UPDATE trigger
+    let checks_sql = r#"
+        SELECT TRIM(cc.rdb$constraint_name), t.rdb$trigger_source
+        FROM rdb$relation_constraints rc
+        JOIN rdb$check_constraints cc ON cc.rdb$constraint_name = rc.rdb$constraint_name
+        JOIN rdb$triggers t ON t.rdb$trigger_name = cc.rdb$trigger_name
+        WHERE rc.rdb$relation_name = ? AND rc.rdb$constraint_type = 'CHECK' AND t.rdb$trigger_type = 1
+        ORDER BY 1
+    "#;
+    let rows: Vec<(String, Option<String>)> = conn.query(checks_sql, (table.to_string(),))?;
+    let checks = rows
+        .into_iter()
+        .map(|(name, source)| CheckConstraint { name, source: source.unwrap_or_default().trim().to_string() })
+        .collect();
+
+    let indices_sql = r#"
+        SELECT TRIM(i.rdb$index_name), COALESCE(i.rdb$unique_flag, 0), COALESCE(i.rdb$index_type, 0),
+            COALESCE(i.rdb$index_inactive, 0), i.rdb$expression_source, TRIM(s.rdb$field_name)
+        FROM rdb$indices i
+        LEFT JOIN rdb$index_segments s ON s.rdb$index_name = i.rdb$index_name
+        WHERE i.rdb$relation_name = ?
+        AND NOT EXISTS (SELECT 1 FROM rdb$relation_constraints rc WHERE rc.rdb$index_name = i.rdb$index_name)
+        ORDER BY i.rdb$index_name, s.rdb$field_position
+    "#;
+    type IndexRow = (String, i16, i16, i16, Option<String>, Option<String>);
+    let rows: Vec<IndexRow> = conn.query(indices_sql, (table.to_string(),))?;
+    let indices = group(rows.into_iter().map(|(name, unique, index_type, inactive, expression, column)| {
+        ((name, unique, index_type, inactive, expression), column)
+    }))
+    .map(|((name, unique, index_type, inactive, expression), columns)| Index {
+        name,
+        columns: columns.into_iter().flatten().collect(),
+        expression: expression.map(|e| e.trim().to_string()),
+        unique: unique == 1,
+        descending: index_type == 1,
+        active: inactive == 0,
+    })
+    .collect();
+
+    Ok(TableDdl { table: table.to_string(), columns, primary_key, unique, foreign_keys, checks, indices })
+}
+
+/// Consecutive `(key, item)` pairs with equal keys gathered into
+/// `(key, items)`; the rows come ordered by key.
+fn group<K: PartialEq, V>(rows: impl Iterator<Item = (K, V)>) -> impl Iterator<Item = (K, Vec<V>)> {
+    let mut groups: Vec<(K, Vec<V>)> = Vec::new();
+    for (key, item) in rows {
+        match groups.last_mut() {
+            Some(last) if last.0 == key => last.1.push(item),
+            _ => groups.push((key, vec![item])),
+        }
+    }
+    groups.into_iter()
+}
+
+impl TableDdl {
+    /// Firebird DDL recreating the table: `CREATE TABLE` with its keys and
+    /// checks, then its foreign keys and indices.
+    pub fn sql(&self) -> String {
+        let table = quote_ident(&self.table);
+        let names = |columns: &[String]| columns.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", ");
+        let mut lines: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| match c.computed {
+                Some(ref computed) => format!("{} COMPUTED BY {}", quote_ident(&c.name), computed),
+                None => {
+                    let mut line = format!("{} {}", quote_ident(&c.name), c.data_type);
+                    if let Some(ref default) = c.default {
+                        line += &format!(" {}", default);
+                    }
+                    if !c.nullable {
+                        line += " NOT NULL";
+                    }
+                    line
+                }
+            })
+            .collect();
+        if let Some(ref pk) = self.primary_key {
+            lines.push(format!("CONSTRAINT {} PRIMARY KEY ({})", quote_ident(&pk.name), names(&pk.columns)));
+        }
+        for unique in &self.unique {
+            lines.push(format!("CONSTRAINT {} UNIQUE ({})", quote_ident(&unique.name), names(&unique.columns)));
+        }
+        for check in &self.checks {
+            lines.push(format!("CONSTRAINT {} {}", quote_ident(&check.name), check.source));
+        }
+        let mut sql = format!("CREATE TABLE {} (\n    {}\n);\n", table, lines.join(",\n    "));
+
+        for fk in &self.foreign_keys {
+            sql += &format!(
+                "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) ON UPDATE {} ON DELETE {};\n",
+                table,
+                quote_ident(&fk.name),
+                names(&fk.columns),
+                quote_ident(&fk.references_table),
+                names(&fk.references_columns),
+                fk.on_update,
+                fk.on_delete
+            );
+        }
+        for index in &self.indices {
+            let target = match index.expression {
+                Some(ref expression) => format!("COMPUTED BY {}", expression),
+                None => format!("({})", names(&index.columns)),
+            };
+            sql += &format!(
+                "CREATE {}{}INDEX {} ON {} {};\n",
+                if index.unique { "UNIQUE " } else { "" },
+                if index.descending { "DESCENDING " } else { "" },
+                quote_ident(&index.name),
+                table,
+                target
+            );
+            if !index.active {
+                sql += &format!("ALTER INDEX {} INACTIVE;\n", quote_ident(&index.name));
+            }
+        }
+        sql
+    }
+}
@@ -0,0 +1,90 @@
+//! Dictionary-encoded text columns
+//!
+//! A column converted as `Dictionary<Int32, Utf8>` stores each distinct value
+//! once per batch plus an integer per row, and gets Parquet dictionary pages,
+//! which suits status codes and other enum-like VARCHARs. Columns opt in with
+//! the `dictionary` type override; with `auto_dictionary` set, every CHAR and
+//! VARCHAR column whose sampled rows hold few distinct values does too.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use arrow::{array::ArrayRef, compute::cast, datatypes::DataType};
+use tracing::debug;
+
+use crate::extractor::ColumnMetadata;
+use crate::partition::PartitionSpec;
+use crate::source::{Select, SourceConnection, Value};
+
+/// Rows read to estimate cardinality.
+const SAMPLE_ROWS: usize = 10_000;
+/// Most distinct values an automatically encoded column may have in the sample.
+const MAX_DISTINCT: usize = 1_000;
+
+/// The type of a dictionary-encoded column.
+pub(crate) fn dictionary_type() -> DataType {
+    DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+}
+
+/// `array`, a converted `Utf8` column, dictionary-encoded.
+pub(crate) fn encode(array: ArrayRef) -> Result<ArrayRef> {
+    Ok(cast(&array, &dictionary_type())?)
+}
+
+/// Switch the CHAR and VARCHAR columns of `table` that repeat their values
+/// (at most [`MAX_DISTINCT`] distinct ones, and at most one per ten rows, in
+/// the first [`SAMPLE_ROWS`] rows `filter` picks) to dictionary encoding.
+/// Masked columns keep their type, since masks work on plain text.
+pub(crate) fn choose_columns(
+    conn: &mut dyn SourceConnection,
+    table: &str,
+    filter: Option<&str>,
+    columns: &mut [ColumnMetadata],
+) -> Result<()> {
+    let candidates: Vec<usize> = (0..columns.len())
+        .filter(|&i| {
+            let c = &columns[i];
+            // CHAR, VARCHAR
+            let text = matches!(c.field.field_type, 14 | 37);
+            text && c.data_type == DataType::Utf8 && !c.is_text_blob && c.mask.is_none()
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Ok(());
+    }
+    let select = Select {
+        table: table.to_string(),
+        columns: candidates.iter().map(|&i| columns[i].name.clone()).collect(),
+        db_key: false,
+        partition: match filter {
+            Some(filter) => PartitionSpec::predicate(filter.to_string()),
+            None => PartitionSpec::full(),
+        },
+        order_by: Vec::new(),
+    };
+    // `None` once a column has too many values to qualify
+    let mut distinct: Vec<Option<HashSet<String>>> = vec![Some(HashSet::new()); candidates.len()];
+    let mut sampled = 0;
+    conn.fetch(&select, &mut |row| {
+        sampled += 1;
+        for (values, value) in distinct.iter_mut().zip(&row.values) {
+            if let (Some(set), Value::Text(t)) = (values.as_mut(), value) {
+                set.insert(t.trim().to_string());
+                if set.len() > MAX_DISTINCT {
+                    *values = None;
+                }
+            }
+        }
+        Ok(sampled < SAMPLE_ROWS && distinct.iter().any(Option::is_some))
+    })?;
+
+    for (&i, values) in candidates.iter().zip(&distinct) {
+        let Some(values) = values else { continue };
+        if sampled > 0 && values.len() * 10 <= sampled {
+            let column = &mut columns[i];
+            debug!("  {}: {} distinct values in {} rows; dictionary-encoded", column.name, values.len(), sampled);
+            column.data_type = dictionary_type();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,349 @@
+//! Comparing a table's current rows with an earlier extraction, by primary key
+//!
+//! [`Extractor::diff_table`] loads the keys of a previous Parquet file with a
+//! hash of each row's values, then streams the table through
+//! [`Extractor::batch_stream`] and classifies every current row as inserted,
+//! updated or unchanged; keys left over were deleted. Only columns present
+//! in both with the same type are compared. The previous keys are held in
+//! memory, a few dozen bytes per row.
+//!
+//! With a changes file, inserted and updated rows are written with their
+//! current values and deleted rows with their previous ones (null where the
+//! previous file lacks a column), plus a `_change` column saying which.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::File,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{new_null_array, ArrayRef, BooleanArray, StringArray},
+    compute::filter_record_batch,
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::{RecordBatch, RecordBatchReader},
+    row::{RowConverter, Rows, SortField},
+};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+use crate::stream::stream_error;
+
+/// Name of the column saying how a row in the changes file changed.
+pub const CHANGE_COLUMN: &str = "_change";
+
+/// What changed between an earlier extraction and the table now.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DiffReport {
+    pub table: String,
+    /// The earlier extraction
+    pub against: PathBuf,
+    pub previous_rows: usize,
+    pub current_rows: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    /// Columns whose values were compared: those in both with the same type
+    pub compared_columns: Vec<String>,
+    /// Columns only the current table has, or with another type than before
+    pub new_columns: Vec<String>,
+    /// Columns only the earlier extraction has
+    pub dropped_columns: Vec<String>,
+    /// Previous rows whose key appeared more than once; only the last counts
+    pub duplicate_keys: usize,
+    pub changes_file: Option<PathBuf>,
+    pub duration_secs: f64,
+}
+
+impl DiffReport {
+    pub fn has_changes(&self) -> bool {
+        self.inserted + self.updated + self.deleted > 0
+    }
+}
+
+impl Extractor {
+    /// Compare `table_name` now with its earlier extraction `against`, and
+    /// write the changed rows to `changes_out` if given.
+    pub fn diff_table(
+        &self,
+        table_name: &str,
+        against: &Path,
+        changes_out: Option<&Path>,
+    ) -> Result<DiffReport, ExtractError> {
+        let start = Instant::now();
+        let pk = self.table_info(table_name)?.pk;
+        if pk.is_empty() {
+            let e = anyhow::anyhow!("{} has no primary key to match rows by", table_name);
+            return Err(ExtractError::Validation(e));
+        }
+        let stream = self.batch_stream(table_name)?;
+        let previous = File::open(against)
+            .with_context(|| format!("Failed to open {}", against.display()))
+            .and_then(|file| {
+                ParquetRecordBatchReaderBuilder::try_new(file)
+                    .with_context(|| format!("{} isn't a readable Parquet file", against.display()))
+            })
+            .map_err(ExtractError::Validation)?;
+        let layout = Layout::new(&stream.schema(), previous.schema(), &pk).map_err(ExtractError::Validation)?;
+
+        let mut report = DiffReport {
+            table: table_name.to_string(),
+            against: against.to_path_buf(),
+            compared_columns: layout.compared.iter().map(|&(current, _)| layout.name(current)).collect(),
+            new_columns: layout.new_columns(),
+            dropped_columns: layout.dropped.clone(),
+            ..DiffReport::default()
+        };
+
+        // Previous keys → value hash
+        let mut keys = layout.converters().map_err(ExtractError::TypeMapping)?;
+        let mut remaining: HashMap<Vec<u8>, u64> = HashMap::new();
+        let reader = previous.build().map_err(|e| ExtractError::Validation(e.into()))?;
+        for batch in reader {
+            let batch = batch
+                .with_context(|| format!("Failed to read {}", against.display()))
+                .map_err(ExtractError::Validation)?;
+            let (batch_keys, hashes) = keys.rows(&batch, Side::Previous).map_err(ExtractError::TypeMapping)?;
+            for (i, hash) in hashes.into_iter().enumerate() {
+                if remaining.insert(batch_keys.row(i).as_ref().to_vec(), hash).is_some() {
+                    report.duplicate_keys += 1;
+                }
+            }
+            report.previous_rows += batch.num_rows();
+        }
+        if report.duplicate_keys > 0 {
+            warn!("  ⚠ {} repeats {} primary key value(s)", against.display(), report.duplicate_keys);
+        }
+
+        let mut changes = match changes_out {
+            Some(path) => Some(ChangesWriter::new(self, path, &stream.schema()).map_err(ExtractError::WriterIo)?),
+            None => None,
+        };
+        for batch in stream {
+            let batch = batch.map_err(stream_error)?;
+            let (batch_keys, hashes) = keys.rows(&batch, Side::Current).map_err(ExtractError::TypeMapping)?;
+            let mut labels = Vec::with_capacity(batch.num_rows());
+            for (i, hash) in hashes.into_iter().enumerate() {
+                let label = match remaining.remove(batch_keys.row(i).as_ref()) {
+                    None => Some("insert"),
+                    Some(previous) if previous != hash => Some("update"),
+                    Some(_) => None,
+                };
+                match label {
+                    Some("insert") => report.inserted += 1,
+                    Some(_) => report.updated += 1,
+                    None => report.unchanged += 1,
+                }
+                labels.push(label);
+            }
+            report.current_rows += batch.num_rows();
+            if let Some(ref mut changes) = changes {
+                changes.write(&batch, labels).map_err(ExtractError::WriterIo)?;
+            }
+        }
+        report.deleted = remaining.len();
+
+        if let Some(mut changes) = changes {
+            if !remaining.is_empty() {
+                changes.write_deleted(against, &layout, &mut keys, &remaining).map_err(ExtractError::WriterIo)?;
+            }
+            report.changes_file = Some(changes.finish().map_err(ExtractError::WriterIo)?);
+        }
+        report.duration_secs = start.elapsed().as_secs_f64();
+        info!(
+            "{}: {} inserted, {} updated, {} deleted, {} unchanged since {}",
+            table_name,
+            report.inserted,
+            report.updated,
+            report.deleted,
+            report.unchanged,
+            against.display()
+        );
+        Ok(report)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Previous,
+    Current,
+}
+
+/// Where the key and compared columns are in each schema.
+struct Layout {
+    current: SchemaRef,
+    /// Key columns as (current index, previous index)
+    keys: Vec<(usize, usize)>,
+    /// Compared non-key columns as (current index, previous index)
+    compared: Vec<(usize, usize)>,
+    dropped: Vec<String>,
+}
+
+impl Layout {
+    fn new(current: &SchemaRef, previous: &SchemaRef, pk: &[String]) -> Result<Self> {
+        let mut keys = Vec::with_capacity(pk.len());
+        for column in pk {
+            let (Ok(c), Ok(p)) = (current.index_of(column), previous.index_of(column)) else {
+                anyhow::bail!("Key column {} is missing from the output or the earlier extraction", column);
+            };
+            if current.field(c).data_type() != previous.field(p).data_type() {
+                anyhow::bail!(
+                    "Key column {} was {} and is now {}",
+                    column,
+                    previous.field(p).data_type(),
+                    current.field(c).data_type()
+                );
+            }
+            keys.push((c, p));
+        }
+        let compared = current
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(c, _)| !keys.iter().any(|k| k.0 == *c))
+            .filter_map(|(c, field)| {
+                let p = previous.index_of(field.name()).ok()?;
+                (previous.field(p).data_type() == field.data_type()).then_some((c, p))
+            })
+            .collect();
+        let dropped = previous
+            .fields()
+            .iter()
+            .filter(|field| current.index_of(field.name()).is_err())
+            .map(|field| field.name().clone())
+            .collect();
+        Ok(Self { current: Arc::clone(current), keys, compared, dropped })
+    }
+
+    fn name(&self, current: usize) -> String {
+        self.current.field(current).name().clone()
+    }
+
+    fn new_columns(&self) -> Vec<String> {
+        (0..self.current.fields().len())
+            .filter(|c| !self.keys.iter().chain(&self.compared).any(|k| k.0 == *c))
+            .map(|c| self.name(c))
+            .collect()
+    }
+
+    fn converters(&self) -> Result<RowKeys> {
+        let fields = |columns: &[(usize, usize)]| -> Vec<SortField> {
+            columns.iter().map(|&(c, _)| SortField::new(self.current.field(c).data_type().clone())).collect()
+        };
+        Ok(RowKeys {
+            keys: self.keys.clone(),
+            compared: self.compared.clone(),
+            key_converter: RowConverter::new(fields(&self.keys))?,
+            value_converter: RowConverter::new(fields(&self.compared))?,
+        })
+    }
+}
+
+/// Encodes key columns as comparable bytes and hashes compared values.
+struct RowKeys {
+    keys: Vec<(usize, usize)>,
+    compared: Vec<(usize, usize)>,
+    key_converter: RowConverter,
+    value_converter: RowConverter,
+}
+
+impl RowKeys {
+    /// The keys of `batch` and a hash of each row's compared values.
+    fn rows(&mut self, batch: &RecordBatch, side: Side) -> Result<(Rows, Vec<u64>)> {
+        let pick = |columns: &[(usize, usize)]| -> Vec<ArrayRef> {
+            columns
+                .iter()
+                .map(|&(c, p)| Arc::clone(batch.column(if matches!(side, Side::Current) { c } else { p })))
+                .collect()
+        };
+        let keys = self.key_converter.convert_columns(&pick(&self.keys))?;
+        let hashes = if self.compared.is_empty() {
+            vec![0; batch.num_rows()]
+        } else {
+            let values = self.value_converter.convert_columns(&pick(&self.compared))?;
+            values
+                .iter()
+                .map(|row| {
+                    let mut hasher = DefaultHasher::new();
+                    row.as_ref().hash(&mut hasher);
+                    hasher.finish()
+                })
+                .collect()
+        };
+        Ok((keys, hashes))
+    }
+}
+
+/// The changes file: the current schema plus [`CHANGE_COLUMN`].
+struct ChangesWriter {
+    path: PathBuf,
+    schema: SchemaRef,
+    writer: ArrowWriter<File>,
+}
+
+impl ChangesWriter {
+    fn new(extractor: &Extractor, path: &Path, current: &SchemaRef) -> Result<Self> {
+        let mut fields: Vec<Field> = current.fields().iter().map(|f| f.as_ref().clone()).collect();
+        fields.push(Field::new(CHANGE_COLUMN, DataType::Utf8, false));
+        let schema = Arc::new(Schema::new(fields));
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(extractor.create_writer_props()))?;
+        Ok(Self { path: path.to_path_buf(), schema, writer })
+    }
+
+    /// Write the rows of `batch` with a label, in the current schema.
+    fn write(&mut self, batch: &RecordBatch, labels: Vec<Option<&str>>) -> Result<()> {
+        let keep: BooleanArray = labels.iter().map(|label| Some(label.is_some())).collect();
+        if keep.true_count() == 0 {
+            return Ok(());
+        }
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(labels.iter().map(|label| Some(label.unwrap_or(""))).collect::<StringArray>()));
+        let labelled = RecordBatch::try_new(Arc::clone(&self.schema), columns)?;
+        self.writer.write(&filter_record_batch(&labelled, &keep)?)?;
+        Ok(())
+    }
+
+    /// Re-read `against` for the rows whose keys are left in `deleted`.
+    fn write_deleted(
+        &mut self,
+        against: &Path,
+        layout: &Layout,
+        keys: &mut RowKeys,
+        deleted: &HashMap<Vec<u8>, u64>,
+    ) -> Result<()> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(against)?)?.build()?;
+        let previous = reader.schema();
+        for batch in reader {
+            let batch = batch?;
+            let (batch_keys, _) = keys.rows(&batch, Side::Previous)?;
+            let labels: Vec<Option<&str>> = (0..batch.num_rows())
+                .map(|i| deleted.contains_key(batch_keys.row(i).as_ref()).then_some("delete"))
+                .collect();
+            let columns: Vec<ArrayRef> = layout
+                .current
+                .fields()
+                .iter()
+                .map(|field| match previous.index_of(field.name()) {
+                    Ok(p) if previous.field(p).data_type() == field.data_type() => Arc::clone(batch.column(p)),
+                    _ => new_null_array(field.data_type(), batch.num_rows()),
+                })
+                .collect();
+            self.write(&RecordBatch::try_new(Arc::clone(&layout.current), columns)?, labels)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<PathBuf> {
+        self.writer.close()?;
+        Ok(self.path)
+    }
+}
@@ -0,0 +1,122 @@
+//! Typed errors at the library boundary
+//!
+//! Internally everything is `anyhow`; failures whose class is known where they
+//! happen (a connection attempt, the merge) are tagged there by wrapping them
+//! in the matching [`ExtractError`], and the public `Extractor` methods then
+//! classify the whole error chain by that tag, or by the phase that failed.
+
+use std::{error::Error, fmt};
+
+/// Why an extraction failed. Each variant carries the full error chain; the
+/// CLI maps variants to distinct exit codes via [`ExtractError::exit_code`].
+#[derive(Debug)]
+pub enum ExtractError {
+    /// Couldn't open or keep a connection (network, auth, wire crypt, timeout)
+    Connection(anyhow::Error),
+    /// Reading table metadata or planning partitions failed (unknown table, ...)
+    Metadata(anyhow::Error),
+    /// A column's Firebird type couldn't be mapped to the requested Arrow type
+    TypeMapping(anyhow::Error),
+    /// Partitions or pages failed and partial output wasn't allowed
+    PartitionFailed(anyhow::Error),
+    /// Writing Parquet files, manifests or creating directories failed
+    WriterIo(anyhow::Error),
+    /// Combining partition files into the output, or the output into a merge target, failed
+    Merge(anyhow::Error),
+    /// A precondition or post-check failed: output exists, disk space, duplicate keys
+    Validation(anyhow::Error),
+    /// Stopped through a [`CancellationToken`](crate::CancellationToken); partial output was removed
+    Cancelled(anyhow::Error),
+}
+
+impl ExtractError {
+    /// Process exit code for this class of failure; 1 stays generic and 2 is
+    /// clap's usage error. Cancellation exits 130, as shells report Ctrl+C.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Connection(_) => 3,
+            Self::Metadata(_) => 4,
+            Self::TypeMapping(_) => 5,
+            Self::PartitionFailed(_) => 6,
+            Self::WriterIo(_) => 7,
+            Self::Merge(_) => 8,
+            Self::Validation(_) => 9,
+            Self::Cancelled(_) => 130,
+        }
+    }
+
+    /// Short snake_case name of the class, e.g. `connection`, for reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Connection(_) => "connection",
+            Self::Metadata(_) => "metadata",
+            Self::TypeMapping(_) => "type_mapping",
+            Self::PartitionFailed(_) => "partition_failed",
+            Self::WriterIo(_) => "writer_io",
+            Self::Merge(_) => "merge",
+            Self::Validation(_) => "validation",
+            Self::Cancelled(_) => "cancelled",
+        }
+    }
+
+    /// The underlying error chain.
+    pub fn inner(&self) -> &anyhow::Error {
+        match self {
+            Self::Connection(e)
+            | Self::Metadata(e)
+            | Self::TypeMapping(e)
+            | Self::PartitionFailed(e)
+            | Self::WriterIo(e)
+            | Self::Merge(e)
+            | Self::Validation(e)
+            | Self::Cancelled(e) => e,
+        }
+    }
+
+    /// Tag `e` as this class inside an `anyhow` chain, for [`Self::classify`]
+    /// to find later. An error that already carries a tag keeps it.
+    pub(crate) fn tag(class: fn(anyhow::Error) -> Self, e: anyhow::Error) -> anyhow::Error {
+        if e.chain().any(|cause| cause.is::<ExtractError>()) {
+            return e;
+        }
+        anyhow::Error::new(class(e))
+    }
+
+    /// `e` classified by the innermost tag in its chain, else as `fallback`.
+    pub(crate) fn classify(e: anyhow::Error, fallback: fn(anyhow::Error) -> Self) -> Self {
+        let tagged = e
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<ExtractError>())
+            .last()
+            .map(Self::class);
+        tagged.unwrap_or(fallback)(e)
+    }
+
+    fn class(&self) -> fn(anyhow::Error) -> Self {
+        match self {
+            Self::Connection(_) => Self::Connection,
+            Self::Metadata(_) => Self::Metadata,
+            Self::TypeMapping(_) => Self::TypeMapping,
+            Self::PartitionFailed(_) => Self::PartitionFailed,
+            Self::WriterIo(_) => Self::WriterIo,
+            Self::Merge(_) => Self::Merge,
+            Self::Validation(_) => Self::Validation,
+            Self::Cancelled(_) => Self::Cancelled,
+        }
+    }
+}
+
+// Display and source expose the wrapped chain layer by layer, so `{:#}` reads
+// the same as the plain anyhow error would, tags included or not.
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+impl Error for ExtractError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let top: &(dyn Error + 'static) = self.inner().as_ref();
+        top.source()
+    }
+}
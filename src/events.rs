@@ -0,0 +1,40 @@
+//! Typed progress events for library callers
+//!
+//! [`Extractor::extract_table_with_progress`](crate::Extractor::extract_table_with_progress)
+//! hands these to a callback as the extraction runs, so an embedding GUI or
+//! service can render its own progress instead of parsing log output.
+
+use std::sync::Arc;
+
+use crate::extractor::ExtractionStats;
+
+/// A step of one table's extraction. Partition events come from worker
+/// threads, concurrently for different partitions; a sequential extraction
+/// reports itself as partition 0.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ProgressEvent {
+    /// Metadata is loaded and the extraction is about to start
+    MetadataLoaded {
+        table: String,
+        /// Exact or estimated row count, if known
+        rows: Option<i64>,
+        columns: usize,
+        partitions: usize,
+    },
+    PartitionStarted { partition: usize },
+    /// A batch of `rows` rows was converted and handed to the writer
+    BatchWritten { partition: usize, rows: usize },
+    /// A partition is done; `error` is set if it failed after all retries
+    PartitionFinished {
+        partition: usize,
+        rows: usize,
+        error: Option<String>,
+    },
+    /// Partition files are being merged into the output
+    MergeStarted { files: usize },
+    /// The table is extracted
+    Done(ExtractionStats),
+}
+
+pub(crate) type ProgressCallback = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
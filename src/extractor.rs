@@ -9,106 +9,696 @@
 //! - Cross-platform (Windows/Linux compatible)
 
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
     fs::{create_dir_all, File},
     io::BufWriter,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, OnceLock,
+    },
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use crossbeam_channel::{bounded, Receiver, Sender};
 use anyhow::{Context, Result};
+use chrono::{NaiveDateTime, Timelike};
 use arrow::{
-    array::{ArrayRef, BinaryBuilder, Float64Builder, Int64Builder, StringBuilder},
-    datatypes::{DataType, Field, Schema},
+    array::{
+        ArrayRef, BooleanBuilder, Date32Builder, Float64Builder, GenericBinaryBuilder, GenericStringBuilder,
+        Int64Builder, OffsetSizeTrait, StringBuilder, Time64MicrosecondBuilder, TimestampMicrosecondBuilder,
+    },
+    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
     record_batch::RecordBatch,
 };
 use parquet::{
     arrow::ArrowWriter,
     basic::Compression,
-    file::properties::WriterProperties,
+    file::properties::{WriterProperties, WriterPropertiesBuilder},
+    schema::types::ColumnPath,
 };
 use rayon::prelude::*;
-use rsfbclient::{charset, Queryable, Row, SimpleConnection};
-
-use crate::config::ExtractorConfig;
-
+use rsfbclient::{
+    charset, Execute, Queryable, SimpleConnection, TrDataAccessMode, TrIsolationLevel, TrLockResolution,
+    TrRecordVersion, TransactionConfiguration,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, field, info, info_span, instrument, warn, Span};
+
+use crate::blob::BlobFetcher;
+use crate::cancel::{self, CancellationToken};
+use crate::dictionary;
+use crate::error::ExtractError;
+use crate::events::{ProgressCallback, ProgressEvent};
+use crate::config::{
+    Backend, BlobMode, ExtractorConfig, Isolation, NulBytes, OutputMode, PartitionStrategy, SoftDeleteMode,
+    SpecialFloats, TableOverrides, TimestampZone, WireCrypt,
+};
+use crate::gaps;
+use crate::merge::MergeStats;
+use crate::manifest::{schema_changes, schema_fields, Manifest, MissingPartition};
+use crate::mask::Mask;
+use crate::memory::{estimate_rows_bytes, BatchSizer, MemoryBudget};
+use crate::partition::{self, quote_ident, sql_string_literal, PartitionSpec};
+use crate::profile::{Stage, StageTimes};
+use crate::sidecar::{self, BlobFiles};
+use crate::soft_delete::deleted_predicate;
+use crate::progress::{PartitionProgress, TableProgress};
+use crate::resources;
+use crate::retry;
+use crate::server_info::{ServerFeature, ServerInfo};
+use crate::source::{Row, Select, Source, SourceConnection, Value};
+use crate::throttle::{self, Throttle, ThrottleStats};
+use crate::transform::{self, BatchTransform};
+use crate::validate;
+use crate::types::{ColumnValues, DefaultTypeMapper, FieldInfo, TypeMapper};
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+use crate::writer::{PartitionSink, WriterPool};
+
+/// Target of the structured events (`event = "partition_completed"`, ...)
+/// emitted alongside the human-readable log lines; see `--log-format json`.
+pub const EVENT_TARGET: &str = "firebird_peregrine_falcon::events";
+
+/// Batches queued after the fetch and the convert stage of a sequential or
+/// async extraction when `prefetch_depth` isn't set
+pub(crate) const DEFAULT_PREFETCH_DEPTHS: (usize, usize) = (10, 8);
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct ExtractionStats {
     pub rows_extracted: usize,
     pub duration_secs: f64,
     pub file_size_mb: f64,
+    /// Partitions skipped under `allow_partial`; their rows are not in the output
+    pub failed_partitions: usize,
+    /// Estimated in-memory size of the rows fetched from Firebird
+    pub bytes_read: usize,
+    /// Record batches written
+    pub batches: usize,
+    /// Null values per output column
+    pub null_counts: BTreeMap<String, usize>,
+    /// The Parquet file and manifest written for the table
+    pub output_files: Vec<PathBuf>,
+    /// One entry per partition; sequential extraction counts as one
+    pub partitions: Vec<PartitionStats>,
+    /// Rows extracted by the plan's `IS NULL` partition, whose partition key is NULL
+    pub null_key_rows: usize,
+    /// NaN and ±Infinity values in Float64 columns, written as is or as null
+    /// depending on `special_floats`; rows of retried partitions count again
+    pub special_floats: usize,
+    /// Text columns that held NUL or U+FFFD characters; the other columns aren't listed
+    pub text_issues: BTreeMap<String, TextIssues>,
+    /// Soft-deleted rows written to the tombstone file (`soft_delete` mode `tombstone`)
+    pub tombstones: usize,
+    /// How the output went into `merge_target`, when one is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge: Option<MergeStats>,
+}
+
+/// Suspicious characters in one text column; see [`ExtractionStats::text_issues`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TextIssues {
+    /// NUL characters, kept or stripped depending on `nul_bytes`
+    pub nul_bytes: usize,
+    /// U+FFFD: the driver met bytes invalid in the connection charset and replaced them
+    pub replacement_chars: usize,
+}
+
+/// How one partition went; see [`ExtractionStats::partitions`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PartitionStats {
+    pub index: usize,
+    /// The rows the partition selects: its key range predicate, a `ROWS`
+    /// window, or `all rows`
+    #[serde(default)]
+    pub range: String,
+    pub rows: usize,
+    /// Estimated in-memory size of the rows fetched from Firebird
+    pub bytes_read: usize,
+    pub batches: usize,
+    pub duration_secs: f64,
+    #[serde(default)]
+    pub rows_per_sec: f64,
+    /// Attempts after the first (for sequential extraction, summed over pages)
+    pub retries: usize,
+    /// Why the partition failed, when `allow_partial` kept the output without it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct Extractor {
-    config: ExtractorConfig,
-    pool: Arc<ConnectionPool>,
+    pub(crate) config: ExtractorConfig,
+    pub(crate) pool: Arc<ConnectionPool>,
+    /// Metadata and rows; the pool itself unless replaced by `with_source`
+    pub(crate) source: Arc<dyn Source>,
+    pub(crate) budget: Arc<MemoryBudget>,
+    /// Receives [`ProgressEvent`]s; set by `extract_table_with_progress`
+    pub(crate) observer: Option<ProgressCallback>,
+    pub(crate) cancel: CancellationToken,
+    /// Applied to every batch before it's written; see `with_transform`
+    pub(crate) transform: Option<BatchTransform>,
+    pub(crate) type_mapper: Arc<dyn TypeMapper>,
+    /// Row rate and concurrent query limits, shared like the pool
+    pub(crate) throttle: Arc<Throttle>,
+}
+
+/// A table returned by [`Extractor::list_tables`].
+#[derive(Clone, Debug, Serialize)]
+pub struct TableSummary {
+    pub name: String,
+    /// From PK index statistics; `None` without a PK or computed statistics
+    pub estimated_rows: Option<i64>,
+    /// Without one, only the `column:` and `rows` partition strategies can split the table
+    pub has_pk: bool,
+}
+
+/// A table's layout as returned by [`Extractor::table_info`].
+#[derive(Clone, Debug)]
+pub struct TableInfo {
+    pub name: String,
+    /// Exact unless `skip_count` is set, then estimated from PK index
+    /// statistics; `None` if neither is available
+    pub row_count: Option<i64>,
+    pub row_count_exact: bool,
+    /// Output names of the primary key columns in key order; empty without a PK
+    pub pk: Vec<String>,
+    /// In table order, with configured column overrides and renames applied
+    pub columns: Vec<ColumnInfo>,
+}
+
+/// One column of a [`TableInfo`].
+#[derive(Clone, Debug)]
+pub struct ColumnInfo {
+    /// Output name, after `column_case` and renames
+    pub name: String,
+    /// Arrow type the column is written as
+    pub data_type: DataType,
+    /// Fetched as a blob (text or binary)
+    pub blob: bool,
+}
+
+/// Connection pool counters, cumulative since the extractor was created.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct PoolStats {
+    /// Idle connections that passed the liveness check and were reused
+    pub hits: usize,
+    /// Acquires that found no idle connection and opened a new one
+    pub misses: usize,
+    /// Idle connections that failed the liveness check and were replaced
+    pub reconnects: usize,
+    /// Idle connections replaced for exceeding the max lifetime
+    pub expired: usize,
+    /// Connections the pool was cut back to when the server refused more
+    /// attachments; acquires beyond it waited for a connection to come back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment_limit: Option<usize>,
+}
+
+#[derive(Default)]
+struct PoolCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    reconnects: AtomicUsize,
+    expired: AtomicUsize,
+    /// Connections handed out and not yet dropped
+    in_use: Arc<AtomicUsize>,
+    /// Cap on connections after an attachment limit error; 0 for none
+    attachment_limit: AtomicUsize,
+}
+
+struct IdleConnection {
+    conn: SimpleConnection,
+    created: Instant,
 }
 
-struct ConnectionPool {
-    connections: Arc<Mutex<Vec<SimpleConnection>>>,
+/// How long [`ConnectionPool::acquire`] waits at the attachment limit with
+/// no connection handed back before failing.
+const POOL_WAIT_LIMIT: Duration = Duration::from_secs(600);
+/// How often a waiting acquire checks for cancellation.
+const POOL_WAIT_POLL: Duration = Duration::from_millis(250);
+
+pub(crate) struct ConnectionPool {
+    connections: Arc<Mutex<Vec<IdleConnection>>>,
+    /// Notified whenever a connection is handed back
+    returned: Arc<Condvar>,
     config: ExtractorConfig,
+    counters: PoolCounters,
+    /// Index into `config.connection_hosts()` of the host new connections try
+    /// first: the one that last accepted a connection
+    current_host: AtomicUsize,
+    /// Read on the first connection
+    server: OnceLock<ServerInfo>,
 }
 
 impl ConnectionPool {
+    /// Connections are opened on first use unless `eager_pool` asks for all
+    /// `pool_size` of them up front.
     fn new(config: ExtractorConfig) -> Result<Self> {
-        let mut connections = Vec::new();
-        for _ in 0..config.pool_size {
-            let conn = Self::create_connection(&config)?;
-            connections.push(conn);
-        }
-        Ok(Self {
-            connections: Arc::new(Mutex::new(connections)),
+        let pool = Self {
+            connections: Arc::new(Mutex::new(Vec::new())),
+            returned: Arc::new(Condvar::new()),
             config,
-        })
+            counters: PoolCounters::default(),
+            current_host: AtomicUsize::new(0),
+            server: OnceLock::new(),
+        };
+        if pool.config.eager_pool {
+            for _ in 0..pool.config.pool_size {
+                let conn = pool.create_connection()?;
+                pool.connections.lock().unwrap().push(IdleConnection { conn, created: Instant::now() });
+            }
+        }
+        Ok(pool)
     }
 
-    fn create_connection(config: &ExtractorConfig) -> Result<SimpleConnection> {
-        let mut builder = rsfbclient::builder_native().with_dyn_link().with_remote();
-        builder.db_name(&config.database_path);
-        builder.user(&config.user);
-        builder.pass(&config.password);
-        builder.charset(charset::ISO_8859_1);
+    fn create_connection(&self) -> Result<SimpleConnection> {
+        self.config
+            .retry
+            .run("Connection", || self.connect_any_host())
+            .map_err(|e| ExtractError::tag(ExtractError::Connection, e))
+    }
 
-        let conn: SimpleConnection = builder
-            .connect()
-            .context("Failed to connect to Firebird")?
-            .into();
+    /// Connect to the current host, failing over to the others in order.
+    /// Staying on the host that worked keeps a run's partitions on one server.
+    fn connect_any_host(&self) -> Result<SimpleConnection> {
+        let hosts = self.config.connection_hosts();
+        let current = self.current_host.load(Ordering::Relaxed).min(hosts.len() - 1);
+        let name = |host: Option<&str>| host.unwrap_or("localhost").to_string();
+        let mut failures = Vec::new();
+        for i in (current..hosts.len()).chain(0..current) {
+            match Self::connect(&self.config, hosts[i], &self.server) {
+                Ok(conn) => {
+                    if i != current {
+                        warn!("Failed over from {} to {}", name(hosts[current]), name(hosts[i]));
+                        self.current_host.store(i, Ordering::Relaxed);
+                    }
+                    return Ok(conn);
+                }
+                Err(e) if hosts.len() == 1 => return Err(e),
+                Err(e) => {
+                    warn!("Connecting to {} failed: {:#}", name(hosts[i]), e);
+                    failures.push(e);
+                }
+            }
+        }
+        let tried: Vec<String> = hosts.iter().map(|&host| name(host)).collect();
+        let last = failures.pop().expect("every host was tried");
+        Err(last.context(format!("No host accepted the connection (tried {})", tried.join(", "))))
+    }
+
+    /// Open and set up a connection to `host`, within `connect_timeout` if
+    /// one is set. The first one also reads the server version into `server`.
+    fn connect(
+        config: &ExtractorConfig,
+        host: Option<&str>,
+        server: &OnceLock<ServerInfo>,
+    ) -> Result<SimpleConnection> {
+        let mut conn = match config.connect_timeout {
+            Some(timeout) => Self::open_with_timeout(config, host, timeout)?,
+            None => Self::open_connection(config, host, TrDataAccessMode::ReadOnly)?,
+        };
+
+        if !config.embedded {
+            Self::check_security(&mut conn, config)?;
+        }
+        let server = match server.get() {
+            Some(server) => server,
+            None => {
+                let read = ServerInfo::query(&mut conn)?;
+                info!("Server: Firebird {}", read.version);
+                server.get_or_init(|| read)
+            }
+        };
+        server.prepare(&mut conn)?;
+        if let Some(timeout) = config.query_timeout {
+            server.require(ServerFeature::StatementTimeout, "--query-timeout")?;
+            let sql = format!("SET STATEMENT TIMEOUT {} MILLISECOND", timeout.as_millis().max(1));
+            conn.execute(&sql, ()).context("Failed to set the statement timeout")?;
+        }
         Ok(conn)
     }
 
-    fn acquire(&self) -> Result<PooledConnection> {
-        let mut pool = self.connections.lock().unwrap();
-        if let Some(conn) = pool.pop() {
-            Ok(PooledConnection {
-                conn: Some(conn),
-                pool: Arc::clone(&self.connections),
-                config: self.config.clone(),
-            })
-        } else {
-            // Create new connection if pool is empty
-            Ok(PooledConnection {
-                conn: Some(Self::create_connection(&self.config)?),
-                pool: Arc::clone(&self.connections),
-                config: self.config.clone(),
-            })
+    /// The client libraries can't cancel a connect, so it runs on its own
+    /// thread; on timeout that thread is abandoned and its connection, if it
+    /// ever arrives, is closed.
+    fn open_with_timeout(config: &ExtractorConfig, host: Option<&str>, timeout: Duration) -> Result<SimpleConnection> {
+        let (tx, rx) = bounded(1);
+        let thread_config = config.clone();
+        let host = host.map(str::to_string);
+        thread::spawn(move || {
+            let _ = tx.send(Self::open_connection(&thread_config, host.as_deref(), TrDataAccessMode::ReadOnly));
+        });
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(_) => {
+                let msg = format!(
+                    "Connecting to Firebird timed out after {:.1}s (--connect-timeout)",
+                    timeout.as_secs_f64()
+                );
+                Err(std::io::Error::new(std::io::ErrorKind::TimedOut, msg).into())
+            }
+        }
+    }
+
+    /// A connection whose transactions may write, for the audit log and
+    /// change capture. Not
+    /// pooled: extraction connections stay read-only. Always to the primary
+    /// `host`, as replicas are read-only.
+    pub(crate) fn connect_read_write(config: &ExtractorConfig) -> Result<SimpleConnection> {
+        let mut conn = Self::open_connection(config, config.host.as_deref(), TrDataAccessMode::ReadWrite)
+            .map_err(|e| ExtractError::tag(ExtractError::Connection, e))?;
+        if !config.embedded {
+            Self::check_security(&mut conn, config)?;
+        }
+        Ok(conn)
+    }
+
+    fn open_connection(
+        config: &ExtractorConfig,
+        host: Option<&str>,
+        access: TrDataAccessMode,
+    ) -> Result<SimpleConnection> {
+        let conn: SimpleConnection = match config.backend {
+            // Embedded: fbclient opens the file in-process, no server or password
+            Backend::Native if config.embedded => {
+                let mut builder = rsfbclient::builder_native().with_dyn_link().with_embedded();
+                builder.db_name(&config.database_path);
+                builder.user(&config.user);
+                builder.charset(charset::ISO_8859_1);
+                builder.transaction(Self::transaction_config(config, access));
+
+                builder
+                    .connect()
+                    .context("Failed to open Firebird database in embedded mode")?
+                    .into()
+            }
+            Backend::PureRust if config.embedded => {
+                anyhow::bail!("Embedded mode needs the native backend (fbclient with the engine plugin)")
+            }
+            Backend::Native => {
+                let mut builder = rsfbclient::builder_native().with_dyn_link().with_remote();
+                builder.db_name(&config.database_path);
+                if let Some(host) = host {
+                    builder.host(host);
+                }
+                if let Some(port) = config.port {
+                    builder.port(port);
+                }
+                builder.user(&config.user);
+                builder.pass(&config.password);
+                builder.charset(charset::ISO_8859_1);
+                builder.transaction(Self::transaction_config(config, access));
+
+                builder
+                    .connect()
+                    .context("Failed to connect to Firebird")
+                    .with_context(|| Self::security_hint(config))?
+                    .into()
+            }
+            Backend::PureRust => {
+                let mut builder = rsfbclient::builder_pure_rust();
+                builder.db_name(&config.database_path);
+                if let Some(host) = host {
+                    builder.host(host);
+                }
+                if let Some(port) = config.port {
+                    builder.port(port);
+                }
+                builder.user(&config.user);
+                builder.pass(&config.password);
+                builder.charset(charset::ISO_8859_1);
+                builder.transaction(Self::transaction_config(config, access));
+
+                builder
+                    .connect()
+                    .context("Failed to connect to Firebird (pure Rust backend)")
+                    .with_context(|| Self::security_hint(config))?
+                    .into()
+            }
+        };
+        Ok(conn)
+    }
+
+    /// `access` (READ ONLY but for the audit log) at the configured
+    /// isolation, waiting on locks like the default.
+    fn transaction_config(config: &ExtractorConfig, access: TrDataAccessMode) -> TransactionConfiguration {
+        TransactionConfiguration {
+            data_access: access,
+            isolation: match config.isolation {
+                Isolation::Concurrency => TrIsolationLevel::Concurrency,
+                Isolation::ReadCommitted => TrIsolationLevel::ReadCommited(TrRecordVersion::RecordVersion),
+            },
+            lock_resolution: TrLockResolution::Wait(None),
+        }
+    }
+
+    /// Enforce `wire_crypt` and `auth_plugin` against what the attachment
+    /// actually negotiated.
+    fn check_security(conn: &mut SimpleConnection, config: &ExtractorConfig) -> Result<()> {
+        if config.wire_crypt == WireCrypt::Enabled && config.auth_plugin.is_none() {
+            return Ok(());
+        }
+
+        let sql = r#"
+            SELECT RDB$GET_CONTEXT('SYSTEM', 'WIRE_ENCRYPTED'), a.MON$AUTH_METHOD
+            FROM MON$ATTACHMENTS a
+            WHERE a.MON$ATTACHMENT_ID = CURRENT_CONNECTION
+        "#;
+        let rows: Vec<(Option<String>, Option<String>)> =
+            conn.query(sql, ()).context("Failed to read the connection's security attributes (Firebird 3+ required)")?;
+        let (encrypted, auth_method) = rows.into_iter().next().unwrap_or((None, None));
+        let encrypted = encrypted.is_some_and(|e| e.trim().eq_ignore_ascii_case("TRUE"));
+
+        match config.wire_crypt {
+            WireCrypt::Required if !encrypted => anyhow::bail!(
+                "Connection is not wire-encrypted but --wire-crypt required was given. {}",
+                Self::security_hint(config)
+            ),
+            WireCrypt::Disabled if encrypted => anyhow::bail!(
+                "Connection is wire-encrypted but --wire-crypt disabled was given. {}",
+                Self::security_hint(config)
+            ),
+            _ => {}
+        }
+        if let Some(plugin) = config.auth_plugin {
+            let method = auth_method.unwrap_or_default();
+            if !method.trim().eq_ignore_ascii_case(plugin.name()) {
+                anyhow::bail!(
+                    "Connection authenticated with '{}' but --auth-plugin {} was given. {}",
+                    method.trim(),
+                    plugin.name(),
+                    Self::security_hint(config)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The `firebird.conf` lines that make the client negotiate `wire_crypt`
+    /// and `auth_plugin`. rsfbclient's builders take no DPB items or
+    /// per-attachment settings (`isc_dpb_config`), so these can't be passed
+    /// with the connection; [`Self::check_security`] asserts the result.
+    fn client_settings(config: &ExtractorConfig) -> Vec<String> {
+        let mut settings = Vec::new();
+        if config.wire_crypt != WireCrypt::Enabled {
+            settings.push(format!("WireCrypt = {:?}", config.wire_crypt));
+        }
+        if let Some(plugin) = config.auth_plugin {
+            settings.push(format!("AuthClient = {}", plugin.name()));
+        }
+        settings
+    }
+
+    fn security_hint(config: &ExtractorConfig) -> String {
+        let settings = Self::client_settings(config);
+        match (config.backend, settings.is_empty()) {
+            (Backend::PureRust, true) => "Servers requiring wire encryption or a specific auth plugin may need the \
+                native backend (--backend native) with matching WireCrypt / AuthClient settings in fbclient's \
+                firebird.conf"
+                .to_string(),
+            (Backend::PureRust, false) => format!(
+                "The pure-Rust backend negotiates on its own and reads no firebird.conf; use --backend native with {} \
+                 in fbclient's firebird.conf",
+                settings.join(" and ")
+            ),
+            (Backend::Native, true) => "Servers requiring wire encryption or a specific auth plugin need matching \
+                WireCrypt / AuthClient settings in the client's firebird.conf"
+                .to_string(),
+            (Backend::Native, false) => {
+                format!("The client negotiates from its firebird.conf; make sure it has {}", settings.join(" and "))
+            }
+        }
+    }
+
+    /// The server's version, once a connection was opened.
+    pub(crate) fn server_info(&self) -> Option<&ServerInfo> {
+        self.server.get()
+    }
+
+    /// Hand out an idle connection, replacing it if it outlived
+    /// `max_connection_lifetime` or fails a `SELECT 1 FROM RDB$DATABASE` probe.
+    /// With `max_attachments` handed out, or once the server refused an
+    /// attachment as many as it accepted, waits for one to come back.
+    pub(crate) fn acquire(&self) -> Result<PooledConnection> {
+        self.acquire_until(&CancellationToken::new())
+    }
+
+    /// [`Self::acquire`], giving up the wait for a connection once `cancel`
+    /// fires or none came back for [`POOL_WAIT_LIMIT`].
+    pub(crate) fn acquire_until(&self, cancel: &CancellationToken) -> Result<PooledConnection> {
+        let (conn, created) = loop {
+            let idle = {
+                let mut idle = self.connections.lock().unwrap();
+                let mut waiting_since = Instant::now();
+                while idle.is_empty() && self.at_attachment_limit() {
+                    cancel.check()?;
+                    if waiting_since.elapsed() >= POOL_WAIT_LIMIT {
+                        let e = anyhow::anyhow!(
+                            "No pooled connection came back for {}s with all {} attachments in use",
+                            POOL_WAIT_LIMIT.as_secs(),
+                            self.counters.in_use.load(Ordering::Relaxed)
+                        );
+                        return Err(ExtractError::tag(ExtractError::Connection, e));
+                    }
+                    let (guard, wait) = self.returned.wait_timeout(idle, POOL_WAIT_POLL).unwrap();
+                    idle = guard;
+                    if !wait.timed_out() {
+                        waiting_since = Instant::now();
+                    }
+                }
+                idle.pop()
+            };
+
+            let opened = match idle {
+                Some(mut idle) => {
+                    let expired = self
+                        .config
+                        .max_connection_lifetime
+                        .map_or(false, |max| idle.created.elapsed() >= max);
+                    if expired {
+                        self.counters.expired.fetch_add(1, Ordering::Relaxed);
+                        drop(idle);
+                        self.open()?
+                    } else if Self::is_alive(&mut idle.conn) {
+                        self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                        break (idle.conn, idle.created);
+                    } else {
+                        self.counters.reconnects.fetch_add(1, Ordering::Relaxed);
+                        drop(idle);
+                        self.open()?
+                    }
+                }
+                None => {
+                    // Create new connection if pool is empty
+                    self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                    self.open()?
+                }
+            };
+            if let Some(conn) = opened {
+                break (conn, Instant::now());
+            }
+        };
+
+        self.counters.in_use.fetch_add(1, Ordering::Relaxed);
+        Ok(PooledConnection {
+            conn: Some(conn),
+            created,
+            pool: Arc::clone(&self.connections),
+            returned: Arc::clone(&self.returned),
+            max_idle: self.config.pool_size,
+            in_use: Arc::clone(&self.counters.in_use),
+        })
+    }
+
+    /// A new connection, or `None` if the server is out of attachments and
+    /// the caller should wait for one of ours instead.
+    fn open(&self) -> Result<Option<SimpleConnection>> {
+        match self.create_connection() {
+            Ok(conn) => Ok(Some(conn)),
+            Err(e) if retry::is_attachment_limit(&e) => {
+                self.limit_attachments(e)?;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Cap the pool at the connections handed out, the attachments the server
+    /// accepted. With none handed out there's nothing to wait for.
+    fn limit_attachments(&self, e: anyhow::Error) -> Result<()> {
+        let in_use = self.counters.in_use.load(Ordering::Relaxed);
+        if in_use == 0 {
+            return Err(e.context(
+                "The server accepts no more attachments; other clients hold them all, so wait for them \
+                 or have the DBA raise the limit",
+            ));
+        }
+        let limit = &self.counters.attachment_limit;
+        let previous = limit.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |cap| {
+            (cap == 0 || in_use < cap).then_some(in_use)
+        });
+        if previous.is_ok() {
+            warn!(
+                "  ⚠ The server refused another attachment ({:#}); continuing with {} connections, \
+                 partitions queue for them",
+                e, in_use
+            );
+        }
+        Ok(())
+    }
+
+    fn at_attachment_limit(&self) -> bool {
+        let detected = self.counters.attachment_limit.load(Ordering::Relaxed);
+        let limit = self.config.max_attachments.into_iter().chain(Some(detected)).filter(|&n| n > 0).min();
+        limit.is_some_and(|limit| self.counters.in_use.load(Ordering::Relaxed) >= limit)
+    }
+
+    fn is_alive(conn: &mut SimpleConnection) -> bool {
+        let probe: Result<Vec<(i32,)>, _> = conn.query("SELECT 1 FROM RDB$DATABASE", ());
+        probe.is_ok()
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            reconnects: self.counters.reconnects.load(Ordering::Relaxed),
+            expired: self.counters.expired.load(Ordering::Relaxed),
+            attachment_limit: Some(self.counters.attachment_limit.load(Ordering::Relaxed)).filter(|&n| n > 0),
         }
     }
+
+    /// Connections currently handed out, and the configured pool size.
+    pub(crate) fn usage(&self) -> (usize, usize) {
+        (self.counters.in_use.load(Ordering::Relaxed), self.config.pool_size)
+    }
 }
 
-struct PooledConnection {
+pub(crate) struct PooledConnection {
     conn: Option<SimpleConnection>,
-    pool: Arc<Mutex<Vec<SimpleConnection>>>,
-    config: ExtractorConfig,
+    created: Instant,
+    pool: Arc<Mutex<Vec<IdleConnection>>>,
+    returned: Arc<Condvar>,
+    max_idle: usize,
+    in_use: Arc<AtomicUsize>,
 }
 
 impl Drop for PooledConnection {
     fn drop(&mut self) {
+        self.in_use.fetch_sub(1, Ordering::Relaxed);
         if let Some(conn) = self.conn.take() {
             if let Ok(mut pool) = self.pool.lock() {
-                pool.push(conn);
+                // Overflow connections opened under load are closed, not kept
+                if pool.len() < self.max_idle {
+                    pool.push(IdleConnection { conn, created: self.created });
+                }
             }
         }
+        self.returned.notify_one();
     }
 }
 
@@ -126,314 +716,1621 @@ impl std::ops::DerefMut for PooledConnection {
 }
 
 #[derive(Clone)]
-struct TableMetadata {
-    table_name: String,
-    columns: Vec<ColumnMetadata>,
-    row_count: i64,
-    has_blob: bool,
+pub(crate) struct TableMetadata {
+    pub(crate) table_name: String,
+    pub(crate) columns: Vec<ColumnMetadata>,
+    pub(crate) row_count: RowCount,
+    pub(crate) has_blob: bool,
     pk: Option<PrimaryKeyInfo>,
+    /// Configured row filter, WHERE clause and soft delete exclusion
+    /// narrowing every extraction query
+    pub(crate) filter: Option<String>,
+    /// Predicate matching soft-deleted rows, when they go to a tombstone file
+    pub(crate) tombstone_filter: Option<String>,
+    pub(crate) type_mapper: Arc<dyn TypeMapper>,
+    pub(crate) special_floats: SpecialFloats,
+    /// NaN and ±Infinity values met while converting, shared by all clones
+    pub(crate) special_floats_seen: Arc<AtomicUsize>,
+    pub(crate) nul_bytes: NulBytes,
+    /// Per column, in column order; shared by all clones
+    pub(crate) text_counts: Arc<[TextCounts]>,
+    /// ORDER BY of every extraction query; set only for `deterministic`
+    order_by: Vec<String>,
+    /// Prefix of every value hashed by `hash` and `fake` masks
+    pub(crate) mask_salt: Arc<[u8]>,
+    /// Where externalized blobs go; set under [`BlobMode::External`]
+    pub(crate) blob_files: Option<BlobFiles>,
+}
+
+/// NUL and U+FFFD characters met while converting one text column.
+#[derive(Default)]
+pub(crate) struct TextCounts {
+    nul_bytes: AtomicUsize,
+    replacement_chars: AtomicUsize,
+}
+
+/// Table size as known before extraction starts.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RowCount {
+    /// From `SELECT COUNT(*)`
+    Exact(i64),
+    /// From PK index selectivity; only as fresh as the last `SET STATISTICS`
+    Estimated(i64),
+    Unknown,
+}
+
+impl RowCount {
+    pub(crate) fn approx(self) -> Option<i64> {
+        match self {
+            Self::Exact(n) | Self::Estimated(n) => Some(n),
+            Self::Unknown => None,
+        }
+    }
+
+    /// Only an exact count can prove the table empty.
+    pub(crate) fn is_empty(self) -> bool {
+        matches!(self, Self::Exact(0))
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Self::Exact(n) => format_number(n),
+            Self::Estimated(n) => format!("~{} (estimated)", format_number(n)),
+            Self::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
+impl TableMetadata {
+    /// Columns with NUL or replacement characters so far, by name.
+    pub(crate) fn text_issues(&self) -> BTreeMap<String, TextIssues> {
+        self.columns
+            .iter()
+            .zip(self.text_counts.iter())
+            .map(|(column, counts)| {
+                let issues = TextIssues {
+                    nul_bytes: counts.nul_bytes.load(Ordering::Relaxed),
+                    replacement_chars: counts.replacement_chars.load(Ordering::Relaxed),
+                };
+                (column.name.clone(), issues)
+            })
+            .filter(|(_, issues)| issues.nul_bytes > 0 || issues.replacement_chars > 0)
+            .collect()
+    }
+
+    pub(crate) fn arrow_schema(&self) -> Arc<Schema> {
+        let fields: Vec<Field> =
+            self.columns.iter().map(|m| Field::new(&m.output_name, m.data_type.clone(), true)).collect();
+        Arc::new(Schema::new(fields))
+    }
+
+    /// The output name of source column `name`; `name` itself if it isn't extracted.
+    pub(crate) fn output_name<'a>(&'a self, name: &'a str) -> &'a str {
+        find_column(&self.columns, name).map_or(name, |i| &self.columns[i].output_name)
+    }
+
+    /// The output names of the primary key columns.
+    pub(crate) fn output_key(&self) -> Option<Vec<String>> {
+        let pk = self.pk.as_ref()?;
+        Some(pk.columns.iter().map(|c| self.output_name(c).to_string()).collect())
+    }
+
+    /// The query extracting `spec`, narrowed by the table's configured filter.
+    /// With `blobs_apart` the blob columns are left to a [`BlobFetcher`] and
+    /// `RDB$DB_KEY` is selected last in their place.
+    pub(crate) fn select(&self, blobs_apart: bool, spec: &PartitionSpec) -> Select {
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .filter(|c| !(blobs_apart && c.is_blob()))
+            .map(|c| c.name.clone())
+            .collect();
+        let partition = match self.filter {
+            Some(ref filter) => spec.and(filter),
+            None => spec.clone(),
+        };
+        // A ROWS window of an unordered scan is whatever the plan happens to produce
+        let order_by = match (&spec.rows, &self.pk) {
+            (Some(_), Some(pk)) if self.order_by.is_empty() => pk.columns.clone(),
+            (Some(_), None) if self.order_by.is_empty() => vec![partition::DB_KEY.to_string()],
+            _ => self.order_by.clone(),
+        };
+        Select { table: self.table_name.clone(), columns, db_key: blobs_apart, partition, order_by }
+    }
 }
 
 #[derive(Clone)]
-struct ColumnMetadata {
-    name: String,
-    data_type: DataType,
-    is_text_blob: bool,
+pub(crate) struct ColumnMetadata {
+    pub(crate) name: String,
+    pub(crate) data_type: DataType,
+    pub(crate) is_text_blob: bool,
+    pub(crate) field: FieldInfo,
+    /// Name in the output, after `column_case` and renames; `name` is the
+    /// source's, used in queries
+    pub(crate) output_name: String,
+    /// Applied to every batch after conversion
+    pub(crate) mask: Option<Mask>,
+    /// A blob written to sidecar files; the column references them
+    pub(crate) external: bool,
+}
+
+impl ColumnMetadata {
+    pub(crate) fn is_blob(&self) -> bool {
+        self.is_text_blob || self.external || matches!(self.data_type, DataType::Binary | DataType::LargeBinary)
+    }
 }
 
 #[derive(Clone)]
-struct PrimaryKeyInfo {
+pub(crate) struct PrimaryKeyInfo {
     columns: Vec<String>,
-    min_values: Vec<i64>,
-    max_values: Vec<i64>,
-    row_count: i64,
+    /// First and last value of the first key column; `None` for text keys,
+    /// for strategies that don't range-split, and for an empty table
+    range: Option<(i64, i64)>,
+    /// First key column is CHAR/VARCHAR (e.g. UUIDs)
+    text_key: bool,
 }
 
 impl Extractor {
-    pub fn new(config: ExtractorConfig) -> Result<Self> {
-        create_dir_all(&config.out_dir)?;
-        let pool = Arc::new(ConnectionPool::new(config.clone())?);
-        Ok(Self { config, pool })
+    pub fn new(config: ExtractorConfig) -> Result<Self, ExtractError> {
+        config.validate()?;
+        let dirs = || -> Result<()> {
+            create_dir_all(&config.out_dir)
+                .with_context(|| format!("Failed to create {}", config.out_dir.display()))?;
+            if let Some(ref temp_dir) = config.temp_dir {
+                create_dir_all(temp_dir).with_context(|| format!("Failed to create {}", temp_dir.display()))?;
+            }
+            Ok(())
+        };
+        dirs().map_err(ExtractError::WriterIo)?;
+        let pool = ConnectionPool::new(config.clone())
+            .map_err(|e| ExtractError::classify(e, ExtractError::Connection))?;
+        let pool = Arc::new(pool);
+        let budget = Arc::new(MemoryBudget::new(config.max_memory));
+        let throttle = Arc::new(Throttle::new(config.max_rows_per_sec, config.max_concurrent_queries));
+        Ok(Self {
+            source: throttle::throttled(Arc::clone(&pool) as Arc<dyn Source>, &throttle),
+            config,
+            pool,
+            budget,
+            observer: None,
+            cancel: CancellationToken::new(),
+            transform: None,
+            type_mapper: Arc::new(DefaultTypeMapper),
+            throttle,
+        })
     }
 
-    pub fn extract_table(&self, table_name: &str) -> Result<ExtractionStats> {
-        let start = Instant::now();
-        println!("→ Extracting table: {}", table_name);
+    /// Map column types (and convert their values) with `mapper` instead of
+    /// the built-in [`DefaultTypeMapper`].
+    pub fn with_type_mapper(mut self, mapper: impl TypeMapper + 'static) -> Self {
+        self.type_mapper = Arc::new(mapper);
+        self
+    }
 
-        // Load metadata
-        let meta = Arc::new(self.load_metadata(table_name)?);
-        println!("  Rows: {}", format_number(meta.row_count));
-        println!("  Columns: {}", meta.columns.len());
-
-        if meta.row_count == 0 {
-            println!("  (empty table) — skipping");
-            return Ok(ExtractionStats {
-                rows_extracted: 0,
-                duration_secs: start.elapsed().as_secs_f64(),
-                file_size_mb: 0.0,
-            });
+    /// Read metadata and rows from `source` instead of the Firebird connection
+    /// pool, e.g. a fake in tests. Key sampling, blob columns and table listing
+    /// still go to Firebird. The configured throttles apply to it too.
+    pub fn with_source(mut self, source: impl Source + 'static) -> Self {
+        self.source = throttle::throttled(Arc::new(source), &self.throttle);
+        self
+    }
+
+    /// Use `token` instead of this extractor's own, e.g. to stop several
+    /// extractors at once.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// The token that stops this extractor's running and pending extractions
+    /// when cancelled from another thread.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn extract_table(&self, table_name: &str) -> Result<ExtractionStats, ExtractError> {
+        self.for_table(table_name)
+            .extract_with_plan(table_name, None)
+            .map_err(|e| ExtractError::classify(e, ExtractError::PartitionFailed))
+    }
+
+    /// [`Extractor::extract_table`], calling `on_progress` with a [`ProgressEvent`]
+    /// at each step. Partition events arrive from worker threads.
+    pub fn extract_table_with_progress(
+        &self,
+        table_name: &str,
+        on_progress: impl Fn(ProgressEvent) + Send + Sync + 'static,
+    ) -> Result<ExtractionStats, ExtractError> {
+        let mut this = self.for_table(table_name);
+        // An observer set beforehand, like with_metrics's, keeps getting events
+        let previous = this.observer.take();
+        this.observer = Some(Arc::new(move |event: ProgressEvent| {
+            if let Some(ref previous) = previous {
+                previous(event.clone());
+            }
+            on_progress(event);
+        }));
+        this.extract_with_plan(table_name, None)
+            .map_err(|e| ExtractError::classify(e, ExtractError::PartitionFailed))
+    }
+
+    /// Extract a table using a caller-supplied partition plan, e.g. one returned
+    /// by [`Extractor::plan_partitions`] and then adjusted. Under
+    /// `deterministic` only the single full partition is accepted.
+    pub fn extract_table_with_plan(
+        &self,
+        table_name: &str,
+        plan: &[PartitionSpec],
+    ) -> Result<ExtractionStats, ExtractError> {
+        if self.config.deterministic && !matches!(plan, [only] if only.is_full()) {
+            return Err(ExtractError::Validation(anyhow::anyhow!(
+                "A deterministic extraction of {} reads one full partition; a plan of {} partitions would interleave \
+                 their rows by timing",
+                table_name,
+                plan.len()
+            )));
         }
+        self.for_table(table_name)
+            .extract_with_plan(table_name, Some(plan))
+            .map_err(|e| ExtractError::classify(e, ExtractError::PartitionFailed))
+    }
 
-        let output_path = self.config.out_dir.join(format!("{}.parquet", table_name.to_lowercase()));
+    /// The partitions `extract_table` would use for this table under the
+    /// configured [`PartitionStrategy`].
+    pub fn plan_partitions(&self, table_name: &str) -> Result<Vec<PartitionSpec>, ExtractError> {
+        let this = self.for_table(table_name);
+        this.load_metadata(table_name)
+            .and_then(|meta| this.plan_for(&meta))
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
 
-        // ULTRA-AGGRESSIVE: Always try parallel PK partitioning
-        // Even with small ranges, multiple workers can still help
-        if let Some(ref pk) = meta.pk {
-            println!("  Using parallel PK partitioning with {} workers", self.config.parallelism);
-            self.extract_parallel_pk(&meta, &output_path, start)
-        } else {
-            println!("  No PK detected — using optimized sequential extraction");
-            self.extract_sequential(&meta, &output_path, start)
+    /// This extractor with `table_name`'s configured overrides applied,
+    /// sharing the connection pool and memory budget.
+    pub(crate) fn for_table(&self, table_name: &str) -> Extractor {
+        let mut config = self.config.clone();
+        if let Some(overrides) = self.config.table_overrides(table_name) {
+            if let Some(parallelism) = overrides.parallelism {
+                config.parallelism = parallelism;
+            }
+            if let Some(target_batch_bytes) = overrides.target_batch_bytes {
+                config.target_batch_bytes = target_batch_bytes;
+            }
+            if let Some(ref strategy) = overrides.partition_strategy {
+                config.partition_strategy = strategy.clone();
+            }
+        }
+        Extractor {
+            config,
+            pool: Arc::clone(&self.pool),
+            source: Arc::clone(&self.source),
+            budget: Arc::clone(&self.budget),
+            observer: self.observer.clone(),
+            cancel: self.cancel.clone(),
+            transform: self.transform.clone(),
+            type_mapper: Arc::clone(&self.type_mapper),
+            throttle: Arc::clone(&self.throttle),
         }
     }
 
-    fn load_metadata(&self, table: &str) -> Result<TableMetadata> {
-        let mut conn = self.pool.acquire()?;
+    /// Hand `event` to the progress callback, if there is one.
+    pub(crate) fn emit(&self, event: ProgressEvent) {
+        if let Some(ref observer) = self.observer {
+            observer(event);
+        }
+    }
 
-        // Detect PK
-        let pk = Self::detect_pk(&mut *conn, table)?;
+    /// Connection pool hit/miss/reconnect counters so far.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.stats()
+    }
 
-        // Load columns
-        let columns = Self::load_columns(&mut *conn, table)?;
+    /// Rows counted and time spent waiting under `max_rows_per_sec` and
+    /// `max_concurrent_queries`, over all tables so far.
+    pub fn throttle_stats(&self) -> ThrottleStats {
+        self.throttle.stats()
+    }
 
-        // Get row count
-        let count_sql = format!("SELECT COUNT(*) FROM {}", table);
-        let counts: Vec<(i64,)> = conn.query(&count_sql, ())?;
-        let row_count = counts.first().map(|c| c.0).unwrap_or(0);
+    /// User tables (not views or system tables) by name, with row counts
+    /// estimated from PK index statistics.
+    pub fn list_tables(&self) -> Result<Vec<TableSummary>, ExtractError> {
+        self.source
+            .connect()
+            .and_then(|mut conn| conn.list_tables())
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
 
-        let has_blob = columns.iter().any(|c| matches!(c.data_type, DataType::Utf8 if c.is_text_blob));
+    /// User views (not system ones) by name. Views extract like tables but
+    /// have no PK, so only the `column:` and `rows` strategies partition them.
+    pub fn list_views(&self) -> Result<Vec<String>, ExtractError> {
+        self.source
+            .connect()
+            .and_then(|mut conn| conn.list_views())
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
 
-        Ok(TableMetadata {
-            table_name: table.to_string(),
-            columns,
-            row_count,
-            has_blob,
-            pk,
+    /// The Arrow schema `extract_table` would write for this table, with its
+    /// configured column overrides and any transform applied.
+    pub fn table_schema(&self, table_name: &str) -> Result<SchemaRef, ExtractError> {
+        let this = self.for_table(table_name);
+        this.load_metadata(table_name)
+            .and_then(|meta| this.output_schema(&meta))
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
+
+    /// Row count, primary key and columns of a table, as `extract_table` sees
+    /// them, e.g. to create a downstream table before extracting.
+    pub fn table_info(&self, table_name: &str) -> Result<TableInfo, ExtractError> {
+        let this = self.for_table(table_name);
+        let meta = this
+            .load_metadata(table_name)
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))?;
+        Ok(TableInfo {
+            name: meta.table_name.clone(),
+            row_count: meta.row_count.approx(),
+            row_count_exact: matches!(meta.row_count, RowCount::Exact(_)),
+            pk: meta.output_key().unwrap_or_default(),
+            columns: meta
+                .columns
+                .iter()
+                .map(|c| ColumnInfo { name: c.output_name.clone(), data_type: c.data_type.clone(), blob: c.is_blob() })
+                .collect(),
         })
     }
 
-    fn detect_pk(pool: &mut SimpleConnection, table: &str) -> Result<Option<PrimaryKeyInfo>> {
-        // Find PK index
-        let sql = r#"
-            SELECT ri.rdb$index_name
-            FROM rdb$indices ri
-            WHERE ri.rdb$relation_name = ? 
-            AND ri.rdb$index_type = 1
-        "#;
-        
-        let indices: Vec<(String,)> = pool.query(sql, (table.to_uppercase(),))?;
-        let pk_index_name = match indices.first() {
-            Some((idx,)) => idx.trim().to_string(),
-            None => return Ok(None),
-        };
-
-        // Get PK columns
-        let col_sql = r#"
-            SELECT seg.rdb$field_name
-            FROM rdb$index_segments seg
-            WHERE seg.rdb$index_name = ?
-            ORDER BY seg.rdb$field_position
-        "#;
-        
-        let pk_cols: Vec<(String,)> = pool.query(&col_sql, (pk_index_name.to_uppercase(),))?;
-        let pk_column_names: Vec<String> = pk_cols
-            .iter()
-            .map(|(c,)| c.trim().to_string())
-            .collect();
+    #[instrument(name = "extract_table", skip_all, fields(table = table_name, rows = field::Empty))]
+    fn extract_with_plan(&self, table_name: &str, plan: Option<&[PartitionSpec]>) -> Result<ExtractionStats> {
+        self.cancel.check()?;
+        let start = Instant::now();
+        info!("→ Extracting table: {}", table_name);
+        info!(target: EVENT_TARGET, event = "table_started", table = table_name);
+        let output_path = self.output_path(table_name)?;
+        let replaced = self.replaced_appends(table_name, &output_path);
 
-        if pk_column_names.is_empty() {
-            return Ok(None);
-        }
+        // Load metadata
+        let meta = self.load_metadata(table_name).map_err(|e| ExtractError::tag(ExtractError::Metadata, e))?;
+        let meta = Arc::new(meta);
+        self.check_schema_drift(table_name, &meta, &output_path)?;
+        let merge_key = self.merge_key(table_name, &meta)?;
+        info!("  Rows: {}", meta.row_count.describe());
+        info!("  Columns: {}", meta.columns.len());
+
+        let metadata_loaded = |partitions| ProgressEvent::MetadataLoaded {
+            table: meta.table_name.clone(),
+            rows: meta.row_count.approx(),
+            columns: meta.columns.len(),
+            partitions,
+        };
 
-        // Check if all PK columns are numeric (INTEGER/BIGINT)
-        let type_sql = r#"
-            SELECT rdb$field_type
-            FROM rdb$relation_fields
-            WHERE rdb$relation_name = ? AND rdb$field_name = ?
-        "#;
-        
-        let mut all_numeric = true;
-        for col in &pk_column_names {
-            let types: Vec<(i16,)> = pool.query(type_sql, (table.to_uppercase(), col.to_uppercase()))?;
-            let fb_type = types.first().map(|t| t.0).unwrap_or(0);
-            // 7 = SMALLINT, 8 = INTEGER, 16 = BIGINT
-            if fb_type != 7 && fb_type != 8 && fb_type != 16 {
-                all_numeric = false;
-                break;
-            }
+        if meta.row_count.is_empty() {
+            info!("  (empty table) — skipping");
+            self.emit(metadata_loaded(0));
+            let mut stats = ExtractionStats::default();
+            self.finish_outputs(table_name, &meta, merge_key.as_deref(), &output_path, false, &mut stats)
+                .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+            stats.duration_secs = start.elapsed().as_secs_f64();
+            self.emit(ProgressEvent::Done(stats.clone()));
+            return Ok(stats);
         }
 
-        if !all_numeric {
-            return Ok(None);
+        let plan = match plan {
+            Some(plan) => plan.to_vec(),
+            None => self.plan_for(&meta).map_err(|e| ExtractError::tag(ExtractError::Metadata, e))?,
+        };
+        if plan.is_empty() {
+            let e = anyhow::anyhow!("Partition plan for {} is empty", table_name);
+            return Err(ExtractError::tag(ExtractError::Metadata, e));
         }
+        self.emit(metadata_loaded(plan.len()));
 
-        // Get row count first
-        let count_sql = format!("SELECT COUNT(*) FROM {}", table);
-        let counts: Vec<(i64,)> = pool.query(&count_sql, ())?;
-        let row_count = counts.first().map(|c| c.0).unwrap_or(0);
-
-        // OPTIMIZATION: Skip expensive MIN/MAX for huge tables, but still try partitioning
-        // Even without exact ranges, we can partition by row count
-        if row_count > 10_000_000 && pk_column_names.len() > 1 {
-            // For composite keys on huge tables, use row-based partitioning
-            println!("  Using row-based partitioning (table too large for MIN/MAX)");
-            return Ok(Some(PrimaryKeyInfo {
-                columns: pk_column_names,
-                min_values: vec![0],
-                max_values: vec![row_count],
-                row_count,
-            }));
-        }
-
-        // Get MIN, MAX for first PK column (for partitioning)
-        let first_col = &pk_column_names[0];
-        let stats_sql = format!("SELECT MIN({}), MAX({}) FROM {}", first_col, first_col, table);
-        let stats: Vec<(Option<i64>, Option<i64>)> = pool.query(&stats_sql, ())?;
-        
-        let (min_val, max_val) = stats.first()
-            .and_then(|(min, max)| Some((min.unwrap_or(0), max.unwrap_or(0))))
-            .unwrap_or((0, row_count));
-
-        Ok(Some(PrimaryKeyInfo {
-            columns: pk_column_names,
-            min_values: vec![min_val],
-            max_values: vec![max_val],
-            row_count,
-        }))
-    }
+        let sequential = plan.len() == 1 && plan[0].is_full();
+        self.check_disk_space(&meta, &output_path, !sequential)?;
 
-    fn load_columns(pool: &mut SimpleConnection, table: &str) -> Result<Vec<ColumnMetadata>> {
-        // Get field names first
-        let name_sql = r#"
-            SELECT rdb$field_name
-            FROM rdb$relation_fields
-            WHERE rdb$relation_name = ?
-            ORDER BY rdb$field_position
-        "#;
-        
-        let field_names: Vec<(String,)> = pool.query(name_sql, (table.to_uppercase(),))?;
-        
-        let mut columns = Vec::new();
-        
-        // For each field, get its type from rdb$fields
-        let type_sql = r#"
-            SELECT f.rdb$field_type, f.rdb$field_sub_type
-            FROM rdb$fields f
-            INNER JOIN rdb$relation_fields rf ON f.rdb$field_name = rf.rdb$field_source
-            WHERE rf.rdb$relation_name = ? AND rf.rdb$field_name = ?
-        "#;
-        
-        for (field_name,) in field_names {
-            let col_name = field_name.trim().to_string();
-            let types: Vec<(i16, i16)> = pool.query(type_sql, (table.to_uppercase(), col_name.to_uppercase()))?;
-            let (fb_type, subtype) = types.first().map(|t| (t.0, t.1)).unwrap_or((37, 0)); // Default to VARCHAR
-            
-            let (data_type, is_text_blob) = fb_to_arrow_type(fb_type, subtype);
-            columns.push(ColumnMetadata {
-                name: col_name,
-                data_type,
-                is_text_blob,
-            });
+        let mut stats = if sequential {
+            info!("  Using optimized sequential extraction");
+            self.extract_sequential(&meta, &output_path, start)?
+        } else {
+            info!("  Using parallel partitioning with {} workers", plan.len());
+            self.extract_parallel(&meta, &plan, &output_path, start)?
+        };
+        // A transform may drop rows, and skipped partitions leave theirs out
+        if stats.failed_partitions == 0 && self.transform.is_none() {
+            check_rows_windows(table_name, &plan, stats.rows_extracted)?;
+        }
+        stats.null_key_rows = null_key_rows(&plan, &stats.partitions);
+        if stats.null_key_rows > 0 {
+            info!("  {} rows with a NULL partition key", format_number(stats.null_key_rows as i64));
+        }
+        stats.special_floats = meta.special_floats_seen.load(Ordering::Relaxed);
+        if stats.special_floats > 0 {
+            let action = if meta.special_floats == SpecialFloats::Null { "written as null" } else { "kept" };
+            warn!("  ⚠ {} NaN/Infinity values {}", format_number(stats.special_floats as i64), action);
         }
+        stats.text_issues = meta.text_issues();
+        for (column, issues) in &stats.text_issues {
+            warn!(
+                "  ⚠ {}: {} NUL characters{}, {} replacement characters (U+FFFD)",
+                column,
+                format_number(issues.nul_bytes as i64),
+                if meta.nul_bytes == NulBytes::Strip { " stripped" } else { "" },
+                format_number(issues.replacement_chars as i64)
+            );
+        }
+        if self.config.validate {
+            self.validate_output(&meta, &output_path, &mut stats)?;
+        }
+        if self.config.pk_gap_report {
+            self.report_pk_gaps(&meta, &output_path, &mut stats)?;
+        }
+        self.record_output(table_name, &output_path, &replaced)
+            .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        self.finish_outputs(table_name, &meta, merge_key.as_deref(), &output_path, true, &mut stats)
+            .map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        info!(
+            target: EVENT_TARGET,
+            event = "table_completed",
+            table = table_name,
+            output = %output_path.display(),
+            rows = stats.rows_extracted,
+            duration_secs = stats.duration_secs,
+            file_size_mb = stats.file_size_mb,
+            failed_partitions = stats.failed_partitions,
+        );
+        Span::current().record("rows", stats.rows_extracted);
+        self.emit(ProgressEvent::Done(stats.clone()));
+        Ok(stats)
+    }
 
-        Ok(columns)
+    /// The key `merge_target` merges rows by, None without a merge target.
+    /// Fails up front when merging or tombstones need a primary key the table
+    /// doesn't have.
+    pub(crate) fn merge_key(&self, table_name: &str, meta: &TableMetadata) -> Result<Option<Vec<String>>> {
+        let merge_key = match (&self.config.merge_target, meta.output_key()) {
+            (None, _) => None,
+            (Some(_), Some(key)) => Some(key),
+            (Some(_), None) => {
+                let e = anyhow::anyhow!("{} has no primary key to merge rows by", table_name);
+                return Err(ExtractError::tag(ExtractError::Validation, e));
+            }
+        };
+        if meta.tombstone_filter.is_some() && meta.pk.is_none() {
+            let e = anyhow::anyhow!("{} has no primary key to write tombstones by", table_name);
+            return Err(ExtractError::tag(ExtractError::Validation, e));
+        }
+        Ok(merge_key)
     }
 
-    fn extract_parallel_pk(
+    /// Once the output is complete, write the table's DDL and the tombstone
+    /// file and merge both into the merge target. `written` is false for empty
+    /// tables, which have no output.
+    pub(crate) fn finish_outputs(
         &self,
+        table_name: &str,
         meta: &TableMetadata,
+        merge_key: Option<&[String]>,
         output_path: &Path,
-        start: Instant,
-    ) -> Result<ExtractionStats> {
-        let pk = meta.pk.as_ref().unwrap();
-        let parallelism = self.config.parallelism;
+        written: bool,
+        stats: &mut ExtractionStats,
+    ) -> Result<()> {
+        if self.config.export_ddl {
+            stats.output_files.extend(self.write_ddl(table_name)?);
+        }
+        let tombstones = match (&meta.tombstone_filter, &meta.pk) {
+            (Some(_), Some(pk)) => {
+                let (path, rows) = self.write_tombstones(table_name, meta, &pk.columns, output_path)?;
+                stats.tombstones = rows;
+                stats.output_files.push(path.clone());
+                Some(path)
+            }
+            _ => None,
+        };
+        if let Some(key) = merge_key {
+            let output = written.then_some(output_path);
+            stats.merge = Some(self.merge_into_target(table_name, key, output, tombstones.as_deref())?);
+        }
+        Ok(())
+    }
 
-        // Calculate large batch size (500K-1M rows)
-        let batch_size = calculate_batch_size(meta.row_count, meta.has_blob);
+    /// `<out_dir>/<table>.parquet`
+    fn base_output_path(&self, table_name: &str) -> PathBuf {
+        self.config.out_dir.join(format!("{}.parquet", output_stem(table_name)))
+    }
 
-        // Partition PK range
-        let pk_range = pk.max_values[0] - pk.min_values[0];
-        let rows_per_partition = (meta.row_count as f64 / parallelism as f64).ceil() as i64;
-        let pk_step = if pk_range > 0 { pk_range as f64 / parallelism as f64 } else { 1.0 };
+    /// Where this run writes `table_name` under `output_mode`: the base path,
+    /// or in append mode the next free `<table>.<n>.parquet` beside it.
+    pub(crate) fn output_path(&self, table_name: &str) -> Result<PathBuf> {
+        let base = self.base_output_path(table_name);
+        if !base.exists() {
+            return Ok(base);
+        }
+        match self.config.output_mode {
+            OutputMode::FailIfExists => {
+                let e = anyhow::anyhow!(
+                    "{} already exists; use --output-mode overwrite to replace it or append to add a file beside it",
+                    base.display()
+                );
+                Err(ExtractError::tag(ExtractError::Validation, e))
+            }
+            OutputMode::Overwrite => Ok(base),
+            OutputMode::Append => Ok((1..)
+                .map(|n| base.with_extension(format!("{}.parquet", n)))
+                .find(|path| !path.exists())
+                .expect("some append index is free")),
+        }
+    }
 
-        println!("  Batch size: {}", format_number(batch_size as i64));
-        println!("  Partitions: {}", parallelism);
-        println!("  Rows per partition: ~{}", format_number(rows_per_partition));
+    /// Before an append run, compare the schema `meta` will be written with to
+    /// the one the newest file of the dataset recorded. Added, dropped or
+    /// retyped columns fail the run unless `allow_schema_evolution` is set.
+    pub(crate) fn check_schema_drift(&self, table_name: &str, meta: &TableMetadata, output_path: &Path) -> Result<()> {
+        let base = self.base_output_path(table_name);
+        if output_path == base {
+            return Ok(());
+        }
+        let Some(previous) = Manifest::latest_schema(&base)? else {
+            debug!("  No schema recorded for {}; skipping drift check", base.display());
+            return Ok(());
+        };
+        let changes = schema_changes(&previous, &schema_fields(&self.output_schema(meta)?));
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if self.config.allow_schema_evolution {
+            warn!("  ⚠ Schema of {} changed since the previous file: {}", table_name, changes.join("; "));
+            return Ok(());
+        }
+        let e = anyhow::anyhow!(
+            "Schema of {} changed since the previous file ({}); pass --allow-schema-evolution to append anyway",
+            table_name,
+            changes.join("; ")
+        );
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
 
-        // Create temp files for each partition
-        let temp_dir = output_path.parent().unwrap();
-        let temp_files: Vec<PathBuf> = (0..parallelism)
-            .map(|i| temp_dir.join(format!("{}_part_{}.parquet", output_path.file_stem().unwrap().to_str().unwrap(), i)))
+    /// Files an overwrite of the base output replaces besides it: those earlier
+    /// append runs listed in its manifest. Empty in append mode; an unreadable
+    /// manifest is logged, not fatal, so a broken dataset can still be replaced.
+    pub(crate) fn replaced_appends(&self, table_name: &str, output_path: &Path) -> Vec<PathBuf> {
+        let manifest_path = Manifest::path_for(output_path);
+        if output_path != self.base_output_path(table_name) || !manifest_path.exists() {
+            return Vec::new();
+        }
+        match Manifest::read(&manifest_path) {
+            Ok(manifest) => manifest.appended_files,
+            Err(e) => {
+                warn!("  Not removing appended files of {}: {:#}", output_path.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// After a run, list `output_path` in the base output's manifest if it was
+    /// appended, or else delete the `replaced` appended files (and their
+    /// manifests): the new base manifest starts the dataset over without them.
+    pub(crate) fn record_output(&self, table_name: &str, output_path: &Path, replaced: &[PathBuf]) -> Result<()> {
+        let base = self.base_output_path(table_name);
+        if output_path != base {
+            info!("  Appended beside {}", base.display());
+            return Manifest::record_append(table_name, &base, output_path);
+        }
+        for path in replaced {
+            for path in [path.clone(), Manifest::path_for(path)] {
+                match std::fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !replaced.is_empty() {
+            info!("  Removed {} files appended before the overwrite", replaced.len());
+        }
+        Ok(())
+    }
+
+    /// Where partition `index` of `output_path` is staged before the merge:
+    /// `<temp_dir or out_dir>/<table>_part_<i>.parquet`
+    pub(crate) fn partition_temp_path(&self, output_path: &Path, index: usize) -> PathBuf {
+        let stem = output_path.file_stem().unwrap().to_str().unwrap();
+        let file_name = format!("{}_part_{}.parquet", stem, index);
+        match self.config.temp_dir {
+            Some(ref temp_dir) => temp_dir.join(file_name),
+            None => output_path.with_file_name(file_name),
+        }
+    }
+
+    /// Fail before extracting if the output (and, when `staged`, the partition
+    /// temp files) clearly won't fit. The size is estimated from the row count
+    /// and the width of a small sample; without a row count the check is skipped.
+    fn check_disk_space(&self, meta: &TableMetadata, output_path: &Path, staged: bool) -> Result<()> {
+        let Some(rows) = meta.row_count.approx() else {
+            return Ok(());
+        };
+        let row_bytes = match self.sample_row_bytes(meta) {
+            Ok(Some(row_bytes)) => row_bytes,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                warn!("  Disk space check skipped ({:#})", e);
+                return Ok(());
+            }
+        };
+        let estimate = (row_bytes * rows.max(0) as f64) as u64;
+
+        let out_dir = output_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let mut needs = vec![(out_dir, estimate)];
+        if staged {
+            let temp_dir = self.partition_temp_path(output_path, 0).parent().unwrap_or(Path::new(".")).to_path_buf();
+            if resources::same_filesystem(&temp_dir, &needs[0].0) {
+                needs[0].1 += estimate;
+            } else {
+                needs.push((temp_dir, estimate));
+            }
+        }
+
+        for (dir, need) in needs {
+            let Some(free) = resources::available_disk(&dir) else {
+                continue;
+            };
+            if free < need {
+                let e = anyhow::anyhow!(
+                    "Not enough disk space in {}: ~{:.1} GB needed for {} (estimated), {:.1} GB free; free up space or point --temp-dir elsewhere",
+                    dir.display(),
+                    need as f64 / GB,
+                    meta.table_name,
+                    free as f64 / GB
+                );
+                return Err(ExtractError::tag(ExtractError::Validation, e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Average in-memory row size over the first 1000 rows.
+    fn sample_row_bytes(&self, meta: &TableMetadata) -> Result<Option<f64>> {
+        let rows = self.source.connect()?.fetch_all(&meta.select(false, &PartitionSpec::rows(1, 1000)))?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(estimate_rows_bytes(&rows) as f64 / rows.len() as f64))
+    }
+
+    /// Build the partition plan for the configured strategy.
+    pub(crate) fn plan_for(&self, meta: &TableMetadata) -> Result<Vec<PartitionSpec>> {
+        let parts = self.config.parallelism;
+        if self.config.deterministic {
+            // One ordered scan into one writer; partitions would interleave row groups by timing
+            return Ok(vec![PartitionSpec::full()]);
+        }
+        if is_system_table(&meta.table_name) {
+            // Small, and a monitoring table's partitions would each read another snapshot
+            info!("  System table — using a single partition");
+            return Ok(vec![PartitionSpec::full()]);
+        }
+        match self.config.partition_strategy {
+            PartitionStrategy::None => Ok(vec![PartitionSpec::full()]),
+            PartitionStrategy::RowsPaging => {
+                // An estimate could undercount and leave rows past the last window
+                let row_count = match meta.row_count {
+                    RowCount::Exact(n) => n,
+                    _ => self.source.connect()?.count_selected(&meta.select(false, &PartitionSpec::full()))?,
+                };
+                Ok(partition::rows_paging_partitions(row_count, parts))
+            }
+            PartitionStrategy::Column(ref column) => self.plan_column_partitions(meta, column, parts),
+            PartitionStrategy::RangePk | PartitionStrategy::HashPk | PartitionStrategy::Auto => match meta.pk {
+                Some(ref pk) => self.plan_pk_partitions(meta, pk, parts),
+                None => {
+                    info!("  No PK detected — falling back to a single partition");
+                    Ok(vec![PartitionSpec::full()])
+                }
+            },
+        }
+    }
+
+    /// Linear MIN/MAX split on a user-chosen integer column.
+    fn plan_column_partitions(&self, meta: &TableMetadata, column: &str, parts: usize) -> Result<Vec<PartitionSpec>> {
+        let col = find_column(&meta.columns, column)
+            .map(|i| &meta.columns[i])
+            .with_context(|| format!("Partition column {} not found in {}", column, meta.table_name))?;
+        if col.data_type != DataType::Int64 {
+            anyhow::bail!("Partition column {} must be an integer column", col.name);
+        }
+
+        let (min, max) = match self.source.connect()?.int_range(&meta.table_name, &col.name, None)? {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Ok(vec![PartitionSpec::full()]),
+        };
+
+        // Unlike a PK, the column may be NULL, and no range matches those rows
+        let mut partitions = partition::range_partitions(&col.name, &partition::linear_boundaries(min, max, parts));
+        partitions.push(PartitionSpec::null_key(&col.name));
+        Ok(partitions)
+    }
+
+    pub(crate) fn load_metadata(&self, table: &str) -> Result<TableMetadata> {
+        let mut conn = self.source.connect()?;
+        let table = &resolve_table(&mut *conn, table)?;
+        let fields = conn.fields(table)?;
+
+        // Detect PK (only range planning needs the key range). System tables
+        // have unique indices but no PK constraint, and are never partitioned
+        let load_range = matches!(self.config.partition_strategy, PartitionStrategy::RangePk | PartitionStrategy::Auto);
+        let pk = match is_system_table(table) {
+            true => None,
+            false => Self::detect_pk(&mut *conn, table, &fields, load_range)?,
+        };
+
+        let overrides = self.config.table_overrides(table);
+        let soft_delete = match overrides.and_then(|o| o.soft_delete.as_ref()) {
+            Some(soft_delete) => Some((soft_delete.mode, deleted_predicate(table, &fields, &soft_delete.column)?)),
+            None => None,
+        };
+
+        // Load columns
+        let mut columns = Self::load_columns(fields, &*self.type_mapper, self.config.timestamp_zone);
+        for column in &mut columns {
+            column.output_name = self.config.column_case.apply(&column.name);
+        }
+        if let Some(overrides) = overrides {
+            columns = apply_column_overrides(table, columns, overrides)?;
+        }
+        let mut output_names = HashSet::new();
+        if let Some(column) = columns.iter().find(|c| !output_names.insert(c.output_name.as_str())) {
+            let e = anyhow::anyhow!("Two columns of {} would both be written as {}", table, column.output_name);
+            return Err(ExtractError::tag(ExtractError::Validation, e));
+        }
+        let salted = matches!(self.config.mask_salt, Some(ref salt) if !salt.is_empty());
+        if !salted && columns.iter().any(|c| matches!(c.mask, Some(Mask::Hash | Mask::Fake(_)))) {
+            warn!("  ⚠ {} hashes columns without a mask salt; short values can be recovered by brute force", table);
+        }
+
+        // The row filter is mandatory; the `where` override and soft deletes narrow it further
+        let mut predicates: Vec<String> =
+            overrides.into_iter().flat_map(|o| [o.row_filter.clone(), o.filter.clone()]).flatten().collect();
+        if let Some((SoftDeleteMode::Exclude | SoftDeleteMode::Tombstone, ref deleted)) = soft_delete {
+            predicates.push(format!("NOT ({})", deleted));
+        }
+        let filter = match predicates.len() {
+            0 | 1 => predicates.pop(),
+            _ => Some(predicates.iter().map(|p| format!("({})", p)).collect::<Vec<_>>().join(" AND ")),
+        };
+        let tombstone_filter = match soft_delete {
+            Some((SoftDeleteMode::Tombstone, deleted)) => Some(deleted),
+            _ => None,
+        };
+
+        // COUNT(*) is a full scan in Firebird; --skip-count reads index statistics instead,
+        // which only count the whole table
+        let row_count = match filter {
+            // Counted in another transaction, so against another snapshot
+            _ if is_monitoring_table(table) => RowCount::Unknown,
+            _ if self.config.skip_count && filter.is_some() => RowCount::Unknown,
+            _ if self.config.skip_count => match conn.estimate_row_count(table)? {
+                Some(n) => RowCount::Estimated(n),
+                None => RowCount::Unknown,
+            },
+            Some(ref filter) => {
+                let select = Select {
+                    table: table.to_string(),
+                    columns: Vec::new(),
+                    db_key: false,
+                    partition: PartitionSpec::predicate(filter.clone()),
+                    order_by: Vec::new(),
+                };
+                RowCount::Exact(conn.count_selected(&select)?)
+            }
+            None => RowCount::Exact(conn.count_rows(table)?),
+        };
+
+        let has_blob = columns.iter().any(|c| matches!(c.data_type, DataType::Utf8 if c.is_text_blob));
+        let blob_files = match self.config.blob_mode {
+            BlobMode::External if columns.iter().any(|c| c.is_blob()) => {
+                for column in columns.iter_mut().filter(|c| c.is_blob() && c.mask.is_none()) {
+                    column.external = true;
+                    column.data_type = sidecar::sidecar_type();
+                }
+                // Masked keys would leak into file names
+                let key_columns: Option<Vec<usize>> = match pk {
+                    Some(ref pk) => pk
+                        .columns
+                        .iter()
+                        .map(|name| find_column(&columns, name).filter(|&i| columns[i].mask.is_none()))
+                        .collect(),
+                    None => None,
+                };
+                Some(BlobFiles::new(&self.config.out_dir, table, key_columns.unwrap_or_default()))
+            }
+            _ => None,
+        };
+        if self.config.large_blobs {
+            for column in columns.iter_mut().filter(|c| c.is_blob() && !c.external && c.mask.is_none()) {
+                column.data_type = match column.data_type {
+                    DataType::Utf8 => DataType::LargeUtf8,
+                    DataType::Binary => DataType::LargeBinary,
+                    ref other => other.clone(),
+                };
+            }
+        }
+        if self.config.auto_dictionary {
+            dictionary::choose_columns(&mut *conn, table, filter.as_deref(), &mut columns)?;
+        }
+
+        // Without a PK, sort by every column that can be sorted; ties are then identical rows
+        let order_by = match pk {
+            _ if !self.config.deterministic => Vec::new(),
+            Some(ref pk) => pk.columns.clone(),
+            // Firebird can't sort by a BLOB, whatever type it's mapped to
+            None => columns
+                .iter()
+                .filter(|c| !c.is_blob() && c.field.field_type != 261)
+                .map(|c| c.name.clone())
+                .collect(),
+        };
+        if self.config.deterministic && order_by.is_empty() {
+            warn!("  ⚠ {} has no sortable columns; row order may differ between runs", table);
+        }
+
+        Ok(TableMetadata {
+            table_name: table.to_string(),
+            columns,
+            row_count,
+            has_blob,
+            pk,
+            filter,
+            tombstone_filter,
+            type_mapper: Arc::clone(&self.type_mapper),
+            special_floats: self.config.special_floats,
+            special_floats_seen: Arc::new(AtomicUsize::new(0)),
+            nul_bytes: self.config.nul_bytes,
+            text_counts: columns.iter().map(|_| TextCounts::default()).collect(),
+            order_by,
+            mask_salt: self.config.mask_salt.as_deref().unwrap_or_default().as_bytes().into(),
+            blob_files,
+        })
+    }
+
+    pub(crate) fn count_rows(&self, table: &str) -> Result<i64> {
+        let mut conn = self.source.connect()?;
+        let table = resolve_table(&mut *conn, table)?;
+        conn.count_rows(&table)
+    }
+
+    fn detect_pk(
+        conn: &mut dyn SourceConnection,
+        table: &str,
+        fields: &[FieldInfo],
+        load_range: bool,
+    ) -> Result<Option<PrimaryKeyInfo>> {
+        let pk_column_names = conn.primary_key(table)?;
+        if pk_column_names.is_empty() {
+            return Ok(None);
+        }
+
+        // Check if all PK columns are numeric (INTEGER/BIGINT)
+        let fb_types: Vec<i16> = pk_column_names
+            .iter()
+            .map(|col| {
+                let field = fields.iter().find(|f| f.column.eq_ignore_ascii_case(col));
+                field.map(|f| f.field_type).unwrap_or(0)
+            })
             .collect();
 
-        // Parallel extraction with multiple writers
-        let pool = Arc::clone(&self.pool);
+        // 7 = SMALLINT, 8 = INTEGER, 16 = BIGINT
+        let all_numeric = fb_types.iter().all(|t| matches!(t, 7 | 8 | 16));
+        // 14 = CHAR, 37 = VARCHAR: partition on sampled string boundaries
+        let text_key = !all_numeric && matches!(fb_types[0], 14 | 37);
+
+        if !all_numeric && !text_key {
+            return Ok(None);
+        }
+
+        // First and last key for range partitioning, read off the PK index;
+        // composite keys use their first column's, the index's leading segment
+        let range = match text_key || !load_range {
+            true => None,
+            false => match conn.key_range(table, &pk_column_names[0])? {
+                (Some(min), Some(max)) => Some((min, max)),
+                _ => None,
+            },
+        };
+
+        Ok(Some(PrimaryKeyInfo { columns: pk_column_names, range, text_key }))
+    }
+
+    fn load_columns(fields: Vec<FieldInfo>, mapper: &dyn TypeMapper, zone: TimestampZone) -> Vec<ColumnMetadata> {
+        fields
+            .into_iter()
+            .map(|field| FieldInfo { timestamp_zone: zone, ..field })
+            .map(|field| ColumnMetadata {
+                name: field.column.clone(),
+                data_type: mapper.arrow_type(&field),
+                is_text_blob: field.field_type == 261 && field.sub_type == 1,
+                output_name: field.column.clone(),
+                field,
+                mask: None,
+                external: false,
+            })
+            .collect()
+    }
+
+    fn extract_parallel(
+        &self,
+        meta: &TableMetadata,
+        partitions: &[PartitionSpec],
+        output_path: &Path,
+        start: Instant,
+    ) -> Result<ExtractionStats> {
+        let parallelism = partitions.len();
+
+        // Each partition adapts its batch size to the observed row width
+        let sizer = self.batch_sizer(meta);
+        debug!("  Partitions: {}", parallelism);
+        if let Some(row_count) = meta.row_count.approx() {
+            let rows_per_partition = (row_count as f64 / parallelism as f64).ceil() as i64;
+            debug!("  Rows per partition: ~{}", format_number(rows_per_partition));
+        }
+
+        // Create temp files for each partition
+        let temp_files: Vec<PathBuf> = (0..parallelism).map(|i| self.partition_temp_path(output_path, i)).collect();
+
+        // Parallel extraction feeding a shared pool of writer threads
+        // Several writers would order row groups by timing
+        let writer_threads = match self.config.deterministic {
+            true => 1,
+            false => self.config.writer_threads.unwrap_or(parallelism),
+        };
+        debug!("  Writer threads: {}", writer_threads);
+        let queue_depth = self.config.prefetch_depth.unwrap_or(writer_threads * 2);
+        let writers = WriterPool::new(writer_threads, queue_depth, Arc::clone(&self.budget), self.cancel.clone());
+        let temp_props = self.temp_writer_props();
+        let schema = self.output_schema(meta)?;
+        let blobs = self.blob_fetcher(meta)?;
+
         let meta_arc = Arc::new(meta.clone());
-        let results: Vec<Result<PartitionResult>> = (0..parallelism)
-            .into_par_iter()
-            .map(|i| {
-                let start_pk = pk.min_values[0] + (pk_step * i as f64) as i64;
-                let end_pk = if i == parallelism - 1 {
-                    pk.max_values[0]
-                } else {
-                    pk.min_values[0] + (pk_step * (i + 1) as f64) as i64
-                };
+        let retry = &self.config.retry;
+        let partition_times: Vec<Arc<StageTimes>> = (0..parallelism).map(|_| Arc::new(StageTimes::default())).collect();
+        let progress = TableProgress::new(
+            self.config.progress,
+            &meta.table_name,
+            meta.row_count.approx(),
+            self.observer.clone(),
+        );
+        // Worker threads don't inherit the current span
+        let table_span = Span::current();
+        let run = |i: usize| -> (Result<PartitionResult>, usize, f64) {
+            let span = info_span!(parent: &table_span, "partition", partition = i, rows = field::Empty);
+            let _entered = span.enter();
+            let temp_path = &temp_files[i];
+            let times = &partition_times[i];
+            info!(target: EVENT_TARGET, event = "partition_started", table = %meta.table_name, partition = i);
+            self.emit(ProgressEvent::PartitionStarted { partition: i });
+            let started = Instant::now();
+            let part_progress = progress.partition(i);
+            let mut attempts = 0;
+            let result = retry.run(&format!("Partition {}", i), || {
+                self.cancel.check()?;
+                attempts += 1;
+                // Start every attempt from a clean temp file
+                let _ = std::fs::remove_file(temp_path);
+                part_progress.reset();
+                let sink = writers.sink(temp_path.clone(), Arc::clone(&schema), temp_props.clone(), Arc::clone(times));
+                extract_partition(
+                    &*self.source,
+                    meta_arc.clone(),
+                    &partitions[i],
+                    sizer.clone(),
+                    sink,
+                    &self.budget,
+                    times,
+                    blobs.as_ref(),
+                    &part_progress,
+                    &self.cancel,
+                    self.transform.as_ref(),
+                )
+            });
+            if let Ok(ref part) = result {
+                span.record("rows", part.rows);
+            }
+            match result {
+                Ok(ref part) => info!(
+                    target: EVENT_TARGET,
+                    event = "partition_completed",
+                    table = %meta.table_name,
+                    partition = i,
+                    rows = part.rows,
+                    bytes = part.bytes,
+                    duration_secs = started.elapsed().as_secs_f64(),
+                ),
+                Err(ref e) => warn!(
+                    target: EVENT_TARGET,
+                    event = "partition_failed",
+                    table = %meta.table_name,
+                    partition = i,
+                    duration_secs = started.elapsed().as_secs_f64(),
+                    error = %format!("{:#}", e),
+                ),
+            }
+            self.emit(ProgressEvent::PartitionFinished {
+                partition: i,
+                rows: result.as_ref().map_or(0, |part| part.rows),
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            });
+            (result, attempts.saturating_sub(1), started.elapsed().as_secs_f64())
+        };
+
+        // Largest partitions first, so the run doesn't end with one big
+        // partition running alone while the other workers sit idle
+        let order = partition::largest_first(partitions);
+        if order.iter().enumerate().any(|(position, &i)| position != i) {
+            debug!("  Partition order, largest first: {:?}", order);
+        }
+        let workers = self.config.parallelism.clamp(1, parallelism);
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<Option<(Result<PartitionResult>, usize, f64)>> = (0..parallelism).map(|_| None).collect();
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        while let Some(&i) = order.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            done.push((i, run(i)));
+                        }
+                        done
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (i, result) in handle.join().expect("partition worker panicked") {
+                    results[i] = Some(result);
+                }
+            }
+        });
+        let results: Vec<(Result<PartitionResult>, usize, f64)> =
+            results.into_iter().map(|result| result.expect("every partition ran")).collect();
+        drop(progress);
+        drop(writers);
+        drop(blobs);
+
+        // Collect results
+        let mut totals = PartitionResult::default();
+        let mut partition_stats = Vec::with_capacity(parallelism);
+        let mut partition_files = Vec::new();
+        let mut missing = Vec::new();
+        
+        for (i, (result, retries, duration_secs)) in results.into_iter().enumerate() {
+            let mut stats = PartitionStats {
+                index: i,
+                range: partitions[i].to_string(),
+                duration_secs,
+                retries,
+                ..PartitionStats::default()
+            };
+            match result {
+                Ok(part_result) => {
+                    if part_result.rows > 0 {
+                        partition_files.push(temp_files[i].clone());
+                    }
+                    debug!("  Partition {}: {} rows", i, format_number(part_result.rows as i64));
+                    stats.rows = part_result.rows;
+                    stats.rows_per_sec = rows_per_sec(part_result.rows, duration_secs);
+                    stats.bytes_read = part_result.bytes;
+                    stats.batches = part_result.batches;
+                    totals.absorb(&part_result);
+                }
+                Err(e) => {
+                    warn!("  Partition {} failed: {:#}", i, e);
+                    stats.error = Some(format!("{:#}", e));
+                    missing.push(MissingPartition {
+                        index: i,
+                        partition: partitions[i].clone(),
+                        error: format!("{:#}", e),
+                    });
+                }
+            }
+            partition_stats.push(stats);
+        }
+        let total_rows = totals.rows;
+
+        if self.cancel.is_cancelled() {
+            for temp_file in &temp_files {
+                let _ = std::fs::remove_file(temp_file);
+            }
+            return Err(cancel::cancelled());
+        }
+
+        if !missing.is_empty() && !self.config.allow_partial {
+            for temp_file in &temp_files {
+                let _ = std::fs::remove_file(temp_file);
+            }
+            let e = anyhow::anyhow!(
+                "{} of {} partitions of {} failed (partition {}: {}); no output written",
+                missing.len(),
+                parallelism,
+                meta.table_name,
+                missing[0].index,
+                missing[0].error
+            );
+            return Err(ExtractError::tag(ExtractError::PartitionFailed, e));
+        }
+
+        // Merge temp files into final output
+        info!("  Merging {} partition files...", partition_files.len());
+        self.emit(ProgressEvent::MergeStarted { files: partition_files.len() });
+        let times = StageTimes::default();
+        let props = self.output_writer_props(&schema);
+        let merged = times.time(Stage::Merge, || merge_parquet_files(&partition_files, output_path, props));
+
+        // Cleanup temp files
+        for temp_file in &temp_files {
+            let _ = std::fs::remove_file(temp_file);
+        }
+        merged.map_err(|e| ExtractError::tag(ExtractError::Merge, e))?;
+
+        if self.config.verify_unique {
+            self.verify_unique_output(meta, output_path, !missing.is_empty())?;
+        }
+        let source_rows = self.verify_row_count(meta, total_rows, !missing.is_empty(), None)?;
+
+        if self.config.profile {
+            info!("  Profile (seconds summed over threads):");
+            for (i, partition) in partition_times.iter().enumerate() {
+                info!("    Partition {}: {}", i, partition.summary());
+                times.absorb(partition);
+            }
+            info!("    Total: {}", times.summary());
+            info!("    Bottleneck: {}", times.bottleneck());
+        }
+
+        let failed_partitions = missing.len();
+        let mut manifest = self.manifest(&meta.table_name, output_path);
+        manifest.rows = total_rows;
+        manifest.partitions = parallelism;
+        manifest.partial = !missing.is_empty();
+        manifest.source_rows = source_rows;
+        manifest.schema = schema_fields(&schema);
+        manifest.missing_partitions = missing;
+        manifest.partition_stats = partition_stats.clone();
+        manifest.write().map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        if manifest.partial {
+            warn!(
+                "  ⚠ Partial output: {} partitions missing, see {}",
+                failed_partitions,
+                Manifest::path_for(output_path).display()
+            );
+        }
+
+        let duration = start.elapsed().as_secs_f64();
+        let file_size_mb = std::fs::metadata(output_path)
+            .map(|m| m.len() as f64 / (1024.0 * 1024.0))
+            .unwrap_or(0.0);
+
+        info!(
+            "  ✓ Done: {} rows → {} in {} ({:.1} MB, {:.0} rows/s)",
+            format_number(total_rows as i64),
+            output_path.display(),
+            format_duration(duration),
+            file_size_mb,
+            total_rows as f64 / duration
+        );
+        log_partition_table(&partition_stats);
+
+        Ok(ExtractionStats {
+            rows_extracted: total_rows,
+            duration_secs: duration,
+            file_size_mb,
+            failed_partitions,
+            bytes_read: totals.bytes,
+            batches: totals.batches,
+            null_counts: totals.null_counts(&schema),
+            output_files: vec![output_path.to_path_buf(), Manifest::path_for(output_path)],
+            partitions: partition_stats,
+            null_key_rows: 0,
+            special_floats: 0,
+            text_issues: BTreeMap::new(),
+            tombstones: 0,
+            merge: None,
+        })
+    }
+
+    /// Fail if any PK value appears more than once in the merged output, or
+    /// if an integer PK's range in the output doesn't match the source's.
+    pub(crate) fn verify_unique_output(&self, meta: &TableMetadata, output_path: &Path, partial: bool) -> Result<()> {
+        let pk = match meta.pk {
+            Some(ref pk) => pk,
+            None => {
+                info!("  No PK — skipping uniqueness check");
+                return Ok(());
+            }
+        };
+
+        info!("  Verifying PK uniqueness...");
+        let scan = scan_keys(output_path, &meta.output_key().unwrap_or_default())?;
+        if scan.duplicates > 0 {
+            let e = anyhow::anyhow!(
+                "{} duplicate PK values in {} (e.g. {}) — partition boundaries overlap",
+                format_number(scan.duplicates as i64),
+                output_path.display(),
+                scan.samples.join("; ")
+            );
+            return Err(ExtractError::tag(ExtractError::Validation, e));
+        }
+        info!("  ✓ PK values unique");
+        match scan.first_range {
+            Some(range) => self.verify_key_range(meta, &pk.columns[0], range, partial),
+            None => Ok(()),
+        }
+    }
+
+    /// Compare the output's range of integer key `column` with the source's:
+    /// a missing end means the first or last partition boundary dropped rows.
+    /// Skipped for filtered tables, whose output needn't reach either end.
+    fn verify_key_range(&self, meta: &TableMetadata, column: &str, output: (i64, i64), partial: bool) -> Result<()> {
+        if meta.filter.is_some() {
+            debug!("  Filtered table — skipping PK range check");
+            return Ok(());
+        }
+        let (Some(min), Some(max)) = self.source.connect()?.key_range(&meta.table_name, column)? else {
+            return Ok(());
+        };
+        if output == (min, max) {
+            info!("  ✓ PK range {}..{} covered", min, max);
+            return Ok(());
+        }
+        let e = anyhow::anyhow!(
+            "{} spans {}..{} in the output but {}..{} in {} — partition boundaries lost rows, or it changed",
+            column,
+            output.0,
+            output.1,
+            min,
+            max,
+            meta.table_name
+        );
+        if partial {
+            warn!("  ⚠ {}", e);
+            return Ok(());
+        }
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
+
+    /// With `verify_counts`, compare the rows written with the table's count
+    /// (narrowed by its filter). `counted` is that count taken in the
+    /// extraction's own transaction, which a sequential extraction has, so it
+    /// checks the read against the snapshot it read. Without one the table is
+    /// re-counted in a new transaction, and rows changed during the extraction
+    /// show up as a mismatch too. A mismatch fails the extraction unless the
+    /// output is already partial.
+    pub(crate) fn verify_row_count(
+        &self,
+        meta: &TableMetadata,
+        written: usize,
+        partial: bool,
+        counted: Option<i64>,
+    ) -> Result<Option<i64>> {
+        if !self.config.verify_counts {
+            return Ok(None);
+        }
+        info!("  Verifying row count...");
+        let source_rows = match counted {
+            Some(counted) => counted,
+            None => self.source.connect()?.count_selected(&meta.select(false, &PartitionSpec::full()))?,
+        };
+        if source_rows == written as i64 {
+            info!("  ✓ Row count matches the source");
+            return Ok(Some(source_rows));
+        }
+        let e = anyhow::anyhow!(
+            "{} rows written but {} has {} — partitions overlap or missed rows, or the table changed meanwhile",
+            format_number(written as i64),
+            meta.table_name,
+            format_number(source_rows)
+        );
+        if partial {
+            warn!("  ⚠ {}", e);
+            return Ok(Some(source_rows));
+        }
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
+
+    /// Compare per-column aggregates of the source and the output and write
+    /// the report beside the output. A mismatch fails the extraction unless
+    /// the output is already partial.
+    pub(crate) fn validate_output(
+        &self,
+        meta: &TableMetadata,
+        output_path: &Path,
+        stats: &mut ExtractionStats,
+    ) -> Result<()> {
+        if self.transform.is_some() {
+            warn!("  A batch transform is set — skipping validation");
+            return Ok(());
+        }
+        if is_monitoring_table(&meta.table_name) {
+            warn!("  Monitoring tables change with every snapshot — skipping validation");
+            return Ok(());
+        }
+        info!("  Validating column aggregates...");
+        let mut conn = self.pool.acquire()?;
+        let _query = self.throttle.query();
+        let report = validate::validate(&mut *conn, meta, output_path)?;
+        let report_path = report.write(output_path).map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        stats.output_files.push(report_path.clone());
+        if report.matches {
+            info!("  ✓ {} checks match the source", report.checks.len());
+            return Ok(());
+        }
+        let mismatches = report.mismatches();
+        for mismatch in &mismatches {
+            warn!("  ✗ {}", mismatch);
+        }
+        let e = anyhow::anyhow!(
+            "{} of {} validation checks failed for {} (first: {}); see {}",
+            mismatches.len(),
+            report.checks.len(),
+            meta.table_name,
+            mismatches[0],
+            report_path.display()
+        );
+        if stats.failed_partitions > 0 {
+            warn!("  ⚠ {}", e);
+            return Ok(());
+        }
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
+
+    /// Write the gap report of a single-column integer PK beside the output.
+    pub(crate) fn report_pk_gaps(
+        &self,
+        meta: &TableMetadata,
+        output_path: &Path,
+        stats: &mut ExtractionStats,
+    ) -> Result<()> {
+        let written_as_int = |name: &str| meta.columns.iter().any(|c| c.name == name && c.data_type == DataType::Int64);
+        let column = match meta.pk {
+            Some(ref pk) if pk.columns.len() == 1 && !pk.text_key && written_as_int(&pk.columns[0]) => &pk.columns[0],
+            _ => {
+                info!("  No single-column integer PK — skipping gap report");
+                return Ok(());
+            }
+        };
+        let report = gaps::gap_report(&meta.table_name, meta.output_name(column), output_path)
+            .with_context(|| format!("Failed to compute PK gaps of {}", output_path.display()))?;
+        let report_path = report.write(output_path).map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        stats.output_files.push(report_path.clone());
+        info!(
+            "  PK gaps: {} ids missing from {}..{}, {} known ranges; see {}",
+            format_number(report.missing_ids as i64),
+            report.min.unwrap_or(0),
+            report.max.unwrap_or(0),
+            report.gaps.len(),
+            report_path.display()
+        );
+        Ok(())
+    }
+
+    /// Build the partition predicates according to the configured strategy.
+    fn plan_pk_partitions(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> Result<Vec<PartitionSpec>> {
+        let hash = || partition::hash_partitions(&pk.columns[0], pk.text_key, parts);
+        match self.config.partition_strategy {
+            PartitionStrategy::HashPk => Ok(hash()),
+            PartitionStrategy::RangePk => self.plan_range_partitions(meta, pk, parts),
+            _ => match self.plan_range_partitions(meta, pk, parts) {
+                Ok(partitions) if partitions.len() > 1 || parts < 2 => Ok(partitions),
+                Ok(_) => {
+                    info!("  No usable PK range — using hash partitioning");
+                    Ok(hash())
+                }
+                Err(e) => {
+                    warn!("  Range planning failed ({}) — using hash partitioning", e);
+                    Ok(hash())
+                }
+            },
+        }
+    }
+
+    /// Build range partition predicates for the PK.
+    ///
+    /// Single-column keys (and composite keys whose first column has enough
+    /// distinct values) are range-split on the first column. When the first
+    /// column of a composite key has fewer values than `parts`, each value is
+    /// sub-split on the second key column instead.
+    fn plan_range_partitions(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> Result<Vec<PartitionSpec>> {
+        let first_col = &pk.columns[0];
+        if pk.text_key {
+            return self.plan_text_partitions(meta, pk, parts);
+        }
+
+        let Some((min, max)) = pk.range else {
+            // No rows to take a key range from; the count may have been an estimate
+            info!("  No PK range (empty table) — using a single partition");
+            return Ok(vec![PartitionSpec::full()]);
+        };
+        let first_span = max.saturating_sub(min).saturating_add(1);
+
+        if pk.columns.len() > 1 && first_span < parts as i64 {
+            info!(
+                "  Composite PK: {} has only {} values — sub-splitting on {}",
+                first_col, first_span, pk.columns[1]
+            );
+            return self.plan_composite_partitions(meta, pk, parts);
+        }
+
+        let (boundaries, sampled) = self.compute_pk_boundaries(meta, pk, parts);
+        let mut partitions = partition::range_partitions(first_col, &boundaries);
+        match (sampled, meta.row_count) {
+            (true, RowCount::Exact(total)) => partition::estimate_steps(&mut partitions, total / parts as i64, total),
+            (true, RowCount::Estimated(total)) => {
+                partition::estimate_by_density(&mut partitions, &boundaries, total / parts as i64)
+            }
+            _ => {}
+        }
+        Ok(partitions)
+    }
+
+    /// Split every value of the first key column into ranges of the second.
+    /// Values aren't counted: each one's rows are estimated from the span of
+    /// its second column, as if rows were spread evenly over the key space.
+    fn plan_composite_partitions(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> Result<Vec<PartitionSpec>> {
+        let mut conn = self.source.connect()?;
+        let first_col = quote_ident(&pk.columns[0]);
+        let second_col = &pk.columns[1];
+        let filter = and_filter(meta);
+        let Some((min, max)) = pk.range else {
+            return Ok(Vec::new());
+        };
+        let values = (max - min + 1) as usize;
+        let sub_parts = (parts + values - 1) / values;
+
+        // The PK index answers MIN and MAX of the second column per value
+        let mut ranges = Vec::new();
+        for value in min..=max {
+            let predicate = format!("{} = {}{}", first_col, value, filter);
+            if let (Some(low), Some(high)) = conn.int_range(&meta.table_name, second_col, Some(&predicate))? {
+                ranges.push((value, low, high));
+            }
+        }
+
+        let span = |low: i64, high: i64| high.saturating_sub(low).saturating_add(1) as f64;
+        let total_span: f64 = ranges.iter().map(|&(_, low, high)| span(low, high)).sum();
+        let mut partitions = Vec::new();
+        for (value, low, high) in ranges {
+            let subs = partition::range_partitions(second_col, &partition::linear_boundaries(low, high, sub_parts));
+            let share = span(low, high) / total_span / subs.len().max(1) as f64;
+            let rows = meta.row_count.approx().map(|total| (total as f64 * share).round() as i64);
+            for sub in subs {
+                let range = sub.predicate.unwrap_or_default();
+                let spec = PartitionSpec::predicate(format!("{} = {} AND {}", first_col, value, range));
+                partitions.push(match rows {
+                    Some(rows) => spec.with_estimate(rows),
+                    None => spec,
+                });
+            }
+        }
+
+        Ok(partitions)
+    }
+
+    /// Range-split a CHAR/VARCHAR key on sampled boundary values.
+    ///
+    /// Partitions are half-open string ranges; the first and last are unbounded
+    /// below and above so no key can fall outside the plan.
+    fn plan_text_partitions(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> Result<Vec<PartitionSpec>> {
+        let col = &pk.columns[0];
+        let boundaries = match self.sample_text_boundaries(meta, col, parts) {
+            Ok(b) => b,
+            Err(e) => {
+                warn!("  Text key sampling failed ({}) — using a single partition", e);
+                Vec::new()
+            }
+        };
+        debug!("  Sampled {} text key boundaries on {}", boundaries.len(), col);
+
+        let mut partitions = Vec::with_capacity(boundaries.len() + 1);
+        let mut lower: Option<&str> = None;
+        for boundary in &boundaries {
+            partitions.push(PartitionSpec::text_range(col, lower, Some(boundary)));
+            lower = Some(boundary);
+        }
+        partitions.push(PartitionSpec::text_range(col, lower, None));
+        if let (false, Some(total)) = (boundaries.is_empty(), meta.row_count.approx()) {
+            partition::estimate_steps(&mut partitions, total / parts as i64, total);
+        }
+        Ok(partitions)
+    }
+
+    /// Same index walk as [`Self::sample_pk_boundaries`], for string keys.
+    fn sample_text_boundaries(&self, meta: &TableMetadata, col: &str, parts: usize) -> Result<Vec<String>> {
+        let step = meta.row_count.approx().unwrap_or(0) / parts as i64;
+        if parts < 2 || step < 1 {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.source.connect()?;
+        let mut boundaries: Vec<String> = Vec::with_capacity(parts - 1);
+        let quoted = quote_ident(col);
+        let filter = and_filter(meta);
+
+        for i in 1..parts {
+            let (predicate, skip) = match boundaries.last() {
+                None if i == 1 => (meta.filter.as_deref().map(|filter| format!("({})", filter)), step + 1),
+                Some(prev) => (Some(format!("{} > {}{}", quoted, sql_string_literal(prev), filter)), step),
+                None => break,
+            };
+            match sample_key(&mut *conn, meta, col, predicate, skip)? {
+                Some(Value::Text(value)) => boundaries.push(value),
+                _ => break,
+            }
+        }
 
-                let pool_clone = Arc::clone(&pool);
-                let meta_clone = meta_arc.clone();
-                let temp_path = temp_files[i].clone();
+        Ok(boundaries)
+    }
 
-                extract_partition(pool_clone, meta_clone, start_pk, end_pk, batch_size, &temp_path)
-            })
-            .collect();
+    /// Boundaries splitting the PK range into `parts` half-open ranges, and
+    /// whether they were sampled.
+    ///
+    /// Boundaries are sampled from the PK index so each range holds roughly the
+    /// same number of rows; falls back to a linear MIN/MAX split when sampling
+    /// isn't possible.
+    fn compute_pk_boundaries(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> (Vec<i64>, bool) {
+        let Some((min, max)) = pk.range else {
+            return (Vec::new(), false);
+        };
 
-        // Collect results
-        let mut total_rows = 0;
-        let mut partition_files = Vec::new();
-        
-        for (i, result) in results.into_iter().enumerate() {
-            match result {
-                Ok(part_result) => {
-                    total_rows += part_result.rows;
-                    if part_result.rows > 0 {
-                        partition_files.push(temp_files[i].clone());
-                    }
-                    println!("  Partition {}: {} rows", i, format_number(part_result.rows as i64));
-                }
-                Err(e) => {
-                    eprintln!("  Partition {} failed: {}", i, e);
-                }
+        match self.sample_pk_boundaries(meta, pk, parts) {
+            Ok(Some(sampled)) => {
+                debug!("  Sampled {} PK boundaries for equal-row partitions", sampled.len());
+                let mut boundaries = vec![min];
+                boundaries.extend(sampled);
+                boundaries.push(max);
+                boundaries.dedup();
+                (boundaries, true)
+            }
+            Ok(None) => (partition::linear_boundaries(min, max, parts), false),
+            Err(e) => {
+                warn!("  PK sampling failed ({}) — using linear MIN/MAX split", e);
+                (partition::linear_boundaries(min, max, parts), false)
             }
         }
+    }
 
-        // Merge temp files into final output
-        println!("  Merging {} partition files...", partition_files.len());
-        merge_parquet_files(&partition_files, output_path)?;
-
-        // Cleanup temp files
-        for temp_file in &temp_files {
-            let _ = std::fs::remove_file(temp_file);
+    /// Walk the PK index in `row_count / parts` steps, returning the first key of
+    /// every partition after the first. Each step continues from the previous
+    /// boundary, so the whole index is traversed only once.
+    fn sample_pk_boundaries(&self, meta: &TableMetadata, pk: &PrimaryKeyInfo, parts: usize) -> Result<Option<Vec<i64>>> {
+        let step = meta.row_count.approx().unwrap_or(0) / parts as i64;
+        let Some((min, max)) = pk.range else {
+            return Ok(None);
+        };
+        if parts < 2 || step < 1 {
+            return Ok(None);
         }
 
-        let duration = start.elapsed().as_secs_f64();
-        let file_size_mb = std::fs::metadata(output_path)
-            .map(|m| m.len() as f64 / (1024.0 * 1024.0))
-            .unwrap_or(0.0);
+        let mut conn = self.source.connect()?;
+        let col = quote_ident(&pk.columns[0]);
+        let mut boundaries: Vec<i64> = Vec::with_capacity(parts - 1);
+        let mut prev = min;
+        let filter = and_filter(meta);
 
-        println!(
-            "  ✓ Done: {} rows → {} in {} ({:.1} MB, {:.0} rows/s)",
-            format_number(total_rows as i64),
-            output_path.display(),
-            format_duration(duration),
-            file_size_mb,
-            total_rows as f64 / duration
-        );
+        for i in 1..parts {
+            // The first step starts at MIN itself, so it skips one row fewer
+            let (predicate, skip) = if i == 1 {
+                (format!("{} >= {}", col, prev), step + 1)
+            } else {
+                (format!("{} > {}", col, prev), step)
+            };
+            let predicate = format!("{}{}", predicate, filter);
+            match sample_key(&mut *conn, meta, &pk.columns[0], Some(predicate), skip)? {
+                Some(Value::Integer(value)) if value > prev && value <= max => {
+                    boundaries.push(value);
+                    prev = value;
+                }
+                _ => break,
+            }
+        }
 
-        Ok(ExtractionStats {
-            rows_extracted: total_rows,
-            duration_secs: duration,
-            file_size_mb,
-        })
+        if boundaries.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(boundaries))
     }
 
     fn extract_sequential(
@@ -443,95 +2340,230 @@ impl Extractor {
         start: Instant,
     ) -> Result<ExtractionStats> {
         // Optimized sequential with prefetch + writer pipeline
-        let batch_size = calculate_batch_size(meta.row_count, meta.has_blob);
-        println!("  Batch size: {}", format_number(batch_size as i64));
+        let mut sizer = self.batch_sizer(meta);
 
         type RowBatch = Vec<Row>;
-        let (fetch_tx, fetch_rx): (Sender<Option<RowBatch>>, Receiver<Option<RowBatch>>) = bounded(10); // Aggressive prefetch
-        let (batch_tx, batch_rx): (Sender<Option<RecordBatch>>, Receiver<Option<RecordBatch>>) = bounded(8);
-
-        let pool_clone = Arc::clone(&self.pool);
-        let columns_sql: String = meta.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
-        let query = format!("SELECT {} FROM {}", columns_sql, meta.table_name); // NO ORDER BY!
-        let page_size = batch_size as i64;
-
-        // Prefetch thread
-        let fetcher = thread::spawn(move || {
-            let mut conn = match pool_clone.acquire() {
-                Ok(c) => c,
-                Err(_) => return,
-            };
-
-            let mut offset = 0i64;
-            loop {
-                let page_query = format!("{} ROWS {} TO {}", query, offset + 1, offset + page_size);
-                match conn.query(&page_query, ()) {
-                    Ok(rows) => {
-                        if rows.is_empty() {
-                            let _ = fetch_tx.send(None);
-                            break;
-                        }
-                        if fetch_tx.send(Some(rows)).is_err() {
-                            break;
-                        }
-                        offset += page_size;
+        // Aggressive prefetch
+        let (fetch_depth, convert_depth) =
+            self.config.prefetch_depth.map_or(DEFAULT_PREFETCH_DEPTHS, |depth| (depth, depth));
+        let (fetch_tx, fetch_rx): (Sender<Option<RowBatch>>, Receiver<Option<RowBatch>>) = bounded(fetch_depth);
+        let (batch_tx, batch_rx): (Sender<Option<RecordBatch>>, Receiver<Option<RecordBatch>>) = bounded(convert_depth);
+
+        let source = Arc::clone(&self.source);
+        let blobs = self.blob_fetcher(meta)?;
+        let fetch_meta = meta.clone();
+
+        // Prefetch thread. Errors end the stream early and are surfaced on join,
+        // so a failed page can never silently truncate the output.
+        let retry = self.config.retry.clone();
+        let fetch_budget = Arc::clone(&self.budget);
+        let times = Arc::new(StageTimes::default());
+        let fetch_times = Arc::clone(&times);
+        let fetch_cancel = self.cancel.clone();
+        let fetch_span = debug_span!("fetch");
+        let verify_counts = self.config.verify_counts;
+        // Returns the bytes fetched, the page retries and, with
+        // `verify_counts`, the rows counted in the pages' transaction
+        let fetcher = thread::spawn(move || -> Result<(usize, usize, Option<i64>)> {
+            let _span = fetch_span.entered();
+            let mut conn = source.connect_until(&fetch_cancel)?;
+            // Pages may each run in their own transaction, so a monitoring
+            // table is read in one query to get one snapshot
+            let whole = is_monitoring_table(&fetch_meta.table_name);
+
+            // With `verify_counts`, one transaction for every page and the count
+            // verifying them, so at concurrency isolation they all read one
+            // snapshot. Otherwise pages keep their own short transactions.
+            if verify_counts {
+                conn.begin_transaction()?;
+            }
+            let mut fetch_pages = || -> Result<(usize, usize, Option<i64>)> {
+                let mut offset = 0i64;
+                let mut bytes = 0;
+                let mut retries = 0;
+                loop {
+                    fetch_cancel.check()?;
+                    let page_size = sizer.rows() as i64;
+                    // NO ORDER BY!
+                    let spec = match whole {
+                        true => PartitionSpec::full(),
+                        false => PartitionSpec::rows(offset + 1, offset + page_size),
+                    };
+                    let page = fetch_meta.select(blobs.is_some(), &spec);
+                    let label = format!("Page at row {}", offset + 1);
+                    let mut attempts = 0;
+                    let rows: Vec<Row> = fetch_times.time(Stage::Fetch, || {
+                        retry.run(&label, || {
+                            attempts += 1;
+                            conn.fetch_all(&page)
+                        })
+                    })?;
+                    retries += attempts - 1;
+                    if rows.is_empty() {
+                        let _ = fetch_tx.send(None);
+                        // A retried page may have lost the transaction with its
+                        // connection, so only a clean run counts in it
+                        let counted = match verify_counts && retries == 0 {
+                            true => Some(conn.count_selected(&fetch_meta.select(false, &PartitionSpec::full()))?),
+                            false => None,
+                        };
+                        return Ok((bytes, retries, counted));
+                    }
+                    let rows = match blobs {
+                        Some(ref blobs) => fetch_times.time(Stage::Fetch, || blobs.complete_rows(&fetch_meta, rows))?,
+                        None => rows,
+                    };
+                    let row_bytes = estimate_rows_bytes(&rows);
+                    bytes += row_bytes;
+                    sizer.observe(rows.len(), row_bytes);
+                    fetch_times.time(Stage::BudgetWait, || fetch_budget.reserve(row_bytes));
+                    if fetch_times.time(Stage::QueueWait, || fetch_tx.send(Some(rows))).is_err() {
+                        return Ok((bytes, retries, None));
                     }
-                    Err(_) => {
+                    if whole {
                         let _ = fetch_tx.send(None);
-                        break;
+                        return Ok((bytes, retries, None));
                     }
+                    offset += page_size;
                 }
+            };
+            let fetched = fetch_pages();
+            if !verify_counts {
+                return fetched;
             }
+            // End it whatever happened, before the connection goes back to the pool
+            let ended = conn.end_transaction();
+            let fetched = fetched?;
+            ended?;
+            Ok(fetched)
         });
 
         // Writer thread
-        let fields: Vec<Field> = meta.columns.iter().map(|m| Field::new(&m.name, m.data_type.clone(), true)).collect();
-        let schema_for_writer = Arc::new(Schema::new(fields));
-        let props_for_writer = self.create_writer_props();
-        let output_path_clone = output_path.to_path_buf();
-
-        let writer_handle = thread::spawn(move || -> Result<()> {
-            let file = File::create(&output_path_clone)?;
-            let buf = BufWriter::with_capacity(128 * 1024 * 1024, file);
-            let mut writer = ArrowWriter::try_new(buf, schema_for_writer, Some(props_for_writer))?;
-
-            while let Ok(opt) = batch_rx.recv() {
-                match opt {
-                    Some(batch) => writer.write(&batch)?,
-                    None => break,
-                }
-            }
-            writer.close()?;
-            Ok(())
-        });
+        let schema = self.output_schema(meta)?;
+        let writer_handle = spawn_parquet_writer(
+            output_path.to_path_buf(),
+            Arc::clone(&schema),
+            self.output_writer_props(&schema),
+            batch_rx,
+            Arc::clone(&self.budget),
+            Arc::clone(&times),
+            self.cancel.clone(),
+        );
 
         // Process batches
+        self.emit(ProgressEvent::PartitionStarted { partition: 0 });
+        let progress = TableProgress::new(
+            self.config.progress,
+            &meta.table_name,
+            meta.row_count.approx(),
+            self.observer.clone(),
+        );
+        let mut counts = PartitionResult::default();
         let mut total_rows = 0;
+        let mut next_report = 500_000;
         while let Ok(Some(rows)) = fetch_rx.recv() {
-            let batch = build_arrow_batch(meta, &rows)?;
+            if self.cancel.is_cancelled() {
+                self.budget.release(estimate_rows_bytes(&rows));
+                break;
+            }
+            let batch = times.time(Stage::Convert, || build_arrow_batch(meta, &rows))?;
+            let row_bytes = estimate_rows_bytes(&rows);
+            drop(rows);
+            let Some(batch) = transform::apply(self.transform.as_ref(), batch)? else {
+                self.budget.release(row_bytes);
+                continue;
+            };
+            self.budget.transfer(row_bytes, batch.get_array_memory_size());
+            counts.count_batch(&batch);
             let row_count = batch.num_rows();
-            if batch_tx.send(Some(batch)).is_err() {
+            if times.time(Stage::QueueWait, || batch_tx.send(Some(batch))).is_err() {
                 break;
             }
             total_rows += row_count;
+            progress.inc(row_count);
 
-            if total_rows % 500_000 == 0 {
+            if !progress.is_enabled() && total_rows >= next_report {
+                next_report = (total_rows / 500_000 + 1) * 500_000;
                 let elapsed = start.elapsed().as_secs_f64();
                 let rate = total_rows as f64 / elapsed;
-                let pct = (total_rows as f64 * 100.0) / (meta.row_count.max(1) as f64);
-                println!(
-                    "  Progress: {} / {} rows ({:.1}%) - {:.0} rows/s",
-                    format_number(total_rows as i64),
-                    format_number(meta.row_count),
-                    pct,
-                    rate
-                );
+                match meta.row_count.approx() {
+                    Some(row_count) => {
+                        let pct = (total_rows as f64 * 100.0) / (row_count.max(1) as f64);
+                        info!(
+                            "  Progress: {} / {} rows ({:.1}%) - {:.0} rows/s",
+                            format_number(total_rows as i64),
+                            format_number(row_count),
+                            pct,
+                            rate
+                        );
+                    }
+                    None => info!(
+                        "  Progress: {} rows - {:.0} rows/s",
+                        format_number(total_rows as i64),
+                        rate
+                    ),
+                }
             }
         }
 
+        drop(progress);
         let _ = batch_tx.send(None);
-        let _ = fetcher.join();
-        writer_handle.join().map_err(|_| anyhow::anyhow!("writer thread panicked"))??;
+        // Unblock a fetcher still waiting on the budget or a full queue
+        for rows in fetch_rx.try_iter().flatten() {
+            self.budget.release(estimate_rows_bytes(&rows));
+        }
+        drop(fetch_rx);
+        let fetched = fetcher.join().map_err(|_| anyhow::anyhow!("fetch thread panicked"))?;
+        let written = writer_handle.join().map_err(|_| anyhow::anyhow!("writer thread panicked"))?;
+        if self.cancel.is_cancelled() {
+            let _ = std::fs::remove_file(output_path);
+            return Err(cancel::cancelled());
+        }
+        written.map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
+        let (bytes_read, retries, counted) = match fetched {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                self.emit(ProgressEvent::PartitionFinished {
+                    partition: 0,
+                    rows: total_rows,
+                    error: Some(format!("{:#}", e)),
+                });
+                let _ = std::fs::remove_file(output_path);
+                let e = e.context(format!("Sequential extraction of {} failed; no output written", meta.table_name));
+                return Err(ExtractError::tag(ExtractError::PartitionFailed, e));
+            }
+        };
+
+        if self.config.profile {
+            info!("  Profile (seconds summed over threads): {}", times.summary());
+            info!("    Bottleneck: {}", times.bottleneck());
+        }
+
+        self.emit(ProgressEvent::PartitionFinished { partition: 0, rows: total_rows, error: None });
+
+        let source_rows = self.verify_row_count(meta, total_rows, false, counted)?;
+        let extracted_secs = start.elapsed().as_secs_f64();
+        let partition = PartitionStats {
+            index: 0,
+            range: PartitionSpec::full().to_string(),
+            rows: total_rows,
+            bytes_read,
+            batches: counts.batches,
+            duration_secs: extracted_secs,
+            rows_per_sec: rows_per_sec(total_rows, extracted_secs),
+            retries,
+            error: None,
+        };
+        let mut manifest = self.manifest(&meta.table_name, output_path);
+        manifest.rows = total_rows;
+        manifest.partitions = 1;
+        manifest.source_rows = source_rows;
+        manifest.schema = schema_fields(&schema);
+        // Timings would make deterministic manifests differ
+        if !self.config.deterministic {
+            manifest.partition_stats = vec![partition.clone()];
+        }
+        manifest.write().map_err(|e| ExtractError::tag(ExtractError::WriterIo, e))?;
 
         let duration = start.elapsed().as_secs_f64();
         let file_size_mb = std::fs::metadata(output_path)
@@ -542,91 +2574,411 @@ impl Extractor {
             rows_extracted: total_rows,
             duration_secs: duration,
             file_size_mb,
+            failed_partitions: 0,
+            bytes_read,
+            batches: counts.batches,
+            null_counts: counts.null_counts(&schema),
+            output_files: vec![output_path.to_path_buf(), Manifest::path_for(output_path)],
+            partitions: vec![partition],
+            null_key_rows: 0,
+            special_floats: 0,
+            text_issues: BTreeMap::new(),
+            tombstones: 0,
+            merge: None,
         })
     }
 
-    fn create_writer_props(&self) -> WriterProperties {
+    /// `batch_size` rows per batch if set, else sized adaptively to `target_batch_bytes`.
+    pub(crate) fn batch_sizer(&self, meta: &TableMetadata) -> BatchSizer {
+        match self.config.batch_size {
+            Some(rows) => {
+                debug!("  Batch size: {} rows", rows);
+                BatchSizer::fixed(rows)
+            }
+            None => {
+                let mb = self.config.target_batch_bytes as f64 / (1024.0 * 1024.0);
+                debug!("  Batch size: adaptive (~{:.0} MB per batch)", mb);
+                BatchSizer::new(self.config.target_batch_bytes, meta.has_blob)
+            }
+        }
+    }
+
+    /// Blob workers for `meta`, if configured and the table has blob columns,
+    /// holding their connections before any partition fetcher takes one.
+    pub(crate) fn blob_fetcher(&self, meta: &TableMetadata) -> Result<Option<BlobFetcher>> {
+        // Lookups would read another snapshot of a monitoring table
+        if self.config.blob_workers == 0
+            || !meta.columns.iter().any(|c| c.is_blob())
+            || is_monitoring_table(&meta.table_name)
+        {
+            return Ok(None);
+        }
+        debug!("  Blob workers: {}", self.config.blob_workers);
+        Ok(Some(BlobFetcher::new(self.config.blob_workers, Arc::clone(&self.source))?))
+    }
+
+    /// Props for partition temp files: `temp_compression`, independent of the
+    /// output codec since the merge re-encodes anyway.
+    pub(crate) fn temp_writer_props(&self) -> WriterProperties {
         WriterProperties::builder()
+            .set_compression(self.config.temp_compression)
+            .set_dictionary_enabled(false)
+            .build()
+    }
+
+    pub(crate) fn create_writer_props(&self) -> WriterProperties {
+        self.writer_props_builder().build()
+    }
+
+    /// Output props for `schema`: those of [`Self::create_writer_props`],
+    /// plus dictionary pages for dictionary-encoded columns.
+    pub(crate) fn output_writer_props(&self, schema: &Schema) -> WriterProperties {
+        schema
+            .fields()
+            .iter()
+            .filter(|f| matches!(f.data_type(), DataType::Dictionary(..)))
+            .fold(self.writer_props_builder(), |builder, f| {
+                builder.set_column_dictionary_enabled(ColumnPath::from(f.name().as_str()), true)
+            })
+            .build()
+    }
+
+    fn writer_props_builder(&self) -> WriterPropertiesBuilder {
+        let builder = WriterProperties::builder()
             .set_compression(if self.config.use_compression {
                 Compression::UNCOMPRESSED
             } else {
                 Compression::UNCOMPRESSED
             })
-            .set_dictionary_enabled(false)
-            .build()
+            .set_dictionary_enabled(false);
+        if self.config.deterministic {
+            // Independent of the parquet crate version, so upgrades alone don't change the bytes
+            builder.set_created_by("firebird_peregrine_falcon".to_string())
+        } else {
+            builder
+        }
+    }
+
+    /// A new manifest for `output_path`; `deterministic` leaves the creation time 0.
+    pub(crate) fn manifest(&self, table: &str, output_path: &Path) -> Manifest {
+        let mut manifest = Manifest::new(table, output_path);
+        if self.config.deterministic {
+            manifest.created_at_unix = 0;
+        }
+        manifest
+    }
+}
+
+/// Fail if a plan of `ROWS` windows read other than the `rows` it was cut
+/// from, the sign of a table modified between the windows' transactions.
+/// Only meaningful when every partition ran and nothing dropped rows.
+pub(crate) fn check_rows_windows(table_name: &str, plan: &[PartitionSpec], rows: usize) -> Result<()> {
+    let Some(expected) = plan.iter().map(|p| p.rows.and(p.estimated_rows)).sum::<Option<i64>>() else {
+        return Ok(());
+    };
+    if rows as i64 == expected {
+        return Ok(());
+    }
+    let e = anyhow::anyhow!(
+        "{} changed during the extraction: its ROWS windows were planned over {} rows but read {}, so rows may be \
+         missing or duplicated; use a key-based partition strategy for tables in use",
+        table_name,
+        format_number(expected),
+        format_number(rows as i64)
+    );
+    Err(ExtractError::tag(ExtractError::Validation, e))
+}
+
+/// What a partition (or the sequential scan) produced.
+#[derive(Default)]
+pub(crate) struct PartitionResult {
+    pub(crate) rows: usize,
+    /// Estimated in-memory size of the fetched rows
+    pub(crate) bytes: usize,
+    pub(crate) batches: usize,
+    /// Nulls per column, in schema order
+    nulls: Vec<usize>,
+}
+
+impl PartitionResult {
+    pub(crate) fn count_batch(&mut self, batch: &RecordBatch) {
+        self.batches += 1;
+        self.nulls.resize(batch.num_columns(), 0);
+        for (nulls, column) in self.nulls.iter_mut().zip(batch.columns()) {
+            *nulls += column.null_count();
+        }
+    }
+
+    pub(crate) fn absorb(&mut self, other: &PartitionResult) {
+        self.rows += other.rows;
+        self.bytes += other.bytes;
+        self.batches += other.batches;
+        self.nulls.resize(self.nulls.len().max(other.nulls.len()), 0);
+        for (nulls, other) in self.nulls.iter_mut().zip(&other.nulls) {
+            *nulls += other;
+        }
+    }
+
+    /// Null counts keyed by column name; every column is listed.
+    pub(crate) fn null_counts(&self, schema: &Schema) -> BTreeMap<String, usize> {
+        schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| (field.name().clone(), self.nulls.get(i).copied().unwrap_or(0)))
+            .collect()
     }
 }
 
-struct PartitionResult {
-    rows: usize,
+/// ` AND (filter)` for the table's filter, to append to a WHERE clause.
+fn and_filter(meta: &TableMetadata) -> String {
+    meta.filter.as_deref().map(|filter| format!(" AND ({})", filter)).unwrap_or_default()
 }
 
+/// The `skip`-th value of `column` in key order among the rows matching
+/// `predicate`, for boundary sampling; `None` past the last row.
+fn sample_key(
+    conn: &mut dyn SourceConnection,
+    meta: &TableMetadata,
+    column: &str,
+    predicate: Option<String>,
+    skip: i64,
+) -> Result<Option<Value>> {
+    let select = Select {
+        table: meta.table_name.clone(),
+        columns: vec![column.to_string()],
+        db_key: false,
+        partition: PartitionSpec { predicate, rows: Some((skip, skip)), ..PartitionSpec::full() },
+        order_by: vec![column.to_string()],
+    };
+    let row = conn.fetch_all(&select)?.into_iter().next();
+    Ok(row.and_then(|row| row.values.into_iter().next()))
+}
+
+/// File name stem of a table's output. Quoted names may hold anything; keep
+/// it one plain path component.
+pub(crate) fn output_stem(table_name: &str) -> String {
+    table_name
+        .to_lowercase()
+        .chars()
+        .filter(|&c| c != '"')
+        .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '-' | '$') { c } else { '_' })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn extract_partition(
-    pool: Arc<ConnectionPool>,
+    source: &dyn Source,
     meta: Arc<TableMetadata>,
-    start_pk: i64,
-    end_pk: i64,
-    batch_size: usize,
-    output_path: &Path,
+    partition: &PartitionSpec,
+    mut sizer: BatchSizer,
+    mut sink: PartitionSink<'_>,
+    budget: &Arc<MemoryBudget>,
+    times: &StageTimes,
+    blobs: Option<&BlobFetcher>,
+    progress: &PartitionProgress,
+    cancel: &CancellationToken,
+    transform: Option<&BatchTransform>,
 ) -> Result<PartitionResult> {
-    let mut conn = pool.acquire()?;
-    let pk_col = &meta.pk.as_ref().unwrap().columns[0];
-    let columns_sql: String = meta.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
+    let mut conn = source.connect_until(cancel)?;
 
     // NO ORDER BY - maximum speed!
-    let query = format!(
-        "SELECT {} FROM {} WHERE {} >= {} AND {} <= {}",
-        columns_sql, meta.table_name, pk_col, start_pk, pk_col, end_pk
-    );
+    let select = meta.select(blobs.is_some(), partition);
+
+    let mut result = PartitionResult::default();
+    let mut chunk: Vec<Row> = Vec::with_capacity(sizer.rows());
+
+    // Stream the cursor in batch-sized chunks instead of materializing the partition
+    let mut fetch_started = Instant::now();
+    conn.fetch(&select, &mut |row| {
+        cancel.check()?;
+        chunk.push(row);
+        if chunk.len() >= sizer.rows() {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(sizer.rows()));
+            let full = match blobs {
+                Some(blobs) => blobs.complete_rows(&meta, full)?,
+                None => full,
+            };
+            times.add(Stage::Fetch, fetch_started.elapsed());
+            let row_bytes = estimate_rows_bytes(&full);
+            result.bytes += row_bytes;
+            sizer.observe(full.len(), row_bytes);
+            progress.inc(full.len());
+            write_chunk(&meta, full, row_bytes, budget, &mut sink, times, transform, &mut result)?;
+            fetch_started = Instant::now();
+        }
+        Ok(true)
+    })?;
+    if !chunk.is_empty() {
+        let chunk = match blobs {
+            Some(blobs) => blobs.complete_rows(&meta, chunk)?,
+            None => chunk,
+        };
+        times.add(Stage::Fetch, fetch_started.elapsed());
+        let row_bytes = estimate_rows_bytes(&chunk);
+        result.bytes += row_bytes;
+        progress.inc(chunk.len());
+        write_chunk(&meta, chunk, row_bytes, budget, &mut sink, times, transform, &mut result)?;
+    } else {
+        times.add(Stage::Fetch, fetch_started.elapsed());
+    }
 
-    let rows: Vec<Row> = conn.query(&query, ())?;
-    let total_rows = rows.len();
+    sink.finish()?;
+    Ok(result)
+}
 
-    if total_rows == 0 {
-        return Ok(PartitionResult { rows: 0 });
+/// Convert one chunk of fetched rows, transform it and queue it on the
+/// partition's sink; rows are counted as written.
+#[allow(clippy::too_many_arguments)]
+fn write_chunk(
+    meta: &TableMetadata,
+    rows: Vec<Row>,
+    row_bytes: usize,
+    budget: &Arc<MemoryBudget>,
+    sink: &mut PartitionSink<'_>,
+    times: &StageTimes,
+    transform: Option<&BatchTransform>,
+    counts: &mut PartitionResult,
+) -> Result<()> {
+    // Backpressure: wait here while the pipeline holds too much
+    times.time(Stage::BudgetWait, || budget.reserve(row_bytes));
+    let converted = times.time(Stage::Convert, || {
+        let batch = build_arrow_batch(meta, &rows)?;
+        transform::apply(transform, batch)
+    });
+    let batch = match converted {
+        Ok(Some(batch)) => batch,
+        Ok(None) => {
+            budget.release(row_bytes);
+            return Ok(());
+        }
+        Err(e) => {
+            budget.release(row_bytes);
+            return Err(e);
+        }
+    };
+    let batch_bytes = batch.get_array_memory_size();
+    budget.transfer(row_bytes, batch_bytes);
+    drop(rows);
+    counts.rows += batch.num_rows();
+    counts.count_batch(&batch);
+
+    // Writer threads release the batch bytes once it's encoded
+    if let Err(e) = sink.write(batch) {
+        budget.release(batch_bytes);
+        return Err(e);
     }
+    Ok(())
+}
 
-    // Write to temp file with writer thread
-    let (batch_tx, batch_rx): (Sender<Option<RecordBatch>>, Receiver<Option<RecordBatch>>) = bounded(4);
-    
-    let fields: Vec<Field> = meta.columns.iter().map(|m| Field::new(&m.name, m.data_type.clone(), true)).collect();
-    let schema = Arc::new(Schema::new(fields));
-    let props = WriterProperties::builder()
-        .set_compression(Compression::UNCOMPRESSED)
-        .set_dictionary_enabled(false)
-        .build();
-    let output_path_clone = output_path.to_path_buf();
-
-    let writer_handle = thread::spawn(move || -> Result<()> {
-        let file = File::create(&output_path_clone)?;
+/// Writer thread draining `batch_rx` into a Parquet file until `None`,
+/// releasing each batch's bytes from the budget once written. Stops early,
+/// still closing the file, when `cancel` fires.
+fn spawn_parquet_writer(
+    output_path: PathBuf,
+    schema: Arc<Schema>,
+    props: WriterProperties,
+    batch_rx: Receiver<Option<RecordBatch>>,
+    budget: Arc<MemoryBudget>,
+    times: Arc<StageTimes>,
+    cancel: CancellationToken,
+) -> thread::JoinHandle<Result<()>> {
+    let span = debug_span!("write");
+    thread::spawn(move || -> Result<()> {
+        let _span = span.entered();
+        let file = File::create(&output_path)?;
         let buf = BufWriter::with_capacity(128 * 1024 * 1024, file);
         let mut writer = ArrowWriter::try_new(buf, schema, Some(props))?;
 
         while let Ok(opt) = batch_rx.recv() {
             match opt {
-                Some(batch) => writer.write(&batch)?,
+                Some(batch) if cancel.is_cancelled() => {
+                    budget.release(batch.get_array_memory_size());
+                    break;
+                }
+                Some(batch) => {
+                    let written = times.time(Stage::Write, || writer.write(&batch));
+                    budget.release(batch.get_array_memory_size());
+                    written?;
+                }
                 None => break,
             }
         }
         writer.close()?;
         Ok(())
-    });
+    })
+}
 
-    // Process in batches
-    for chunk in rows.chunks(batch_size) {
-        let batch = build_arrow_batch(&meta, chunk)?;
-        if batch_tx.send(Some(batch)).is_err() {
-            break;
-        }
-    }
+/// Repeated keys reported as examples in a duplicate key error.
+const DUPLICATE_SAMPLES: usize = 10;
+
+/// What a scan of the key columns of an output file found.
+#[derive(Default)]
+struct KeyScan {
+    duplicates: usize,
+    /// The first [`DUPLICATE_SAMPLES`] repeated keys, formatted
+    samples: Vec<String>,
+    /// Smallest and largest value of the first key column, if it's an integer
+    first_range: Option<(i64, i64)>,
+}
 
-    let _ = batch_tx.send(None);
-    writer_handle.join().map_err(|_| anyhow::anyhow!("writer thread panicked"))??;
+/// Stream the key columns of a Parquet file, counting repeated key values.
+fn scan_keys(path: &Path, key_columns: &[String]) -> Result<KeyScan> {
+    use arrow::array::{Array, Int64Array};
+    use arrow::row::{RowConverter, SortField};
+    use arrow::util::display::array_value_to_string;
+    use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask};
+    use std::collections::HashSet;
 
-    Ok(PartitionResult { rows: total_rows })
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+    let mut indices = key_columns
+        .iter()
+        .map(|name| builder.schema().index_of(name))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // Projected batches hold the key columns in schema order
+    indices.sort_unstable();
+    let key_types: Vec<SortField> = indices
+        .iter()
+        .map(|&i| SortField::new(builder.schema().field(i).data_type().clone()))
+        .collect();
+    let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+    let reader = builder.with_projection(mask).with_batch_size(100_000).build()?;
+
+    let converter = RowConverter::new(key_types)?;
+    let mut seen = HashSet::new();
+    let mut scan = KeyScan::default();
+    for batch in reader {
+        let batch = batch?;
+        // The projection keeps schema order, which needn't be key order
+        let first = batch.column(batch.schema().index_of(&key_columns[0])?);
+        if let Some(ints) = first.as_any().downcast_ref::<Int64Array>() {
+            if let (Some(min), Some(max)) = (arrow::compute::min(ints), arrow::compute::max(ints)) {
+                scan.first_range = Some(match scan.first_range {
+                    Some((lo, hi)) => (lo.min(min), hi.max(max)),
+                    None => (min, max),
+                });
+            }
+        }
+        let rows = converter.convert_columns(batch.columns())?;
+        for (i, row) in rows.iter().enumerate() {
+            if !seen.insert(row.owned()) {
+                scan.duplicates += 1;
+                if scan.samples.len() < DUPLICATE_SAMPLES {
+                    let key = batch
+                        .columns()
+                        .iter()
+                        .map(|c| array_value_to_string(c, i))
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    scan.samples.push(key.join(", "));
+                }
+            }
+        }
+    }
+    Ok(scan)
 }
 
-fn merge_parquet_files(input_files: &[PathBuf], output_path: &Path) -> Result<()> {
+#[instrument(name = "merge", skip_all, fields(files = input_files.len()))]
+pub(crate) fn merge_parquet_files(input_files: &[PathBuf], output_path: &Path, props: WriterProperties) -> Result<()> {
     use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use std::fs::File;
 
@@ -635,8 +2987,21 @@ fn merge_parquet_files(input_files: &[PathBuf], output_path: &Path) -> Result<()
     }
 
     if input_files.len() == 1 {
-        std::fs::copy(&input_files[0], output_path)?;
-        return Ok(());
+        // A plain copy is only equivalent if the temp file already uses the output codec and dictionary pages
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&input_files[0])?)?;
+        let same_codec = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .flat_map(|rg| rg.columns())
+            .all(|c| {
+                c.compression() == props.compression(c.column_path())
+                    && c.dictionary_page_offset().is_some() == props.dictionary_enabled(c.column_path())
+            });
+        if same_codec {
+            std::fs::copy(&input_files[0], output_path)?;
+            return Ok(());
+        }
     }
 
     // Read first file to get schema and build writer
@@ -647,10 +3012,6 @@ fn merge_parquet_files(input_files: &[PathBuf], output_path: &Path) -> Result<()
     // Create output writer
     let output_file = File::create(output_path)?;
     let buf = BufWriter::with_capacity(128 * 1024 * 1024, output_file);
-    let props = WriterProperties::builder()
-        .set_compression(Compression::UNCOMPRESSED)
-        .set_dictionary_enabled(false)
-        .build();
     let mut writer = ArrowWriter::try_new(buf, schema, Some(props))?;
 
     // Read and write first file
@@ -676,27 +3037,8 @@ fn merge_parquet_files(input_files: &[PathBuf], output_path: &Path) -> Result<()
     Ok(())
 }
 
-fn calculate_batch_size(row_count: i64, has_blob: bool) -> usize {
-    // ULTRA-LARGE batches: 500K-1M rows
-    let base_batch = if row_count < 200_000 {
-        250_000
-    } else if row_count < 10_000_000 {
-        500_000  // Increased from 200K
-    } else if row_count < 50_000_000 {
-        750_000  // Increased from 300K
-    } else {
-        1_000_000  // MASSIVE: 1M rows per batch
-    };
-
-    let mut batch = base_batch;
-    if has_blob {
-        batch = (batch * 2) / 3;  // Reduce by 33% for BLOBs
-    }
-
-    batch.max(100_000)  // Minimum 100K
-}
-
-fn build_arrow_batch(meta: &TableMetadata, rows: &[Row]) -> Result<RecordBatch> {
+#[instrument(name = "convert", level = "debug", skip_all, fields(rows = rows.len()))]
+pub(crate) fn build_arrow_batch(meta: &TableMetadata, rows: &[Row]) -> Result<RecordBatch> {
     let num_cols = meta.columns.len();
 
     // Parallel column building
@@ -704,14 +3046,29 @@ fn build_arrow_batch(meta: &TableMetadata, rows: &[Row]) -> Result<RecordBatch>
         .into_par_iter()
         .map(|ci| {
             let col_meta = &meta.columns[ci];
-            build_column_array(col_meta, rows, ci)
+            let values = ColumnValues::new(rows, ci);
+            let array = match meta.blob_files {
+                Some(ref files) if col_meta.external => sidecar::externalize(files, col_meta, rows, ci),
+                _ => match meta.type_mapper.convert(&col_meta.field, &col_meta.data_type, values) {
+                    Some(array) => array,
+                    None => build_column_array(meta, col_meta, rows, ci),
+                },
+            };
+            let array = match col_meta.mask {
+                Some(ref mask) => array.and_then(|array| mask.apply(&array, &meta.mask_salt)),
+                None => array,
+            };
+            array.map_err(|e| {
+                let e = e.context(format!("Failed to convert {}.{}", meta.table_name, col_meta.name));
+                ExtractError::tag(ExtractError::TypeMapping, e)
+            })
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     let fields: Vec<Field> = meta
         .columns
         .iter()
-        .map(|m| Field::new(&m.name, m.data_type.clone(), true))
+        .map(|m| Field::new(&m.output_name, m.data_type.clone(), true))
         .collect();
 
     let schema = Arc::new(Schema::new(fields));
@@ -719,16 +3076,23 @@ fn build_arrow_batch(meta: &TableMetadata, rows: &[Row]) -> Result<RecordBatch>
         .context("Failed to build record batch")
 }
 
-fn build_column_array(meta: &ColumnMetadata, rows: &[Row], col_index: usize) -> ArrayRef {
+/// The default conversion of column `col_index`; fails only on NaN/Infinity
+/// under [`SpecialFloats::Error`] and NUL characters under [`NulBytes::Error`].
+fn build_column_array(
+    table: &TableMetadata,
+    meta: &ColumnMetadata,
+    rows: &[Row],
+    col_index: usize,
+) -> Result<ArrayRef> {
     let row_count = rows.len();
 
-    match meta.data_type {
+    Ok(match meta.data_type {
         DataType::Int64 => {
             let mut builder = Int64Builder::with_capacity(row_count);
             for row in rows {
-                match row.cols.get(col_index).map(|c| &c.value) {
-                    Some(rsfbclient::SqlType::Integer(v)) => builder.append_value(*v),
-                    Some(rsfbclient::SqlType::Floating(v)) => builder.append_value(*v as i64),
+                match row.get(col_index) {
+                    Some(Value::Integer(v)) => builder.append_value(*v),
+                    Some(Value::Floating(v)) => builder.append_value(*v as i64),
                     _ => builder.append_null(),
                 }
             }
@@ -736,50 +3100,84 @@ fn build_column_array(meta: &ColumnMetadata, rows: &[Row], col_index: usize) ->
         }
         DataType::Float64 => {
             let mut builder = Float64Builder::with_capacity(row_count);
+            let mut special = 0;
             for row in rows {
-                match row.cols.get(col_index).map(|c| &c.value) {
-                    Some(rsfbclient::SqlType::Floating(v)) => builder.append_value(*v),
-                    Some(rsfbclient::SqlType::Integer(v)) => builder.append_value(*v as f64),
+                match row.get(col_index) {
+                    Some(Value::Floating(v)) if !v.is_finite() => {
+                        special += 1;
+                        match table.special_floats {
+                            SpecialFloats::Keep => builder.append_value(*v),
+                            SpecialFloats::Null => builder.append_null(),
+                            SpecialFloats::Error => {
+                                anyhow::bail!("{} value; use --special-floats keep or null to write it", v)
+                            }
+                        }
+                    }
+                    Some(Value::Floating(v)) => builder.append_value(*v),
+                    Some(Value::Integer(v)) => builder.append_value(*v as f64),
                     _ => builder.append_null(),
                 }
             }
+            if special > 0 {
+                table.special_floats_seen.fetch_add(special, Ordering::Relaxed);
+            }
             Arc::new(builder.finish())
         }
-        DataType::Utf8 => {
-            let mut builder = StringBuilder::with_capacity(row_count, row_count * 64);
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::with_capacity(row_count);
             for row in rows {
-                match row.cols.get(col_index).map(|c| &c.value) {
-                    Some(rsfbclient::SqlType::Text(t)) => {
-                        if meta.is_text_blob {
-                            let normalized = String::from_utf8_lossy(t.as_bytes()).trim().to_string();
-                            builder.append_value(normalized);
-                        } else {
-                            builder.append_value(t.trim());
-                        }
-                    }
-                    Some(rsfbclient::SqlType::Integer(v)) => builder.append_value(v.to_string()),
-                    Some(rsfbclient::SqlType::Floating(v)) => builder.append_value(v.to_string()),
-                    Some(rsfbclient::SqlType::Boolean(b)) => {
-                        builder.append_value(if *b { "true" } else { "false" })
+                match row.get(col_index) {
+                    Some(Value::Boolean(b)) => builder.append_value(*b),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Date32 => {
+            let mut builder = Date32Builder::with_capacity(row_count);
+            for row in rows {
+                match row.get(col_index) {
+                    Some(Value::Timestamp(ts)) => {
+                        builder.append_value(ts.and_utc().timestamp().div_euclid(86_400) as i32)
                     }
                     _ => builder.append_null(),
                 }
             }
             Arc::new(builder.finish())
         }
-        DataType::Binary => {
-            let mut builder = BinaryBuilder::new();
+        DataType::Time64(TimeUnit::Microsecond) => {
+            let mut builder = Time64MicrosecondBuilder::with_capacity(row_count);
             for row in rows {
-                match row.cols.get(col_index).map(|c| &c.value) {
-                    Some(rsfbclient::SqlType::Text(t)) => {
-                        // Text blob as binary
-                        builder.append_value(t.as_bytes());
+                match row.get(col_index) {
+                    Some(Value::Timestamp(ts)) => {
+                        let micros = ts.num_seconds_from_midnight() as i64 * 1_000_000 + ts.nanosecond() as i64 / 1_000;
+                        builder.append_value(micros)
                     }
                     _ => builder.append_null(),
                 }
             }
             Arc::new(builder.finish())
         }
+        // A zone makes the values UTC instants, converted per `timestamp_zone`
+        DataType::Timestamp(TimeUnit::Microsecond, zone) => {
+            let mut builder = TimestampMicrosecondBuilder::with_capacity(row_count).with_timezone_opt(zone.clone());
+            let to_utc = |ts: NaiveDateTime| match zone {
+                Some(_) => meta.field.timestamp_zone.to_utc(ts),
+                None => ts,
+            };
+            for row in rows {
+                match row.get(col_index) {
+                    Some(Value::Timestamp(ts)) => builder.append_value(to_utc(*ts).and_utc().timestamp_micros()),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Utf8 => build_text_array::<i32>(table, rows, col_index)?,
+        DataType::LargeUtf8 => build_text_array::<i64>(table, rows, col_index)?,
+        DataType::Binary => build_binary_array::<i32>(rows, col_index)?,
+        DataType::LargeBinary => build_binary_array::<i64>(rows, col_index)?,
+        DataType::Dictionary(..) => dictionary::encode(build_text_array::<i32>(table, rows, col_index)?)?,
         _ => {
             // Fallback: convert to string
             let mut builder = StringBuilder::with_capacity(row_count, row_count * 32);
@@ -788,28 +3186,212 @@ fn build_column_array(meta: &ColumnMetadata, rows: &[Row], col_index: usize) ->
             }
             Arc::new(builder.finish())
         }
+    })
+}
+
+/// A text column, `Utf8` or (with `i64` offsets) `LargeUtf8`.
+fn build_text_array<O: OffsetSizeTrait>(table: &TableMetadata, rows: &[Row], col_index: usize) -> Result<ArrayRef> {
+    // Size the value buffer up front so it rarely reallocates. Rows own
+    // their values, so text is copied once, as a trimmed slice, into the
+    // Arrow buffer; numbers are formatted straight into it instead of
+    // through a temporary String.
+    let data_bytes: usize = rows
+        .iter()
+        .map(|row| match row.get(col_index) {
+            Some(Value::Text(t)) => t.trim().len(),
+            Some(Value::Binary(b)) => b.len(),
+            Some(Value::Integer(_)) | Some(Value::Floating(_)) => 24,
+            Some(Value::Boolean(_)) => 5,
+            _ => 0,
+        })
+        .sum();
+    check_offsets::<O>(data_bytes)?;
+    let mut builder = GenericStringBuilder::<O>::with_capacity(rows.len(), data_bytes);
+    let (mut nul_bytes, mut replacement_chars) = (0, 0);
+    for row in rows {
+        let text = match row.get(col_index) {
+            // Decoded from the connection charset by the driver
+            Some(Value::Text(t)) => Cow::Borrowed(t.as_str()),
+            // Bytes the driver left undecoded (OCTETS, NONE): invalid UTF-8
+            // becomes U+FFFD, counted below; valid text isn't copied
+            Some(Value::Binary(b)) => String::from_utf8_lossy(b),
+            Some(Value::Integer(v)) => {
+                let _ = write!(builder, "{}", v);
+                builder.append_value("");
+                continue;
+            }
+            Some(Value::Floating(v)) => {
+                let _ = write!(builder, "{}", v);
+                builder.append_value("");
+                continue;
+            }
+            Some(Value::Boolean(b)) => {
+                builder.append_value(if *b { "true" } else { "false" });
+                continue;
+            }
+            _ => {
+                builder.append_null();
+                continue;
+            }
+        };
+        let text = text.trim();
+        if !text.contains(['\0', '\u{FFFD}']) {
+            builder.append_value(text);
+            continue;
+        }
+        let nuls = text.matches('\0').count();
+        nul_bytes += nuls;
+        replacement_chars += text.matches('\u{FFFD}').count();
+        match table.nul_bytes {
+            NulBytes::Strip if nuls > 0 => builder.append_value(text.replace('\0', "")),
+            NulBytes::Error if nuls > 0 => {
+                anyhow::bail!("NUL character in text; use --nul-bytes keep or strip to write it")
+            }
+            _ => builder.append_value(text),
+        }
     }
+    let counts = &table.text_counts[col_index];
+    counts.nul_bytes.fetch_add(nul_bytes, Ordering::Relaxed);
+    counts.replacement_chars.fetch_add(replacement_chars, Ordering::Relaxed);
+    Ok(Arc::new(builder.finish()))
 }
 
-fn fb_to_arrow_type(fb_type: i16, subtype: i16) -> (DataType, bool) {
-    match fb_type {
-        7 => (DataType::Int64, false),   // SMALLINT
-        8 => (DataType::Int64, false),   // INTEGER
-        16 => (DataType::Int64, false),  // BIGINT
-        10 => (DataType::Float64, false), // FLOAT
-        27 => (DataType::Float64, false), // DOUBLE
-        12 => {
-            if subtype == 1 {
-                (DataType::Utf8, true)  // BLOB SUB_TYPE TEXT
-            } else {
-                (DataType::Binary, false)  // BLOB
+/// A binary column, `Binary` or (with `i64` offsets) `LargeBinary`.
+fn build_binary_array<O: OffsetSizeTrait>(rows: &[Row], col_index: usize) -> Result<ArrayRef> {
+    let data_bytes: usize = rows
+        .iter()
+        .map(|row| match row.get(col_index) {
+            Some(Value::Text(t)) => t.len(),
+            Some(Value::Binary(b)) => b.len(),
+            _ => 0,
+        })
+        .sum();
+    check_offsets::<O>(data_bytes)?;
+    let mut builder = GenericBinaryBuilder::<O>::with_capacity(rows.len(), data_bytes);
+    for row in rows {
+        match row.get(col_index) {
+            // Text blob as binary
+            Some(Value::Text(t)) => builder.append_value(t.as_bytes()),
+            Some(Value::Binary(b)) => builder.append_value(b),
+            _ => builder.append_null(),
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+/// Fail before a builder with 32-bit offsets would overflow them; batch sizes
+/// normally stay below, but a batch of rows wider than expected can't.
+fn check_offsets<O: OffsetSizeTrait>(data_bytes: usize) -> Result<()> {
+    if !O::IS_LARGE && data_bytes > i32::MAX as usize {
+        anyhow::bail!(
+            "{} bytes in one batch overflow 32-bit offsets; pass --large-blobs for blob columns, or a smaller \
+             --batch-size",
+            format_number(data_bytes as i64)
+        );
+    }
+    Ok(())
+}
+
+/// Narrow `columns` to the configured subset (in its order) and apply type overrides.
+fn apply_column_overrides(table: &str, mut columns: Vec<ColumnMetadata>, overrides: &TableOverrides) -> Result<Vec<ColumnMetadata>> {
+    if let Some(ref wanted) = overrides.columns {
+        let mut selected = Vec::with_capacity(wanted.len());
+        for name in wanted {
+            let pos = find_column(&columns, name).with_context(|| format!("Column {} not found in {}", name, table))?;
+            selected.push(columns.remove(pos));
+        }
+        columns = selected;
+    }
+
+    for (name, type_name) in &overrides.types {
+        let pos = find_column(&columns, name)
+            .with_context(|| format!("Type override for unknown column {}.{}", table, name))?;
+        let column = &mut columns[pos];
+        column.data_type = match type_name.to_ascii_lowercase().as_str() {
+            "int64" => DataType::Int64,
+            "float64" => DataType::Float64,
+            "utf8" => DataType::Utf8,
+            "binary" => DataType::Binary,
+            "dictionary" => dictionary::dictionary_type(),
+            other => {
+                let e = anyhow::anyhow!(
+                    "Unsupported type '{}' for {}.{} (expected int64, float64, utf8, binary or dictionary)",
+                    other,
+                    table,
+                    name
+                );
+                return Err(ExtractError::tag(ExtractError::TypeMapping, e));
             }
+        };
+    }
+
+    for (name, output_name) in &overrides.rename {
+        let pos = find_column(&columns, name).with_context(|| format!("Rename of unknown column {}.{}", table, name))?;
+        columns[pos].output_name = output_name.clone();
+    }
+
+    for (name, mask) in &overrides.masks {
+        let pos = find_column(&columns, name).with_context(|| format!("Mask for unknown column {}.{}", table, name))?;
+        let column = &mut columns[pos];
+        if let Err(e) = mask.check(&column.data_type) {
+            let e = anyhow::anyhow!("Invalid mask for {}.{}: {}", table, name, e);
+            return Err(ExtractError::tag(ExtractError::TypeMapping, e));
+        }
+        column.mask = Some(mask.clone());
+    }
+    Ok(columns)
+}
+
+/// Rows of the partitions of `plan` that select a NULL partition key.
+pub(crate) fn null_key_rows(plan: &[PartitionSpec], partitions: &[PartitionStats]) -> usize {
+    partitions
+        .iter()
+        .filter(|p| plan.get(p.index).is_some_and(PartitionSpec::is_null_key))
+        .map(|p| p.rows)
+        .sum()
+}
+
+/// Position of column `name`: an exact match, else a case-insensitive one.
+fn find_column(columns: &[ColumnMetadata], name: &str) -> Option<usize> {
+    columns
+        .iter()
+        .position(|c| c.name == name)
+        .or_else(|| columns.iter().position(|c| c.name.eq_ignore_ascii_case(name)))
+}
+
+/// Firebird's own tables: metadata (`RDB$`, `SEC$`) and monitoring (`MON$`).
+pub(crate) fn is_system_table(table: &str) -> bool {
+    ["RDB$", "SEC$", "MON$"].iter().any(|prefix| table.starts_with(prefix))
+}
+
+/// `MON$` tables, which each transaction sees as its own snapshot, taken on
+/// first access.
+pub(crate) fn is_monitoring_table(table: &str) -> bool {
+    table.starts_with("MON$")
+}
+
+/// The stored name of table `name`. A `"quoted"` name must match exactly; an
+/// unquoted one matches exactly or else upper-cased, as Firebird stores
+/// unquoted identifiers. Unknown tables are an error, so a name never reaches
+/// generated SQL without being checked against the system tables first.
+pub(crate) fn resolve_table(conn: &mut dyn SourceConnection, name: &str) -> Result<String> {
+    let name = name.trim();
+    if let Some(quoted) = name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        let exact = quoted.replace("\"\"", "\"");
+        if conn.has_table(&exact)? {
+            return Ok(exact);
+        }
+    } else {
+        if conn.has_table(name)? {
+            return Ok(name.to_string());
+        }
+        let upper = name.to_uppercase();
+        if upper != name && conn.has_table(&upper)? {
+            return Ok(upper);
         }
-        14 => (DataType::Utf8, false),  // CHAR
-        37 => (DataType::Utf8, false),  // VARCHAR
-        23 => (DataType::Float64, false), // FLOAT
-        _ => (DataType::Utf8, false),   // Default to string
     }
+    let e = anyhow::anyhow!("Table {} not found", name);
+    Err(ExtractError::tag(ExtractError::Metadata, e))
 }
 
 fn format_number(n: i64) -> String {
@@ -827,6 +3409,34 @@ fn format_number(n: i64) -> String {
     result
 }
 
+pub(crate) fn rows_per_sec(rows: usize, secs: f64) -> f64 {
+    if secs > 0.0 {
+        rows as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// One line per partition after a partitioned extraction, for spotting skew
+/// and slow workers.
+fn log_partition_table(partitions: &[PartitionStats]) {
+    info!("  {:>4} {:>12} {:>8} {:>7} {:>9} {:>10}  Range", "#", "Rows", "Time", "Retries", "MB read", "Rows/s");
+    for p in partitions {
+        let failed = if p.error.is_some() { " (failed)" } else { "" };
+        info!(
+            "  {:>4} {:>12} {:>8} {:>7} {:>9.1} {:>10.0}  {}{}",
+            p.index,
+            format_number(p.rows as i64),
+            format_duration(p.duration_secs),
+            p.retries,
+            p.bytes_read as f64 / (1024.0 * 1024.0),
+            p.rows_per_sec,
+            p.range,
+            failed
+        );
+    }
+}
+
 fn format_duration(secs: f64) -> String {
     let total_secs = secs as u64;
     let mins = total_secs / 60;
@@ -0,0 +1,296 @@
+//! Arrow Flight server (`flight` feature)
+//!
+//! [`FlightServer`] serves tables as Flight streams straight from
+//! [`Extractor::batch_stream`], so remote consumers get record batches with no
+//! Parquet files in between. A ticket is a table name, or JSON
+//! `{"table": "ORDERS", "where": "STATUS = 'open'"}` to narrow the table with
+//! a predicate like the `where` override; it must balance its parentheses and
+//! can't hold `;` or comments, so it can't widen past the configured
+//! `where` and `row_filter`. Arbitrary queries aren't accepted:
+//! extraction works from a table's metadata. `GetFlightInfo` and `GetSchema`
+//! take a descriptor whose path is the table name, or whose command is a
+//! ticket; `ListFlights` lists the user tables.
+
+use std::{future::Future, net::SocketAddr};
+
+use anyhow::{Context, Result};
+use arrow::{datatypes::SchemaRef, error::ArrowError, ipc::writer::IpcWriteOptions, record_batch::RecordBatchReader};
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::{FlightService, FlightServiceServer},
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, PollInfo, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::info;
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+
+/// What a ticket or descriptor asks for.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TableRequest {
+    table: String,
+    /// ANDed with the table's configured `where`, if any
+    #[serde(rename = "where", default, skip_serializing_if = "Option::is_none")]
+    filter: Option<String>,
+}
+
+impl TableRequest {
+    fn from_ticket(ticket: &[u8]) -> Result<Self, Status> {
+        let text = std::str::from_utf8(ticket)
+            .map_err(|_| Status::invalid_argument("Ticket is not UTF-8"))?
+            .trim();
+        if text.starts_with('{') {
+            let request: Self =
+                serde_json::from_str(text).map_err(|e| Status::invalid_argument(format!("Invalid ticket: {}", e)))?;
+            if let Some(ref filter) = request.filter {
+                check_filter(filter).map_err(|e| Status::invalid_argument(format!("Invalid where: {}", e)))?;
+            }
+            return Ok(request);
+        }
+        if text.is_empty() {
+            return Err(Status::invalid_argument("Ticket names no table"));
+        }
+        Ok(Self { table: text.to_string(), filter: None })
+    }
+
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self, Status> {
+        match descriptor.path.as_slice() {
+            [table] => Ok(Self { table: table.clone(), filter: None }),
+            [] => Self::from_ticket(&descriptor.cmd),
+            _ => Err(Status::invalid_argument("Descriptor path must be a single table name")),
+        }
+    }
+
+    /// The ticket `DoGet` accepts for this request.
+    fn ticket(&self) -> Ticket {
+        match self.filter {
+            None => Ticket::new(self.table.clone()),
+            Some(_) => Ticket::new(serde_json::to_string(self).unwrap_or_default()),
+        }
+    }
+}
+
+/// Serves an extractor's tables over Arrow Flight. Also a tonic service, for
+/// embedding in an existing server.
+#[derive(Clone)]
+pub struct FlightServer {
+    extractor: Extractor,
+}
+
+impl FlightServer {
+    pub fn new(extractor: Extractor) -> Self {
+        Self { extractor }
+    }
+
+    /// Serve on `addr` until `shutdown` completes. Streams still running then
+    /// end once the extractor's cancellation token is cancelled.
+    pub async fn serve(self, addr: SocketAddr, shutdown: impl Future<Output = ()> + Send) -> Result<()> {
+        info!("Serving Arrow Flight on {}", addr);
+        tonic::transport::Server::builder()
+            .add_service(FlightServiceServer::new(self))
+            .serve_with_shutdown(addr, shutdown)
+            .await
+            .context("Flight server failed")
+    }
+
+    /// This server's extractor with the request's filter applied. It narrows
+    /// the configured `where`; the table's `row_filter` stays a separate
+    /// predicate ANDed outside both.
+    fn extractor_for(&self, request: &TableRequest) -> Extractor {
+        let mut extractor = self.extractor.clone();
+        if let Some(ref filter) = request.filter {
+            let mut overrides = extractor.config.table_overrides(&request.table).cloned().unwrap_or_default();
+            overrides.filter = Some(match overrides.filter.take() {
+                Some(configured) => format!("({}) AND ({})", configured, filter),
+                None => format!("({})", filter),
+            });
+            extractor.config.set_table_overrides(&request.table, overrides);
+        }
+        extractor
+    }
+
+    async fn schema(&self, request: &TableRequest) -> Result<SchemaRef, Status> {
+        let extractor = self.extractor_for(request);
+        let table = request.table.clone();
+        task::spawn_blocking(move || extractor.table_schema(&table))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(status)
+    }
+}
+
+/// Reject a client predicate that could reach outside its parentheses or
+/// add a statement: it must balance its parentheses and have no `;` or
+/// comment outside string literals and quoted names.
+fn check_filter(filter: &str) -> Result<(), &'static str> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut chars = filter.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            // A doubled quote inside a literal is an escaped one and leaves it open
+            (Some(q), _) if c == q && chars.peek() == Some(&q) => {
+                chars.next();
+            }
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.checked_sub(1).ok_or("unbalanced parentheses")?,
+            (None, ';') => return Err("';' is not allowed"),
+            (None, '-') if chars.peek() == Some(&'-') => return Err("comments are not allowed"),
+            (None, '/') if chars.peek() == Some(&'*') => return Err("comments are not allowed"),
+            (None, _) => {}
+        }
+    }
+    match (quote, depth) {
+        (Some(_), _) => Err("unterminated string or quoted name"),
+        (None, 0) if filter.trim().is_empty() => Err("empty predicate"),
+        (None, 0) => Ok(()),
+        _ => Err("unbalanced parentheses"),
+    }
+}
+
+fn status(e: ExtractError) -> Status {
+    let message = format!("{:#}", e);
+    match e {
+        ExtractError::Metadata(_) => Status::not_found(message),
+        ExtractError::Connection(_) => Status::unavailable(message),
+        ExtractError::Cancelled(_) => Status::cancelled(message),
+        _ => Status::internal(message),
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for FlightServer {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("No authentication; put a proxy in front"))
+    }
+
+    async fn list_flights(&self, _request: Request<Criteria>) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let extractor = self.extractor.clone();
+        let tables = task::spawn_blocking(move || extractor.list_tables())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(status)?;
+        let infos = tables.into_iter().map(|table| {
+            let request = TableRequest { table: table.name, filter: None };
+            Ok(FlightInfo::new()
+                .with_endpoint(FlightEndpoint::new().with_ticket(request.ticket()))
+                .with_descriptor(FlightDescriptor::new_path(vec![request.table]))
+                .with_total_records(table.estimated_rows.unwrap_or(-1)))
+        });
+        Ok(Response::new(futures::stream::iter(infos).boxed()))
+    }
+
+    async fn get_flight_info(&self, request: Request<FlightDescriptor>) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        let table = TableRequest::from_descriptor(&descriptor)?;
+        let schema = self.schema(&table).await?;
+        let info = FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_endpoint(FlightEndpoint::new().with_ticket(table.ticket()))
+            .with_descriptor(descriptor)
+            .with_ordered(true);
+        Ok(Response::new(info))
+    }
+
+    async fn poll_flight_info(&self, _request: Request<FlightDescriptor>) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Use GetFlightInfo"))
+    }
+
+    async fn get_schema(&self, request: Request<FlightDescriptor>) -> Result<Response<SchemaResult>, Status> {
+        let table = TableRequest::from_descriptor(request.get_ref())?;
+        let schema = self.schema(&table).await?;
+        let result: SchemaResult = SchemaAsIpc::new(&schema, &IpcWriteOptions::default())
+            .try_into()
+            .map_err(|e: ArrowError| Status::internal(e.to_string()))?;
+        Ok(Response::new(result))
+    }
+
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let table = TableRequest::from_ticket(&request.get_ref().ticket)?;
+        let extractor = self.extractor_for(&table);
+        let stream = task::spawn_blocking(move || extractor.batch_stream(&table.table))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(status)?;
+        let schema = stream.schema();
+
+        // Batches are fetched on a blocking thread; a client going away drops
+        // the receiver, which drops the stream and stops the fetch
+        let (tx, rx) = mpsc::channel(1);
+        task::spawn_blocking(move || {
+            for batch in stream {
+                if tx.blocking_send(batch.map_err(FlightError::from)).is_err() {
+                    break;
+                }
+            }
+        });
+        let batches = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|batch| (batch, rx)) });
+        let data = FlightDataEncoderBuilder::new().with_schema(schema).build(batches).map_err(Status::from);
+        Ok(Response::new(data.boxed()))
+    }
+
+    async fn do_put(&self, _request: Request<Streaming<FlightData>>) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("Tables are read-only"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("No actions"))
+    }
+
+    async fn list_actions(&self, _request: Request<Empty>) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(futures::stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("Tables are read-only"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_filter;
+
+    #[test]
+    fn accepts_plain_predicates() {
+        assert!(check_filter("STATUS = 'open'").is_ok());
+        assert!(check_filter("(A = 1 OR B = 2) AND C IN (3, 4)").is_ok());
+        assert!(check_filter("NAME = 'it''s (; -- /*'").is_ok());
+        assert!(check_filter("\"Odd)Name\" = 1").is_ok());
+    }
+
+    #[test]
+    fn rejects_escapes() {
+        assert!(check_filter("1 = 1) OR (1 = 1").is_err());
+        assert!(check_filter("(1 = 1").is_err());
+        assert!(check_filter("1 = 1; DELETE FROM T").is_err());
+        assert!(check_filter("1 = 1 -- ").is_err());
+        assert!(check_filter("1 = 1 /* */").is_err());
+        assert!(check_filter("NAME = 'open").is_err());
+        assert!(check_filter("  ").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! `--pk-gap-report`: missing ranges of an integer primary key
+//!
+//! Computed from the output's row group statistics alone, without reading
+//! any data. Row groups whose key ranges overlap (a partition's rows arrive
+//! unordered) are merged into clusters; the ranges between clusters are
+//! exact gaps, while inside a cluster only the number of missing ids is
+//! known: its span minus its row count.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use parquet::{arrow::arrow_reader::ParquetRecordBatchReaderBuilder, file::statistics::Statistics};
+use serde::Serialize;
+
+/// Written as `<table>.pk_gaps.json` beside the output.
+#[derive(Debug, Serialize)]
+pub(crate) struct GapReport {
+    pub(crate) table: String,
+    pub(crate) column: String,
+    pub(crate) min: Option<i64>,
+    pub(crate) max: Option<i64>,
+    pub(crate) rows: u64,
+    /// Ids in `min..=max` with no row
+    pub(crate) missing_ids: u64,
+    /// Ranges known to hold no row, in key order
+    pub(crate) gaps: Vec<Gap>,
+    /// Ranges known to have missing ids, though not which, in key order
+    pub(crate) sparse: Vec<Sparse>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Gap {
+    pub(crate) from: i64,
+    pub(crate) to: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Sparse {
+    pub(crate) from: i64,
+    pub(crate) to: i64,
+    pub(crate) rows: u64,
+    pub(crate) missing_ids: u64,
+}
+
+/// Key range and non-null row count of a row group, or of merged ones.
+struct Cluster {
+    min: i64,
+    max: i64,
+    rows: u64,
+}
+
+impl Cluster {
+    fn span(&self) -> u64 {
+        (self.max as i128 - self.min as i128 + 1) as u64
+    }
+}
+
+/// Gaps of unique integer key `column` in `output_path`.
+pub(crate) fn gap_report(table: &str, column: &str, output_path: &Path) -> Result<GapReport> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(output_path)?)?;
+    let index = builder
+        .parquet_schema()
+        .columns()
+        .iter()
+        .position(|c| c.name() == column)
+        .with_context(|| format!("{} not in {}", column, output_path.display()))?;
+
+    let mut clusters = Vec::new();
+    for row_group in builder.metadata().row_groups() {
+        let chunk = row_group.column(index);
+        let Some(Statistics::Int64(stats)) = chunk.statistics() else {
+            anyhow::bail!("{} has no integer statistics for {}", output_path.display(), column);
+        };
+        if let (Some(&min), Some(&max)) = (stats.min_opt(), stats.max_opt()) {
+            let nulls = stats.null_count_opt().unwrap_or(0);
+            clusters.push(Cluster { min, max, rows: row_group.num_rows() as u64 - nulls });
+        }
+    }
+
+    clusters.sort_by_key(|c| c.min);
+    let mut merged: Vec<Cluster> = Vec::with_capacity(clusters.len());
+    for cluster in clusters {
+        match merged.last_mut() {
+            Some(last) if cluster.min <= last.max => {
+                last.max = last.max.max(cluster.max);
+                last.rows += cluster.rows;
+            }
+            _ => merged.push(cluster),
+        }
+    }
+
+    let gaps = merged
+        .windows(2)
+        .filter(|pair| pair[1].min > pair[0].max + 1)
+        .map(|pair| Gap { from: pair[0].max + 1, to: pair[1].min - 1 })
+        .collect();
+    let sparse = merged
+        .iter()
+        .filter(|c| c.span() > c.rows)
+        .map(|c| Sparse { from: c.min, to: c.max, rows: c.rows, missing_ids: c.span() - c.rows })
+        .collect();
+    let (min, max) = (merged.first().map(|c| c.min), merged.last().map(|c| c.max));
+    let rows = merged.iter().map(|c| c.rows).sum();
+    let missing_ids = match (min, max) {
+        (Some(min), Some(max)) => ((max as i128 - min as i128 + 1) as u64).saturating_sub(rows),
+        _ => 0,
+    };
+    Ok(GapReport { table: table.to_string(), column: column.to_string(), min, max, rows, missing_ids, gaps, sparse })
+}
+
+impl GapReport {
+    /// `orders.parquet` → `orders.pk_gaps.json`
+    pub(crate) fn path_for(output_file: &Path) -> PathBuf {
+        output_file.with_extension("pk_gaps.json")
+    }
+
+    pub(crate) fn write(&self, output_file: &Path) -> Result<PathBuf> {
+        let path = Self::path_for(output_file);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write PK gap report {}", path.display()))?;
+        Ok(path)
+    }
+}
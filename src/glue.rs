@@ -0,0 +1,98 @@
+//! AWS Glue Data Catalog registration (`glue` feature)
+//!
+//! [`GlueCatalog::register`] creates a [`CatalogTable`] in Glue, or updates
+//! its columns and location if it exists, so Athena can query the files as
+//! soon as they're uploaded. Credentials and region come from the usual AWS
+//! sources: environment, profile, or instance role.
+
+use anyhow::{Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_glue::{
+    error::DisplayErrorContext,
+    types::{Column, SerDeInfo, StorageDescriptor, TableInput},
+    Client,
+};
+use tokio::runtime::Runtime;
+use tracing::info;
+
+use crate::catalog::CatalogTable;
+
+const INPUT_FORMAT: &str = "org.apache.hadoop.hive.ql.io.parquet.MapredParquetInputFormat";
+const OUTPUT_FORMAT: &str = "org.apache.hadoop.hive.ql.io.parquet.MapredParquetOutputFormat";
+const SERDE: &str = "org.apache.hadoop.hive.ql.io.parquet.serde.ParquetHiveSerDe";
+
+/// What [`GlueCatalog::register`] did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Registration {
+    Created,
+    Updated,
+}
+
+/// A Glue client. The SDK is async; calls block on a runtime of their own.
+pub struct GlueCatalog {
+    client: Client,
+    runtime: Runtime,
+}
+
+impl GlueCatalog {
+    /// Connect with the default credential chain; `region` overrides the
+    /// configured one.
+    pub fn new(region: Option<&str>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start the Glue client runtime")?;
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region.to_string()));
+        }
+        let config = runtime.block_on(loader.load());
+        Ok(Self { client: Client::new(&config), runtime })
+    }
+
+    /// Create `table`, or replace the definition of the existing table.
+    pub fn register(&self, table: &CatalogTable) -> Result<Registration> {
+        let input = table_input(table)?;
+        let created = self.runtime.block_on(
+            self.client.create_table().database_name(&table.database).table_input(input.clone()).send(),
+        );
+        let registration = match created {
+            Ok(_) => Registration::Created,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_already_exists_exception()) => {
+                self.runtime
+                    .block_on(self.client.update_table().database_name(&table.database).table_input(input).send())
+                    .map_err(|e| anyhow::anyhow!("{}", DisplayErrorContext(e)))
+                    .with_context(|| format!("Failed to update Glue table {}.{}", table.database, table.name))?;
+                Registration::Updated
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!("{}", DisplayErrorContext(e)))
+                    .with_context(|| format!("Failed to create Glue table {}.{}", table.database, table.name))
+            }
+        };
+        info!("  Glue table {}.{} {:?} at {}", table.database, table.name, registration, table.location);
+        Ok(registration)
+    }
+}
+
+fn table_input(table: &CatalogTable) -> Result<TableInput> {
+    let columns = table
+        .columns
+        .iter()
+        .map(|(name, hive_type)| Column::builder().name(name).r#type(hive_type).build())
+        .collect::<Result<Vec<_>, _>>()?;
+    let storage = StorageDescriptor::builder()
+        .set_columns(Some(columns))
+        .location(&table.location)
+        .input_format(INPUT_FORMAT)
+        .output_format(OUTPUT_FORMAT)
+        .serde_info(SerDeInfo::builder().serialization_library(SERDE).build())
+        .build();
+    Ok(TableInput::builder()
+        .name(&table.name)
+        .table_type("EXTERNAL_TABLE")
+        .parameters("classification", "parquet")
+        .parameters("EXTERNAL", "TRUE")
+        .storage_descriptor(storage)
+        .build()?)
+}
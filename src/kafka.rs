@@ -0,0 +1,356 @@
+//! Kafka sink (`kafka` feature)
+//!
+//! [`Extractor::extract_to_kafka`] reads a table through
+//! [`Extractor::batch_stream`] and produces it to a topic instead of writing
+//! Parquet. Arrow IPC messages hold a slice of a batch each, as a complete
+//! IPC stream (schema and batch) sized to fit the broker's message limit. Avro
+//! messages hold one row each in the Confluent wire format: the value schema
+//! is registered with a schema registry and its id prefixed to every message.
+//! Messages are keyed by table name and carry `table` and `format` headers.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use apache_avro::{to_avro_datum, types::Value as AvroValue, Schema as AvroSchema};
+use arrow::{
+    array::{Array, ArrayRef, AsArray},
+    datatypes::{
+        DataType, Date32Type, Float32Type, Float64Type, Int32Type, Int64Type, Schema, Time64MicrosecondType,
+        TimeUnit, TimestampMicrosecondType,
+    },
+    ipc::writer::StreamWriter,
+    record_batch::{RecordBatch, RecordBatchReader},
+};
+use rdkafka::{
+    config::ClientConfig,
+    error::{KafkaError, RDKafkaErrorCode},
+    message::{Header, OwnedHeaders},
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+    util::Timeout,
+    ClientContext,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::error::ExtractError;
+use crate::extractor::{ExtractionStats, Extractor};
+use crate::stream::stream_error;
+
+/// Kafka's default `message.max.bytes`.
+const DEFAULT_MAX_MESSAGE_BYTES: usize = 1_000_000;
+
+/// How long to wait for outstanding deliveries after a table.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Encoding of produced messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KafkaFormat {
+    /// Arrow IPC streams of up to a message's worth of rows
+    #[default]
+    ArrowIpc,
+    /// One Avro record per row, framed for a schema registry
+    Avro,
+}
+
+impl fmt::Display for KafkaFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ArrowIpc => "arrow-ipc",
+            Self::Avro => "avro",
+        })
+    }
+}
+
+impl FromStr for KafkaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "arrow-ipc" | "arrow" | "ipc" => Ok(Self::ArrowIpc),
+            "avro" => Ok(Self::Avro),
+            other => Err(format!("unknown Kafka format '{}' (expected arrow-ipc or avro)", other)),
+        }
+    }
+}
+
+/// Records failed deliveries, which the producer reports asynchronously.
+#[derive(Default)]
+struct Deliveries {
+    failed: AtomicUsize,
+    first_error: Mutex<Option<String>>,
+}
+
+impl ClientContext for Deliveries {}
+
+impl ProducerContext for Deliveries {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        if let Err((e, _)) = result {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+            self.first_error.lock().unwrap().get_or_insert_with(|| e.to_string());
+        }
+    }
+}
+
+/// A Kafka producer and where and how tables are sent.
+pub struct KafkaSink {
+    producer: ThreadedProducer<Deliveries>,
+    topic: String,
+    format: KafkaFormat,
+    schema_registry: Option<String>,
+    max_message_bytes: usize,
+}
+
+impl KafkaSink {
+    /// Produce to `topic` on `brokers` (`host:9092,host2:9092`). A `{table}`
+    /// in the topic is replaced by the table name.
+    pub fn new(brokers: &str, topic: impl Into<String>, format: KafkaFormat) -> Result<Self, ExtractError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .create_with_context(Deliveries::default())
+            .map_err(|e| ExtractError::Connection(anyhow::anyhow!("Failed to create a Kafka producer: {}", e)))?;
+        Ok(Self {
+            producer,
+            topic: topic.into(),
+            format,
+            schema_registry: None,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+        })
+    }
+
+    /// Register Avro value schemas with the schema registry at `url`, under
+    /// the subject `<topic>-value`. Required for [`KafkaFormat::Avro`].
+    pub fn with_schema_registry(mut self, url: impl Into<String>) -> Self {
+        self.schema_registry = Some(url.into());
+        self
+    }
+
+    /// Largest Arrow IPC message to produce, matching the topic's
+    /// `max.message.bytes` (default 1 MB).
+    pub fn with_max_message_bytes(mut self, bytes: usize) -> Self {
+        self.max_message_bytes = bytes.max(1);
+        self
+    }
+
+    fn topic_for(&self, table_name: &str) -> String {
+        self.topic.replace("{table}", table_name)
+    }
+
+    /// Produce `payload`, waiting while the local queue is full.
+    fn send(&self, topic: &str, table_name: &str, payload: &[u8]) -> Result<()> {
+        let headers = OwnedHeaders::new()
+            .insert(Header { key: "table", value: Some(table_name) })
+            .insert(Header { key: "format", value: Some(&self.format.to_string()) });
+        let mut record = BaseRecord::to(topic).key(table_name).payload(payload).headers(headers);
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => return Ok(()),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    record = returned;
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err((e, _)) => anyhow::bail!("Producing to {} failed: {}", topic, e),
+            }
+        }
+    }
+
+    /// Wait for outstanding messages; `Err` if any delivery failed since the
+    /// last flush.
+    fn flush(&self, topic: &str) -> Result<()> {
+        self.producer.flush(Timeout::After(FLUSH_TIMEOUT)).context("Flushing the Kafka producer failed")?;
+        let context = self.producer.context();
+        let failed = context.failed.swap(0, Ordering::Relaxed);
+        if failed > 0 {
+            let first = context.first_error.lock().unwrap().take().unwrap_or_default();
+            anyhow::bail!("{} messages to {} were not delivered, first: {}", failed, topic, first);
+        }
+        Ok(())
+    }
+}
+
+enum Encoder {
+    ArrowIpc { max_message_bytes: usize },
+    Avro { schema: AvroSchema, schema_id: u32 },
+}
+
+impl Encoder {
+    fn messages(&self, batch: &RecordBatch) -> Result<Vec<Vec<u8>>> {
+        match self {
+            Self::ArrowIpc { max_message_bytes } => ipc_messages(batch, *max_message_bytes),
+            Self::Avro { schema, schema_id } => (0..batch.num_rows())
+                .map(|row| {
+                    let fields = batch
+                        .schema()
+                        .fields()
+                        .iter()
+                        .zip(batch.columns())
+                        .map(|(field, column)| (field.name().clone(), avro_value(column, row)))
+                        .collect();
+                    // Confluent wire format: magic byte, schema id, datum
+                    let mut message = vec![0];
+                    message.extend_from_slice(&schema_id.to_be_bytes());
+                    message.extend(to_avro_datum(schema, AvroValue::Record(fields))?);
+                    Ok(message)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// IPC streams of row slices small enough to fit `max_bytes`, estimated from
+/// the batch's average row size.
+fn ipc_messages(batch: &RecordBatch, max_bytes: usize) -> Result<Vec<Vec<u8>>> {
+    let rows = batch.num_rows();
+    let row_bytes = batch.get_array_memory_size() / rows.max(1) + 1;
+    // Headroom for the schema and IPC framing
+    let rows_per_message = (max_bytes * 3 / 4 / row_bytes).max(1);
+    (0..rows)
+        .step_by(rows_per_message)
+        .map(|offset| {
+            let slice = batch.slice(offset, rows_per_message.min(rows - offset));
+            let mut writer = StreamWriter::try_new(Vec::new(), &slice.schema())?;
+            writer.write(&slice)?;
+            Ok(writer.into_inner()?)
+        })
+        .collect()
+}
+
+/// Nullable Avro record schema for an Arrow schema.
+fn avro_schema(table_name: &str, schema: &Schema) -> Result<AvroSchema> {
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let avro_type = match field.data_type() {
+                DataType::Int64 => json!("long"),
+                DataType::Int32 => json!("int"),
+                DataType::Float64 => json!("double"),
+                DataType::Float32 => json!("float"),
+                DataType::Boolean => json!("boolean"),
+                DataType::Utf8 | DataType::LargeUtf8 => json!("string"),
+                DataType::Binary | DataType::LargeBinary => json!("bytes"),
+                DataType::Date32 => json!({ "type": "int", "logicalType": "date" }),
+                DataType::Time64(TimeUnit::Microsecond) => json!({ "type": "long", "logicalType": "time-micros" }),
+                DataType::Timestamp(TimeUnit::Microsecond, None) => {
+                    json!({ "type": "long", "logicalType": "local-timestamp-micros" })
+                }
+                // UTC instants under `timestamp_zone`
+                DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => {
+                    json!({ "type": "long", "logicalType": "timestamp-micros" })
+                }
+                other => anyhow::bail!("{}.{} is {}, which has no Avro mapping", table_name, field.name(), other),
+            };
+            Ok(json!({ "name": field.name(), "type": ["null", avro_type], "default": null }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let record = json!({ "type": "record", "name": table_name, "fields": fields });
+    AvroSchema::parse(&record).with_context(|| format!("Invalid Avro schema for {}", table_name))
+}
+
+/// Value of `row` in the `["null", T]` union of [`avro_schema`].
+fn avro_value(column: &ArrayRef, row: usize) -> AvroValue {
+    if column.is_null(row) {
+        return AvroValue::Union(0, Box::new(AvroValue::Null));
+    }
+    let value = match column.data_type() {
+        DataType::Int64 => AvroValue::Long(column.as_primitive::<Int64Type>().value(row)),
+        DataType::Int32 => AvroValue::Int(column.as_primitive::<Int32Type>().value(row)),
+        DataType::Float64 => AvroValue::Double(column.as_primitive::<Float64Type>().value(row)),
+        DataType::Float32 => AvroValue::Float(column.as_primitive::<Float32Type>().value(row)),
+        DataType::Boolean => AvroValue::Boolean(column.as_boolean().value(row)),
+        DataType::Utf8 => AvroValue::String(column.as_string::<i32>().value(row).to_string()),
+        DataType::LargeUtf8 => AvroValue::String(column.as_string::<i64>().value(row).to_string()),
+        DataType::Binary => AvroValue::Bytes(column.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => AvroValue::Bytes(column.as_binary::<i64>().value(row).to_vec()),
+        DataType::Date32 => AvroValue::Date(column.as_primitive::<Date32Type>().value(row)),
+        DataType::Time64(TimeUnit::Microsecond) => {
+            AvroValue::TimeMicros(column.as_primitive::<Time64MicrosecondType>().value(row))
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, None) => {
+            AvroValue::LocalTimestampMicros(column.as_primitive::<TimestampMicrosecondType>().value(row))
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => {
+            AvroValue::TimestampMicros(column.as_primitive::<TimestampMicrosecondType>().value(row))
+        }
+        other => unreachable!("{} is rejected by avro_schema", other),
+    };
+    AvroValue::Union(1, Box::new(value))
+}
+
+/// Register `schema` under `subject`, returning its id; registering an
+/// existing schema returns the existing id.
+fn register_schema(registry: &str, subject: &str, schema: &AvroSchema) -> Result<u32> {
+    #[derive(Deserialize)]
+    struct Registered {
+        id: u32,
+    }
+    let url = format!("{}/subjects/{}/versions", registry.trim_end_matches('/'), subject);
+    let body = json!({ "schema": serde_json::to_string(schema)? }).to_string();
+    let registered: Registered = ureq::post(&url)
+        .set("Content-Type", "application/vnd.schemaregistry.v1+json")
+        .send_string(&body)
+        .with_context(|| format!("Registering the schema of {} at {} failed", subject, registry))?
+        .into_json()?;
+    Ok(registered.id)
+}
+
+impl Extractor {
+    /// Produce a table's rows to Kafka instead of writing Parquet. The table
+    /// is read in one sequential query; the stats count rows and batches
+    /// only, no files.
+    pub fn extract_to_kafka(&self, table_name: &str, sink: &KafkaSink) -> Result<ExtractionStats, ExtractError> {
+        let start = Instant::now();
+        if sink.format == KafkaFormat::Avro && sink.schema_registry.is_none() {
+            return Err(ExtractError::Validation(anyhow::anyhow!("Avro messages need a schema registry")));
+        }
+        let stream = self.batch_stream(table_name)?;
+        let topic = sink.topic_for(table_name);
+        let encoder = match (sink.format, sink.schema_registry.as_deref()) {
+            (KafkaFormat::Avro, Some(registry)) => {
+                let schema = avro_schema(table_name, &stream.schema()).map_err(ExtractError::TypeMapping)?;
+                let schema_id = register_schema(registry, &format!("{}-value", topic), &schema)
+                    .map_err(ExtractError::Connection)?;
+                Encoder::Avro { schema, schema_id }
+            }
+            _ => Encoder::ArrowIpc { max_message_bytes: sink.max_message_bytes },
+        };
+
+        let mut stats = ExtractionStats::default();
+        let mut messages = 0;
+        let mut bytes = 0;
+        for batch in stream {
+            let batch = batch.map_err(stream_error)?;
+            for message in encoder.messages(&batch).map_err(ExtractError::TypeMapping)? {
+                sink.send(&topic, table_name, &message).map_err(ExtractError::WriterIo)?;
+                messages += 1;
+                bytes += message.len();
+            }
+            stats.rows_extracted += batch.num_rows();
+            stats.batches += 1;
+        }
+        sink.flush(&topic).map_err(ExtractError::WriterIo)?;
+        stats.duration_secs = start.elapsed().as_secs_f64();
+        info!(
+            "{}: {} rows in {} messages ({:.2} MB) to {} in {:.1}s",
+            table_name,
+            stats.rows_extracted,
+            messages,
+            bytes as f64 / (1024.0 * 1024.0),
+            topic,
+            stats.duration_secs
+        );
+        Ok(stats)
+    }
+}
@@ -1,6 +1,102 @@
 pub mod extractor;
+pub mod cancel;
+pub mod error;
+pub mod events;
 pub mod config;
+pub mod config_file;
+pub mod partition;
+pub mod manifest;
+pub mod memory;
+pub mod resources;
+pub mod retry;
+pub mod source;
+pub mod progress;
+pub mod stream;
+pub mod types;
+pub mod schedule;
+mod scheduler;
+mod orchestrate;
+mod profile;
+mod writer;
+mod blob;
+mod transform;
+mod validate;
+mod gaps;
+mod throttle;
+mod catalog;
+mod audit;
+mod mask;
+mod data_profile;
+mod diff;
+mod cdc;
+mod merge;
+mod soft_delete;
+mod sidecar;
+mod dictionary;
+mod sequences;
+mod ddl;
+mod subset;
+mod server_info;
+mod listen;
+mod bench;
+#[cfg(feature = "async")]
+mod async_extract;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "flight")]
+mod flight;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "glue")]
+mod glue;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 
-pub use extractor::{Extractor, ExtractionStats};
-pub use config::ExtractorConfig;
-
+pub use cancel::CancellationToken;
+pub use error::ExtractError;
+pub use events::ProgressEvent;
+pub use extractor::{
+    ColumnInfo, Extractor, ExtractionStats, PartitionStats, PoolStats, TableInfo, TableSummary, TextIssues,
+    EVENT_TARGET,
+};
+pub use config::{
+    Backend, BlobMode, ColumnCase, ExtractorConfig, PartitionStrategy, SoftDelete, SoftDeleteMode, TableOverrides,
+    TimestampZone,
+};
+pub use partition::{PartitionKind, PartitionSpec};
+pub use manifest::Manifest;
+pub use catalog::CatalogTable;
+pub use audit::{AuditEntry, DEFAULT_AUDIT_TABLE};
+pub use mask::{FakeKind, Mask, PartialKind};
+pub use data_profile::{ColumnProfile, ProfileValue, TableProfile, TopValue};
+pub use diff::{DiffReport, CHANGE_COLUMN};
+pub use cdc::CdcObjects;
+pub use merge::MergeStats;
+pub use sequences::{write_sequences, Sequence};
+pub use ddl::{CheckConstraint, DdlColumn, ForeignKey, Index, KeyConstraint, TableDdl};
+pub use subset::FollowFks;
+pub use server_info::{ServerFeature, ServerInfo};
+pub use listen::EventListener;
+pub use bench::SyntheticTable;
+#[cfg(feature = "metrics")]
+pub use metrics::{Metrics, MetricsSnapshot};
+#[cfg(feature = "otel")]
+pub use otel::Telemetry;
+#[cfg(feature = "server")]
+pub use server::Server;
+#[cfg(feature = "flight")]
+pub use flight::FlightServer;
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaFormat, KafkaSink};
+#[cfg(feature = "glue")]
+pub use glue::{GlueCatalog, Registration};
+pub use orchestrate::{ExtractionReport, FailurePolicy, TableOutcome, TableReport, TableSpec};
+pub use retry::RetryPolicy;
+pub use source::Source;
+pub use stream::BatchStream;
+pub use throttle::ThrottleStats;
+pub use types::{FieldInfo, TypeMapper};
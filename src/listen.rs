@@ -0,0 +1,95 @@
+//! Firebird events (`POST_EVENT`) as run triggers
+//!
+//! A trigger or procedure that runs `POST_EVENT 'TABLE_CHANGED'` notifies
+//! every attachment registered for that name when its transaction commits.
+//! [`Extractor::listen_for_events`] registers one connection per event name
+//! on a thread of its own, so `--watch` can start a run as soon as something
+//! changes instead of on the next timer tick. Events are counted, not
+//! queued: several posted while a run is going start one more run. A lost
+//! connection is reopened, and events posted meanwhile are missed, which is
+//! why an interval can still be given as a fallback. Events need the native
+//! client; the pure-Rust backend can't register for them.
+
+use std::{thread, time::Duration};
+
+use anyhow::Result;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use rsfbclient::SimpleConnection;
+use tracing::{debug, warn};
+
+use crate::config::{Backend, ExtractorConfig};
+use crate::error::ExtractError;
+use crate::extractor::{ConnectionPool, Extractor};
+
+/// Wait before reopening a listening connection that failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Event names as they are posted, from [`Extractor::listen_for_events`].
+/// The listening threads stay blocked on their connections until the next
+/// event after this is dropped.
+pub struct EventListener {
+    events: Receiver<String>,
+}
+
+impl EventListener {
+    /// The next event posted, waiting at most `timeout`.
+    pub fn wait(&self, timeout: Duration) -> Option<String> {
+        match self.events.recv_timeout(timeout) {
+            Ok(name) => Some(name),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Forget the events posted so far, returning how many there were.
+    pub fn drain(&self) -> usize {
+        self.events.try_iter().count()
+    }
+}
+
+impl Extractor {
+    /// Register for the Firebird events `names` on the primary host, one
+    /// connection per name. Fails if a connection can't be opened.
+    pub fn listen_for_events(&self, names: &[String]) -> Result<EventListener, ExtractError> {
+        if self.config.backend == Backend::PureRust {
+            let e = anyhow::anyhow!("Listening for events needs the native backend");
+            return Err(ExtractError::Validation(e));
+        }
+        let (tx, events) = unbounded();
+        for name in names {
+            let conn = ConnectionPool::connect_read_write(&self.config)
+                .map_err(|e| ExtractError::classify(e, ExtractError::Connection))?;
+            let (config, name, tx) = (self.config.clone(), name.clone(), tx.clone());
+            thread::spawn(move || listen(conn, &config, &name, &tx));
+        }
+        Ok(EventListener { events })
+    }
+}
+
+/// Pass each posting of `name` to `tx`, reconnecting after failures, until
+/// the listener is dropped.
+fn listen(conn: SimpleConnection, config: &ExtractorConfig, name: &str, tx: &Sender<String>) {
+    let mut conn = Some(conn);
+    loop {
+        let mut wait = || -> Result<()> {
+            let conn = match conn {
+                Some(ref mut conn) => conn,
+                None => conn.insert(ConnectionPool::connect_read_write(config)?),
+            };
+            conn.wait_for_event(name.to_string())?;
+            Ok(())
+        };
+        match wait() {
+            Ok(()) => {
+                debug!("Event {} posted", name);
+                if tx.send(name.to_string()).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("Listening for event {} failed: {:#}; retrying in {}s", name, e, RECONNECT_DELAY.as_secs());
+                conn = None;
+                thread::sleep(RECONNECT_DELAY);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Extraction manifest written next to each Parquet output
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use arrow::datatypes::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::extractor::PartitionStats;
+use crate::partition::PartitionSpec;
+
+/// What an extraction produced, stored as `<table>.manifest.json`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub table: String,
+    pub output_file: PathBuf,
+    pub rows: usize,
+    pub partitions: usize,
+    pub created_at_unix: u64,
+    /// Partitions failed and the output was kept because partial results were allowed
+    pub partial: bool,
+    /// Rows the source held when counted to verify the extraction (`verify_counts`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_rows: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_partitions: Vec<MissingPartition>,
+    /// Key range, rows, time, retries and bytes read of each partition, in plan order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition_stats: Vec<PartitionStats>,
+    /// Files later runs in append mode wrote beside `output_file`, oldest first;
+    /// each has its own manifest too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub appended_files: Vec<PathBuf>,
+    /// Columns `output_file` was written with; empty in manifests of older versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schema: Vec<ManifestField>,
+}
+
+/// One output column as recorded in a manifest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestField {
+    pub name: String,
+    /// Arrow type, e.g. `Int64`
+    pub data_type: String,
+}
+
+/// A partition whose rows are absent from a partial extraction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MissingPartition {
+    pub index: usize,
+    pub partition: PartitionSpec,
+    pub error: String,
+}
+
+impl Manifest {
+    pub fn new(table: &str, output_file: &Path) -> Self {
+        Self {
+            table: table.to_string(),
+            output_file: output_file.to_path_buf(),
+            created_at_unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    /// `orders.parquet` → `orders.manifest.json`
+    pub fn path_for(output_file: &Path) -> PathBuf {
+        output_file.with_extension("manifest.json")
+    }
+
+    pub fn write(&self) -> Result<PathBuf> {
+        let path = Self::path_for(&self.output_file);
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&path, json).with_context(|| format!("Failed to write manifest {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path).with_context(|| format!("Failed to read manifest {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    /// Schema of the newest file of `base_output`'s dataset that recorded one:
+    /// the last appended file, else the base file. `None` without a manifest
+    /// or when none recorded a schema.
+    pub fn latest_schema(base_output: &Path) -> Result<Option<Vec<ManifestField>>> {
+        let path = Self::path_for(base_output);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let base = Self::read(&path)?;
+        for appended in base.appended_files.iter().rev() {
+            let path = Self::path_for(appended);
+            if path.exists() {
+                let manifest = Self::read(&path)?;
+                if !manifest.schema.is_empty() {
+                    return Ok(Some(manifest.schema));
+                }
+            }
+        }
+        Ok(Some(base.schema).filter(|schema| !schema.is_empty()))
+    }
+
+    /// List `appended` in the manifest of `base_output`, creating that
+    /// manifest if the base file predates manifests.
+    pub fn record_append(table: &str, base_output: &Path, appended: &Path) -> Result<()> {
+        let path = Self::path_for(base_output);
+        let mut manifest = if path.exists() { Self::read(&path)? } else { Self::new(table, base_output) };
+        manifest.appended_files.push(appended.to_path_buf());
+        manifest.write()?;
+        Ok(())
+    }
+}
+
+pub(crate) fn schema_fields(schema: &Schema) -> Vec<ManifestField> {
+    schema
+        .fields()
+        .iter()
+        .map(|f| ManifestField { name: f.name().clone(), data_type: f.data_type().to_string() })
+        .collect()
+}
+
+/// Columns added, dropped or retyped from `previous` to `current`, described
+/// one per entry.
+pub(crate) fn schema_changes(previous: &[ManifestField], current: &[ManifestField]) -> Vec<String> {
+    let mut changes = Vec::new();
+    for field in current {
+        match previous.iter().find(|p| p.name == field.name) {
+            None => changes.push(format!("added {} ({})", field.name, field.data_type)),
+            Some(p) if p.data_type != field.data_type => {
+                changes.push(format!("{} changed from {} to {}", field.name, p.data_type, field.data_type))
+            }
+            Some(_) => {}
+        }
+    }
+    for field in previous {
+        if !current.iter().any(|c| c.name == field.name) {
+            changes.push(format!("dropped {} ({})", field.name, field.data_type));
+        }
+    }
+    changes
+}
@@ -0,0 +1,304 @@
+//! Column masking, so extracts for analytics and development hold no raw PII
+//!
+//! A table's `masks` override maps columns to a [`Mask`]; every batch has its
+//! masked columns replaced right after conversion, before transforms and
+//! writers see them. Masks keep nulls null and work on the output type:
+//!
+//! - `hash`: SHA-256 of the value with the configured salt; hex text for
+//!   text columns, the first 8 bytes for integers, the digest for binary
+//! - `redact`: null
+//! - `fixed:VALUE`: this value instead of every non-null one
+//! - `fake:KIND`: a made-up `name`, `first_name`, `last_name`, `email`,
+//!   `phone`, `city` or `company`, always the same for the same input and
+//!   salt, so joins on masked columns still line up
+//! - `partial:email` (`j*******@example.com`), `partial:phone` (all digits
+//!   but the last 4 hidden) or `partial` (all characters but the last 4)
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use arrow::{
+    array::{Array, ArrayRef, AsArray, BinaryArray, Float64Array, Int64Array, StringArray},
+    datatypes::{DataType, Int64Type},
+};
+use sha2::{Digest, Sha256};
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Blake", "Casey", "Dana", "Eli", "Frankie", "Gray", "Harper", "Indy", "Jordan", "Kai", "Logan", "Morgan",
+    "Noel", "Oakley", "Parker", "Quinn", "Riley", "Sage", "Taylor",
+];
+const LAST_NAMES: &[&str] = &[
+    "Abbott", "Bishop", "Carver", "Dalton", "Ellis", "Fletcher", "Garner", "Hayes", "Irving", "Jensen", "Keller",
+    "Lambert", "Mercer", "Norris", "Osborne", "Porter", "Ramsey", "Sutton", "Turner", "Walsh",
+];
+const CITIES: &[&str] = &[
+    "Ashford", "Brookfield", "Clearwater", "Dunmore", "Eastfield", "Fairview", "Glenwood", "Hillcrest", "Lakeside",
+    "Millbrook", "Northgate", "Oakridge", "Riverton", "Springdale", "Westbury",
+];
+const COMPANIES: &[&str] = &[
+    "Acme", "Bluepeak", "Cobalt", "Driftwood", "Evergreen", "Foxglove", "Granite", "Harbor", "Ironwood", "Juniper",
+];
+const COMPANY_SUFFIXES: &[&str] = &["Ltd", "Inc", "Group", "Partners", "Systems"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mask {
+    Hash,
+    Redact,
+    Fixed(String),
+    Fake(FakeKind),
+    Partial(PartialKind),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FakeKind {
+    Name,
+    FirstName,
+    LastName,
+    Email,
+    Phone,
+    City,
+    Company,
+}
+
+impl FakeKind {
+    const ALL: [Self; 7] =
+        [Self::Name, Self::FirstName, Self::LastName, Self::Email, Self::Phone, Self::City, Self::Company];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::FirstName => "first_name",
+            Self::LastName => "last_name",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::City => "city",
+            Self::Company => "company",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartialKind {
+    /// All characters but the last 4
+    Any,
+    Email,
+    Phone,
+}
+
+impl FromStr for Mask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (s.trim(), None),
+        };
+        Ok(match (name.to_ascii_lowercase().as_str(), arg) {
+            ("hash", None) => Self::Hash,
+            ("redact", None) => Self::Redact,
+            ("fixed", Some(value)) => Self::Fixed(value.to_string()),
+            ("fake", Some(kind)) => {
+                let kind = kind.trim().to_ascii_lowercase();
+                match FakeKind::ALL.into_iter().find(|k| k.name() == kind) {
+                    Some(kind) => Self::Fake(kind),
+                    None => {
+                        return Err(format!(
+                            "unknown fake kind '{}' (expected name, first_name, last_name, email, phone, city or \
+                             company)",
+                            kind
+                        ))
+                    }
+                }
+            }
+            ("partial", None) => Self::Partial(PartialKind::Any),
+            ("partial", Some(kind)) => Self::Partial(match kind.trim().to_ascii_lowercase().as_str() {
+                "email" => PartialKind::Email,
+                "phone" => PartialKind::Phone,
+                other => return Err(format!("unknown partial mask '{}' (expected email or phone)", other)),
+            }),
+            _ => {
+                return Err(format!(
+                    "invalid mask '{}' (expected hash, redact, fixed:VALUE, fake:KIND, partial, partial:email or \
+                     partial:phone)",
+                    s
+                ))
+            }
+        })
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hash => f.write_str("hash"),
+            Self::Redact => f.write_str("redact"),
+            Self::Fixed(value) => write!(f, "fixed:{}", value),
+            Self::Fake(kind) => write!(f, "fake:{}", kind.name()),
+            Self::Partial(PartialKind::Any) => f.write_str("partial"),
+            Self::Partial(PartialKind::Email) => f.write_str("partial:email"),
+            Self::Partial(PartialKind::Phone) => f.write_str("partial:phone"),
+        }
+    }
+}
+
+impl Mask {
+    /// Whether this mask can produce `data_type`; fixed values must parse as it.
+    pub(crate) fn check(&self, data_type: &DataType) -> Result<(), String> {
+        match (self, data_type) {
+            (Self::Redact, _) => Ok(()),
+            (Self::Hash, DataType::Int64 | DataType::Utf8 | DataType::Binary) => Ok(()),
+            (Self::Fixed(value), DataType::Int64) => {
+                value.parse::<i64>().map(|_| ()).map_err(|_| format!("fixed value '{}' is not an integer", value))
+            }
+            (Self::Fixed(value), DataType::Float64) => {
+                value.parse::<f64>().map(|_| ()).map_err(|_| format!("fixed value '{}' is not a number", value))
+            }
+            (Self::Fixed(_), DataType::Utf8 | DataType::Binary) => Ok(()),
+            (Self::Fake(_) | Self::Partial(_), DataType::Utf8) => Ok(()),
+            (mask, other) => Err(format!("the {} mask doesn't apply to {} columns", mask, other)),
+        }
+    }
+
+    /// `array` with every non-null value masked.
+    pub(crate) fn apply(&self, array: &ArrayRef, salt: &[u8]) -> Result<ArrayRef> {
+        let nulls = |i: usize| array.is_null(i);
+        Ok(match (self, array.data_type()) {
+            (Self::Redact, data_type) => arrow::array::new_null_array(data_type, array.len()),
+            (Self::Hash, DataType::Int64) => {
+                let masked: Int64Array = array
+                    .as_primitive::<Int64Type>()
+                    .iter()
+                    .map(|v| v.map(|v| seed(salt, &v.to_string()) as i64))
+                    .collect();
+                Arc::new(masked)
+            }
+            (Self::Hash, DataType::Binary) => {
+                let masked: BinaryArray =
+                    array.as_binary::<i32>().iter().map(|v| v.map(|v| digest(salt, v).to_vec())).collect();
+                Arc::new(masked)
+            }
+            (Self::Fixed(value), DataType::Int64) => {
+                let value: i64 = value.parse()?;
+                Arc::new((0..array.len()).map(|i| (!nulls(i)).then_some(value)).collect::<Int64Array>())
+            }
+            (Self::Fixed(value), DataType::Float64) => {
+                let value: f64 = value.parse()?;
+                Arc::new((0..array.len()).map(|i| (!nulls(i)).then_some(value)).collect::<Float64Array>())
+            }
+            (Self::Fixed(value), DataType::Binary) => Arc::new(
+                (0..array.len()).map(|i| (!nulls(i)).then_some(value.as_bytes())).collect::<BinaryArray>(),
+            ),
+            (mask, DataType::Utf8) => {
+                let masked: StringArray =
+                    array.as_string::<i32>().iter().map(|v| v.map(|v| mask.text(v, salt))).collect();
+                Arc::new(masked)
+            }
+            (mask, other) => anyhow::bail!("the {} mask doesn't apply to {} columns", mask, other),
+        })
+    }
+
+    fn text(&self, value: &str, salt: &[u8]) -> String {
+        match self {
+            Self::Hash => hex(&digest(salt, value.as_bytes())),
+            Self::Fixed(fixed) => fixed.clone(),
+            Self::Fake(kind) => fake(*kind, seed(salt, value)),
+            Self::Partial(PartialKind::Any) => hide(value, |_| true, 4),
+            Self::Partial(PartialKind::Phone) => hide(value, |c| c.is_ascii_digit(), 4),
+            Self::Partial(PartialKind::Email) => match value.split_once('@') {
+                Some((local, domain)) => {
+                    let mut chars = local.chars();
+                    let first = chars.next().map(String::from).unwrap_or_default();
+                    format!("{}{}@{}", first, "*".repeat(chars.count()), domain)
+                }
+                None => hide(value, |_| true, 4),
+            },
+            Self::Redact => unreachable!("redacted columns are all null"),
+        }
+    }
+}
+
+fn digest(salt: &[u8], value: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+fn seed(salt: &[u8], value: &str) -> u64 {
+    u64::from_be_bytes(digest(salt, value.as_bytes())[..8].try_into().unwrap())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `value` with the characters `hidden` selects replaced by `*`, except the
+/// last `keep` of them.
+fn hide(value: &str, hidden: impl Fn(char) -> bool, keep: usize) -> String {
+    let total = value.chars().filter(|&c| hidden(c)).count();
+    let mut seen = 0;
+    value
+        .chars()
+        .map(|c| {
+            if !hidden(c) {
+                return c;
+            }
+            seen += 1;
+            if seen + keep > total {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+fn pick(list: &[&'static str], seed: u64) -> &'static str {
+    list[(seed % list.len() as u64) as usize]
+}
+
+fn fake(kind: FakeKind, seed: u64) -> String {
+    let (first, last) = (pick(FIRST_NAMES, seed), pick(LAST_NAMES, seed >> 16));
+    match kind {
+        FakeKind::Name => format!("{} {}", first, last),
+        FakeKind::FirstName => first.to_string(),
+        FakeKind::LastName => last.to_string(),
+        FakeKind::Email => format!("{}.{}{}@example.com", first, last, (seed >> 32) % 1000).to_lowercase(),
+        // 555-01xx numbers are reserved for fiction
+        FakeKind::Phone => format!("555-01{:02}", (seed >> 32) % 100),
+        FakeKind::City => pick(CITIES, seed).to_string(),
+        FakeKind::Company => format!("{} {}", pick(COMPANIES, seed), pick(COMPANY_SUFFIXES, seed >> 16)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_masks() {
+        assert_eq!("hash".parse::<Mask>(), Ok(Mask::Hash));
+        assert_eq!(" REDACT ".parse::<Mask>(), Ok(Mask::Redact));
+        assert_eq!("fixed:n/a".parse::<Mask>(), Ok(Mask::Fixed("n/a".to_string())));
+        // Only the first colon separates the value
+        assert_eq!("fixed:a:b".parse::<Mask>(), Ok(Mask::Fixed("a:b".to_string())));
+        assert_eq!("fake:email".parse::<Mask>(), Ok(Mask::Fake(FakeKind::Email)));
+        assert_eq!("Fake:First_Name".parse::<Mask>(), Ok(Mask::Fake(FakeKind::FirstName)));
+        assert_eq!("partial".parse::<Mask>(), Ok(Mask::Partial(PartialKind::Any)));
+        assert_eq!("partial:phone".parse::<Mask>(), Ok(Mask::Partial(PartialKind::Phone)));
+    }
+
+    #[test]
+    fn rejects_unknown_masks() {
+        for bad in ["", "hash:x", "fixed", "fake", "fake:ssn", "partial:card", "scramble"] {
+            assert!(bad.parse::<Mask>().is_err(), "{} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        for mask in ["hash", "redact", "fixed:x", "fake:city", "partial", "partial:email"] {
+            assert_eq!(mask.parse::<Mask>().unwrap().to_string(), mask);
+        }
+    }
+}
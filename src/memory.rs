@@ -0,0 +1,279 @@
+//! In-flight memory accounting for the fetch → convert → write pipeline
+
+use std::sync::{Condvar, Mutex};
+
+use crate::source::{Row, Value};
+
+/// Caps the bytes held in pipeline channels across all workers.
+///
+/// Fetchers reserve the size of every chunk they hand off and block while the
+/// budget is exhausted; writers release it once the batch is on disk. A
+/// reservation is always admitted when nothing else is in flight, so a single
+/// chunk larger than the budget can't deadlock the pipeline.
+pub(crate) struct MemoryBudget {
+    limit: Option<usize>,
+    in_flight: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            in_flight: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Block until `bytes` fit in the budget, then account for them.
+    pub(crate) fn reserve(&self, bytes: usize) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(limit) = self.limit {
+            while *in_flight > 0 && *in_flight + bytes > limit {
+                in_flight = self.freed.wait(in_flight).unwrap();
+            }
+        }
+        *in_flight += bytes;
+    }
+
+    pub(crate) fn release(&self, bytes: usize) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        *in_flight = in_flight.saturating_sub(bytes);
+        self.freed.notify_all();
+    }
+
+    /// Swap a reservation for a different size without blocking, e.g. when
+    /// fetched rows become an Arrow batch.
+    pub(crate) fn transfer(&self, from: usize, to: usize) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        *in_flight = in_flight.saturating_sub(from) + to;
+        self.freed.notify_all();
+    }
+}
+
+const MIN_BATCH_ROWS: usize = 1_000;
+const MAX_BATCH_ROWS: usize = 2_000_000;
+/// Row data per batch kept under 32-bit Arrow offsets (2 GiB), with headroom
+/// for the estimate's error.
+const MAX_OFFSET_BYTES: f64 = (1u64 << 30) as f64;
+
+/// Sizes batches so each holds roughly `target_bytes` of fetched row data.
+///
+/// The first batch is a small probe; every completed batch then updates a
+/// running average of the row width and the next batch is sized from it, so
+/// narrow tables get huge batches and blob-heavy ones stay small, never
+/// holding more row data than 32-bit Arrow offsets can address. A
+/// [`BatchSizer::fixed`] one keeps the same row count throughout.
+#[derive(Clone, Debug)]
+pub(crate) struct BatchSizer {
+    target_bytes: usize,
+    avg_row_bytes: Option<f64>,
+    rows: usize,
+    fixed: bool,
+}
+
+impl BatchSizer {
+    pub(crate) fn new(target_bytes: usize, has_blob: bool) -> Self {
+        Self {
+            target_bytes,
+            avg_row_bytes: None,
+            rows: if has_blob { MIN_BATCH_ROWS } else { 10_000 },
+            fixed: false,
+        }
+    }
+
+    /// Always `rows` rows per batch, whatever their width.
+    pub(crate) fn fixed(rows: usize) -> Self {
+        Self {
+            target_bytes: 0,
+            avg_row_bytes: None,
+            rows: rows.max(1),
+            fixed: true,
+        }
+    }
+
+    /// Rows to put in the next batch.
+    pub(crate) fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Feed back the size of a completed batch.
+    pub(crate) fn observe(&mut self, rows: usize, bytes: usize) {
+        if rows == 0 || self.fixed {
+            return;
+        }
+        let width = bytes as f64 / rows as f64;
+        let avg = match self.avg_row_bytes {
+            Some(avg) => avg * 0.7 + width * 0.3,
+            None => width,
+        };
+        self.avg_row_bytes = Some(avg);
+        let rows = ((self.target_bytes as f64 / avg.max(1.0)) as usize).clamp(MIN_BATCH_ROWS, MAX_BATCH_ROWS);
+        // Very wide rows go below the minimum rather than overflow a column's offsets
+        self.rows = rows.min(((MAX_OFFSET_BYTES / avg.max(1.0)) as usize).max(1));
+    }
+}
+
+/// Approximate heap size of fetched rows.
+pub(crate) fn estimate_rows_bytes(rows: &[Row]) -> usize {
+    rows.iter()
+        .map(|row| {
+            row.values
+                .iter()
+                .map(|value| {
+                    let heap = match value {
+                        Value::Text(t) => t.len(),
+                        Value::Binary(b) => b.len(),
+                        _ => 0,
+                    };
+                    std::mem::size_of_val(value) + heap
+                })
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Parse sizes like `512M`, `4G`, `1.5GiB` or plain bytes.
+pub fn parse_byte_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("invalid size '{}': must be a non-negative number", s));
+    }
+    let multiplier: f64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" | "KIB" => 1024.0,
+        "M" | "MB" | "MIB" => 1024.0 * 1024.0,
+        "G" | "GB" | "GIB" => 1024.0 * 1024.0 * 1024.0,
+        "T" | "TB" | "TIB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => return Err(format!("unknown size unit '{}' in '{}'", other, s)),
+    };
+    Ok((value * multiplier) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::*;
+
+    fn in_flight(budget: &MemoryBudget) -> usize {
+        *budget.in_flight.lock().unwrap()
+    }
+
+    const MIB: usize = 1024 * 1024;
+
+    #[test]
+    fn sizes_batches_from_the_row_width() {
+        assert_eq!(BatchSizer::new(64 * MIB, false).rows(), 10_000);
+        assert_eq!(BatchSizer::new(64 * MIB, true).rows(), MIN_BATCH_ROWS);
+
+        let mut sizer = BatchSizer::new(64 * MIB, false);
+        sizer.observe(10_000, 10_000 * 128);
+        assert_eq!(sizer.rows(), 64 * MIB / 128);
+        // Later batches move the average by 30%
+        sizer.observe(10_000, 10_000 * 256);
+        assert_eq!(sizer.rows(), ((64 * MIB) as f64 / (128.0 * 0.7 + 256.0 * 0.3)) as usize);
+        // An empty batch says nothing about the width
+        let rows = sizer.rows();
+        sizer.observe(0, 0);
+        assert_eq!(sizer.rows(), rows);
+    }
+
+    #[test]
+    fn clamps_batch_rows() {
+        let mut narrow = BatchSizer::new(64 * MIB, false);
+        narrow.observe(1_000, 1_000);
+        assert_eq!(narrow.rows(), MAX_BATCH_ROWS);
+
+        let mut wide = BatchSizer::new(64 * MIB, true);
+        wide.observe(10, 10 * 512 * 1024);
+        assert_eq!(wide.rows(), MIN_BATCH_ROWS);
+
+        // Rows so wide that the minimum would overflow 32-bit offsets
+        let mut huge = BatchSizer::new(64 * MIB, true);
+        huge.observe(1, 4 * MIB);
+        assert_eq!(huge.rows(), 256);
+        huge.observe(1, 4096 * MIB);
+        assert_eq!(huge.rows(), 1);
+    }
+
+    #[test]
+    fn fixed_sizer_ignores_observations() {
+        let mut sizer = BatchSizer::fixed(5_000);
+        sizer.observe(5_000, 5_000 * 1024 * 1024);
+        assert_eq!(sizer.rows(), 5_000);
+        assert_eq!(BatchSizer::fixed(0).rows(), 1);
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!(parse_byte_size("1000000"), Ok(1_000_000));
+        assert_eq!(parse_byte_size("512B"), Ok(512));
+        assert_eq!(parse_byte_size("4k"), Ok(4096));
+        assert_eq!(parse_byte_size("256M"), Ok(256 * MIB));
+        assert_eq!(parse_byte_size(" 1.5 GiB "), Ok(1536 * MIB));
+        assert_eq!(parse_byte_size("2TB"), Ok(2 << 40));
+        assert_eq!(parse_byte_size("0"), Ok(0));
+        assert!(parse_byte_size("").is_err());
+        assert!(parse_byte_size("M").is_err());
+        assert!(parse_byte_size("12 parsecs").is_err());
+        assert!(parse_byte_size("-5M").is_err());
+        assert!(parse_byte_size("-1").is_err());
+        assert!(parse_byte_size("NaN").is_err());
+        // Too many digits for an f64 parses as infinity
+        assert!(parse_byte_size(&"9".repeat(400)).is_err());
+    }
+
+    #[test]
+    fn admits_an_oversize_reservation_when_idle() {
+        let budget = MemoryBudget::new(Some(100));
+        budget.reserve(500);
+        assert_eq!(in_flight(&budget), 500);
+        budget.release(500);
+        assert_eq!(in_flight(&budget), 0);
+    }
+
+    #[test]
+    fn reserve_waits_for_release() {
+        let budget = MemoryBudget::new(Some(100));
+        budget.reserve(60);
+        let (done_tx, done_rx) = mpsc::channel();
+        thread::scope(|scope| {
+            let budget = &budget;
+            scope.spawn(move || {
+                budget.reserve(60);
+                done_tx.send(()).unwrap();
+            });
+            assert!(done_rx.recv_timeout(Duration::from_millis(100)).is_err(), "reserve didn't wait");
+            budget.release(60);
+            done_rx.recv_timeout(Duration::from_secs(5)).expect("reserve woke up after release");
+        });
+        assert_eq!(in_flight(&budget), 60);
+    }
+
+    #[test]
+    fn transfer_swaps_the_reservation() {
+        let budget = MemoryBudget::new(Some(100));
+        budget.reserve(40);
+        budget.reserve(30);
+        // Rows become a larger batch without blocking, even past the limit
+        budget.transfer(40, 90);
+        assert_eq!(in_flight(&budget), 120);
+        budget.transfer(30, 10);
+        assert_eq!(in_flight(&budget), 100);
+        budget.release(90);
+        budget.release(10);
+        assert_eq!(in_flight(&budget), 0);
+        // Without a limit nothing ever waits
+        let unlimited = MemoryBudget::new(None);
+        unlimited.reserve(usize::MAX / 2);
+        unlimited.reserve(1);
+        assert_eq!(in_flight(&unlimited), usize::MAX / 2 + 1);
+    }
+}
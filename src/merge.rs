@@ -0,0 +1,260 @@
+//! Merging incremental extractions into a current-state dataset by primary key
+//!
+//! With `merge_target` set, each table is extracted as usual and its output
+//! then merged into `<merge_target>/<table>.parquet`: the target's files (the
+//! base file and any appended ones) are rewritten into one file holding the
+//! target rows whose key the new output lacks, followed by the new rows, so
+//! the target keeps one row per key. The new output's keys are held in memory.
+//! Rows deleted at the source stay in the target, since an incremental
+//! extraction can't tell them from rows its filter left out, except for soft
+//! deletes written to a tombstone file, whose keys are removed.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{new_null_array, ArrayRef, BooleanArray},
+    compute::filter_record_batch,
+    datatypes::SchemaRef,
+    record_batch::RecordBatch,
+    row::{RowConverter, SortField},
+};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::error::ExtractError;
+use crate::extractor::{output_stem, Extractor};
+use crate::manifest::{schema_changes, schema_fields, Manifest};
+
+/// How an extraction's rows went into the merge target.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MergeStats {
+    /// The target's base file, now holding every row
+    pub target: PathBuf,
+    /// New rows whose key the target lacked
+    pub inserted: usize,
+    /// New rows that replaced a target row with the same key
+    pub updated: usize,
+    /// Target rows removed because the tombstone file listed their key
+    pub deleted: usize,
+    /// Target rows kept as they were
+    pub kept: usize,
+    /// Rows in the target after the merge
+    pub rows: usize,
+}
+
+impl Extractor {
+    /// Merge `output`, just written for `table_name` (`None` when the table
+    /// was empty), into the merge target by the `key` columns, removing the
+    /// keys listed in the `tombstones` file.
+    pub(crate) fn merge_into_target(
+        &self,
+        table_name: &str,
+        key: &[String],
+        output: Option<&Path>,
+        tombstones: Option<&Path>,
+    ) -> Result<MergeStats> {
+        let target_dir = self.config.merge_target.as_deref().expect("merge target set");
+        let target = target_dir.join(format!("{}.parquet", output_stem(table_name)));
+        info!("  Merging into {}...", target.display());
+        let merged = match (target.exists(), output) {
+            (true, _) => self.merge_existing(table_name, key, output, tombstones, &target),
+            (false, Some(output)) => self.merge_first(table_name, output, &target),
+            (false, None) => Ok(MergeStats { target: target.clone(), ..MergeStats::default() }),
+        };
+        let stats = merged.map_err(|e| ExtractError::tag(ExtractError::Merge, e))?;
+        info!(
+            "  ✓ Merged: {} inserted, {} updated, {} deleted, {} kept, {} rows in {}",
+            stats.inserted,
+            stats.updated,
+            stats.deleted,
+            stats.kept,
+            stats.rows,
+            target.display()
+        );
+        Ok(stats)
+    }
+
+    /// No target yet: it starts as a copy of the output.
+    fn merge_first(&self, table_name: &str, output: &Path, target: &Path) -> Result<MergeStats> {
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        fs::copy(output, target)
+            .with_context(|| format!("Failed to copy {} to {}", output.display(), target.display()))?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(target)?)?;
+        let rows = reader.metadata().file_metadata().num_rows() as usize;
+        let mut manifest = self.manifest(table_name, target);
+        manifest.rows = rows;
+        manifest.partitions = 1;
+        manifest.schema = schema_fields(reader.schema());
+        manifest.write()?;
+        Ok(MergeStats { target: target.to_path_buf(), inserted: rows, rows, ..MergeStats::default() })
+    }
+
+    fn merge_existing(
+        &self,
+        table_name: &str,
+        key: &[String],
+        output: Option<&Path>,
+        tombstones: Option<&Path>,
+        target: &Path,
+    ) -> Result<MergeStats> {
+        // Without an output, the target's rows keep their columns
+        let schema = Arc::clone(open(output.unwrap_or(target))?.schema());
+        let key_columns = key_columns(&schema, key, output.unwrap_or(target))?;
+        let key_fields = key_columns.iter().map(|&c| SortField::new(schema.field(c).data_type().clone())).collect();
+        let mut converter = RowConverter::new(key_fields)?;
+        let (new_keys, new_rows) = match output {
+            Some(path) => read_keys(path, key, &mut converter)?,
+            None => (HashSet::new(), 0),
+        };
+        let (deleted_keys, _) = match tombstones {
+            Some(path) => read_keys(path, key, &mut converter)?,
+            None => (HashSet::new(), 0),
+        };
+
+        let manifest_path = Manifest::path_for(target);
+        let appended = match manifest_path.exists() {
+            true => Manifest::read(&manifest_path)?.appended_files,
+            false => Vec::new(),
+        };
+        let staging = target.with_extension("merging.parquet");
+        let file = File::create(&staging).with_context(|| format!("Failed to create {}", staging.display()))?;
+        let mut writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(self.output_writer_props(&schema)))?;
+        let mut replaced = HashSet::new();
+        let (mut kept, mut deleted) = (0, 0);
+        for path in std::iter::once(target).chain(appended.iter().map(PathBuf::as_path)) {
+            let reader = open(path)?;
+            let previous = Arc::clone(reader.schema());
+            self.check_target_schema(table_name, path, &previous, &schema)?;
+            let previous_keys = key_columns(&previous, key, path)?;
+            for batch in reader.build()? {
+                let batch = batch.with_context(|| format!("Failed to read {}", path.display()))?;
+                let rows = converter.convert_columns(&pick(&batch, &previous_keys))?;
+                let keep: BooleanArray = rows
+                    .iter()
+                    .map(|row| {
+                        let row = row.as_ref();
+                        if new_keys.contains(row) {
+                            replaced.insert(row.to_vec());
+                            Some(false)
+                        } else if deleted_keys.contains(row) {
+                            deleted += 1;
+                            Some(false)
+                        } else {
+                            Some(true)
+                        }
+                    })
+                    .collect();
+                let batch = filter_record_batch(&conform(&batch, &schema)?, &keep)?;
+                kept += batch.num_rows();
+                writer.write(&batch)?;
+            }
+        }
+        if let Some(output) = output {
+            for batch in open(output)?.build()? {
+                writer.write(&batch?)?;
+            }
+        }
+        writer.close()?;
+
+        fs::rename(&staging, target)
+            .with_context(|| format!("Failed to replace {} with {}", target.display(), staging.display()))?;
+        // The appended files' rows now live in the base file
+        for path in &appended {
+            for file in [path.clone(), Manifest::path_for(path)] {
+                if let Err(e) = fs::remove_file(&file) {
+                    warn!("  ⚠ Couldn't remove {}: {}", file.display(), e);
+                }
+            }
+        }
+        let mut manifest = self.manifest(table_name, target);
+        manifest.rows = kept + new_rows;
+        manifest.partitions = 1;
+        manifest.schema = schema_fields(&schema);
+        manifest.write()?;
+        Ok(MergeStats {
+            target: target.to_path_buf(),
+            inserted: new_rows - replaced.len(),
+            updated: replaced.len(),
+            deleted,
+            kept,
+            rows: kept + new_rows,
+        })
+    }
+
+    /// Fail if the target file at `path` has other columns than the new
+    /// output, unless `allow_schema_evolution` is set; its rows then get
+    /// nulls for columns it lacks.
+    fn check_target_schema(&self, table: &str, path: &Path, previous: &SchemaRef, schema: &SchemaRef) -> Result<()> {
+        let changes = schema_changes(&schema_fields(previous), &schema_fields(schema));
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if self.config.allow_schema_evolution {
+            warn!("  ⚠ Schema of {} changed since {}: {}", table, path.display(), changes.join("; "));
+            return Ok(());
+        }
+        let e = anyhow::anyhow!(
+            "Schema of {} changed since {} ({}); pass --allow-schema-evolution to merge anyway",
+            table,
+            path.display(),
+            changes.join("; ")
+        );
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
+}
+
+fn open(path: &Path) -> Result<ParquetRecordBatchReaderBuilder<File>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    ParquetRecordBatchReaderBuilder::try_new(file).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn key_columns(schema: &SchemaRef, key: &[String], path: &Path) -> Result<Vec<usize>> {
+    let index = |column: &String| {
+        schema.index_of(column).with_context(|| format!("{} lacks key column {}", path.display(), column))
+    };
+    key.iter().map(index).collect()
+}
+
+/// The encoded keys of the rows of `path`, and its row count.
+fn read_keys(path: &Path, key: &[String], converter: &mut RowConverter) -> Result<(HashSet<Vec<u8>>, usize)> {
+    let reader = open(path)?;
+    let columns = key_columns(reader.schema(), key, path)?;
+    let (mut keys, mut rows) = (HashSet::new(), 0);
+    for batch in reader.build()? {
+        let batch = batch.with_context(|| format!("Failed to read {}", path.display()))?;
+        keys.extend(converter.convert_columns(&pick(&batch, &columns))?.iter().map(|row| row.as_ref().to_vec()));
+        rows += batch.num_rows();
+    }
+    Ok((keys, rows))
+}
+
+fn pick(batch: &RecordBatch, columns: &[usize]) -> Vec<ArrayRef> {
+    columns.iter().map(|&c| Arc::clone(batch.column(c))).collect()
+}
+
+/// `batch` in `schema`'s columns, null where it lacks one or has it with
+/// another type.
+fn conform(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch> {
+    if batch.schema() == *schema {
+        return Ok(batch.clone());
+    }
+    let previous = batch.schema();
+    let columns: Vec<ArrayRef> = schema
+        .fields()
+        .iter()
+        .map(|field| match previous.index_of(field.name()) {
+            Ok(p) if previous.field(p).data_type() == field.data_type() => Arc::clone(batch.column(p)),
+            _ => new_null_array(field.data_type(), batch.num_rows()),
+        })
+        .collect();
+    Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+}
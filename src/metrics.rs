@@ -0,0 +1,254 @@
+//! Prometheus metrics (`metrics` feature)
+//!
+//! A [`Metrics`] registry counts what an extractor reports through its
+//! [`ProgressEvent`]s and reads the connection pool when rendered. It can be
+//! scraped over HTTP with [`Metrics::serve`] or pushed to a Pushgateway with
+//! [`Metrics::push`]; both speak plain HTTP/1.1 over std sockets, so the
+//! feature pulls in no dependencies.
+
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+use crate::events::ProgressEvent;
+use crate::extractor::{ConnectionPool, Extractor, PoolStats};
+
+/// Counters and gauges of one process's extractions.
+pub struct Metrics {
+    started: Instant,
+    rows: AtomicU64,
+    output_bytes: AtomicU64,
+    active_partitions: AtomicI64,
+    failed_partitions: AtomicU64,
+    tables: AtomicU64,
+    pool: OnceLock<Arc<ConnectionPool>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            rows: AtomicU64::new(0),
+            output_bytes: AtomicU64::new(0),
+            active_partitions: AtomicI64::new(0),
+            failed_partitions: AtomicU64::new(0),
+            tables: AtomicU64::new(0),
+            pool: OnceLock::new(),
+        }
+    }
+}
+
+/// The values of a [`Metrics`] registry at one point in time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsSnapshot {
+    /// Rows converted and handed to writers
+    pub rows_extracted: u64,
+    /// Bytes of finished output files
+    pub output_bytes: u64,
+    pub active_partitions: u64,
+    /// Partitions that failed after all retries
+    pub failed_partitions: u64,
+    pub tables_extracted: u64,
+    /// Since the registry was created
+    pub rows_per_second: f64,
+    /// Connections handed out; 0 until an extractor is attached
+    pub pool_in_use: usize,
+    pub pool_size: usize,
+    pub pool: PoolStats,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// The process-wide registry the CLI reports to.
+    pub fn global() -> Arc<Self> {
+        static GLOBAL: OnceLock<Arc<Metrics>> = OnceLock::new();
+        Arc::clone(GLOBAL.get_or_init(Metrics::new))
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let rows_extracted = self.rows.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let (pool_in_use, pool_size, pool) = match self.pool.get() {
+            Some(pool) => {
+                let (in_use, size) = pool.usage();
+                (in_use, size, pool.stats())
+            }
+            None => (0, 0, PoolStats::default()),
+        };
+        MetricsSnapshot {
+            rows_extracted,
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+            active_partitions: self.active_partitions.load(Ordering::Relaxed).max(0) as u64,
+            failed_partitions: self.failed_partitions.load(Ordering::Relaxed),
+            tables_extracted: self.tables.load(Ordering::Relaxed),
+            rows_per_second: if elapsed > 0.0 { rows_extracted as f64 / elapsed } else { 0.0 },
+            pool_in_use,
+            pool_size,
+            pool,
+        }
+    }
+
+    pub(crate) fn record(&self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::PartitionStarted { .. } => {
+                self.active_partitions.fetch_add(1, Ordering::Relaxed);
+            }
+            ProgressEvent::BatchWritten { rows, .. } => {
+                self.rows.fetch_add(*rows as u64, Ordering::Relaxed);
+            }
+            ProgressEvent::PartitionFinished { error, .. } => {
+                self.active_partitions.fetch_sub(1, Ordering::Relaxed);
+                if error.is_some() {
+                    self.failed_partitions.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            ProgressEvent::Done(stats) => {
+                let bytes = (stats.file_size_mb * 1024.0 * 1024.0) as u64;
+                self.output_bytes.fetch_add(bytes, Ordering::Relaxed);
+                self.tables.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /// The Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let s = self.snapshot();
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+        };
+        metric("peregrine_rows_extracted_total", "counter", "Rows handed to writers", s.rows_extracted as f64);
+        metric("peregrine_output_bytes_total", "counter", "Bytes of finished output files", s.output_bytes as f64);
+        metric("peregrine_active_partitions", "gauge", "Partitions being extracted", s.active_partitions as f64);
+        metric("peregrine_failed_partitions_total", "counter", "Partitions that failed", s.failed_partitions as f64);
+        metric("peregrine_tables_extracted_total", "counter", "Tables finished", s.tables_extracted as f64);
+        metric("peregrine_rows_per_second", "gauge", "Rows per second since the process started", s.rows_per_second);
+        if self.pool.get().is_some() {
+            metric("peregrine_pool_connections_in_use", "gauge", "Pooled connections handed out", s.pool_in_use as f64);
+            metric("peregrine_pool_size", "gauge", "Configured connection pool size", s.pool_size as f64);
+            metric("peregrine_pool_hits_total", "counter", "Idle connections reused", s.pool.hits as f64);
+            metric("peregrine_pool_misses_total", "counter", "New connections opened", s.pool.misses as f64);
+            metric("peregrine_pool_reconnects_total", "counter", "Dead connections replaced", s.pool.reconnects as f64);
+        }
+        out
+    }
+
+    /// Answer `GET /metrics` on `addr` from a background thread. Returns the
+    /// bound address, useful with port 0.
+    pub fn serve(self: &Arc<Self>, addr: impl ToSocketAddrs) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).context("Failed to bind the metrics endpoint")?;
+        let local = listener.local_addr()?;
+        let metrics = Arc::clone(self);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = metrics.respond(stream) {
+                    debug!("Metrics request failed: {:#}", e);
+                }
+            }
+        });
+        Ok(local)
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Drain the headers; the request has no body
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+        let path = request_line.split_whitespace().nth(1).unwrap_or("");
+        let (status, body) = match path {
+            "/metrics" | "/" => ("200 OK", self.render()),
+            _ => ("404 Not Found", String::new()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        Ok(())
+    }
+
+    /// Replace the metrics of `job` on the Pushgateway at `gateway`
+    /// (`http://host:9091`).
+    pub fn push(&self, gateway: &str, job: &str) -> Result<()> {
+        let rest = gateway
+            .strip_prefix("http://")
+            .with_context(|| format!("Pushgateway URL must start with http://: {}", gateway))?;
+        let (host, prefix) = match rest.find('/') {
+            Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        let body = self.render();
+        let mut stream =
+            TcpStream::connect(host).with_context(|| format!("Failed to connect to the Pushgateway at {}", host))?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        write!(
+            stream,
+            "PUT {}/metrics/job/{} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            prefix,
+            job,
+            host,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status = response.lines().next().unwrap_or("");
+        if !status.split_whitespace().nth(1).map_or(false, |code| code.starts_with('2')) {
+            anyhow::bail!("Pushgateway {} answered '{}'", gateway, status);
+        }
+        Ok(())
+    }
+
+    /// [`Metrics::push`] every `interval` from a background thread, for as
+    /// long as the process runs. Failed pushes are logged and retried at the
+    /// next interval.
+    pub fn push_every(self: &Arc<Self>, gateway: String, job: String, interval: Duration) {
+        let metrics = Arc::clone(self);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            if let Err(e) = metrics.push(&gateway, &job) {
+                warn!("Pushing metrics failed: {:#}", e);
+            }
+        });
+    }
+}
+
+impl Extractor {
+    /// Count this extractor's progress and connection pool in `metrics`.
+    /// Progress callbacks still get every event.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        let _ = metrics.pool.set(Arc::clone(&self.pool));
+        let observer = self.observer.take();
+        self.observer = Some(Arc::new(move |event: ProgressEvent| {
+            metrics.record(&event);
+            if let Some(ref observer) = observer {
+                observer(event);
+            }
+        }));
+        self
+    }
+}
@@ -0,0 +1,153 @@
+//! Running a list of tables in order as one job, with a report at the end
+
+use std::time::Instant;
+
+use tracing::{info, warn};
+
+use crate::config::TableOverrides;
+use crate::error::ExtractError;
+use crate::extractor::{ExtractionStats, Extractor, PoolStats};
+use crate::partition::PartitionSpec;
+
+/// One table of an [`Extractor::extract_many`] plan.
+#[derive(Clone, Debug)]
+pub struct TableSpec {
+    pub name: String,
+    /// Replaces the configured overrides for this table, if set
+    pub overrides: Option<TableOverrides>,
+    /// Partition plan to use instead of planning one
+    pub plan: Option<Vec<PartitionSpec>>,
+}
+
+impl TableSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), overrides: None, plan: None }
+    }
+
+    pub fn with_overrides(mut self, overrides: TableOverrides) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    pub fn with_plan(mut self, plan: Vec<PartitionSpec>) -> Self {
+        self.plan = Some(plan);
+        self
+    }
+}
+
+/// What [`Extractor::extract_many`] does after a table fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Carry on with the remaining tables
+    #[default]
+    Continue,
+    /// Skip the remaining tables
+    Stop,
+}
+
+/// How one table of an [`ExtractionReport`] went.
+#[derive(Debug)]
+pub enum TableOutcome {
+    Extracted(ExtractionStats),
+    Failed(ExtractError),
+    /// Not attempted because an earlier table failed under [`FailurePolicy::Stop`]
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct TableReport {
+    pub table: String,
+    pub outcome: TableOutcome,
+}
+
+/// Result of [`Extractor::extract_many`]: one entry per table, in plan order.
+#[derive(Debug)]
+pub struct ExtractionReport {
+    pub tables: Vec<TableReport>,
+    pub duration_secs: f64,
+    /// Connection pool counters at the end of the run
+    pub pool: PoolStats,
+}
+
+impl ExtractionReport {
+    /// Every table was extracted.
+    pub fn is_success(&self) -> bool {
+        self.tables.iter().all(|t| matches!(t.outcome, TableOutcome::Extracted(_)))
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.extracted().map(|(_, stats)| stats.rows_extracted).sum()
+    }
+
+    pub fn extracted(&self) -> impl Iterator<Item = (&str, &ExtractionStats)> {
+        self.tables.iter().filter_map(|t| match t.outcome {
+            TableOutcome::Extracted(ref stats) => Some((t.table.as_str(), stats)),
+            _ => None,
+        })
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &ExtractError)> {
+        self.tables.iter().filter_map(|t| match t.outcome {
+            TableOutcome::Failed(ref e) => Some((t.table.as_str(), e)),
+            _ => None,
+        })
+    }
+
+    /// Tables not attempted after a failure under [`FailurePolicy::Stop`].
+    pub fn skipped(&self) -> impl Iterator<Item = &str> {
+        self.tables.iter().filter(|t| matches!(t.outcome, TableOutcome::Skipped)).map(|t| t.table.as_str())
+    }
+}
+
+impl Extractor {
+    /// Extract the tables of `plan` one after another, in plan order, each
+    /// with the full configured parallelism and all sharing this extractor's
+    /// connection pool and memory budget. Unlike [`Extractor::extract_tables`]
+    /// nothing runs concurrently, so the order is the caller's to choose.
+    ///
+    /// A failed table is recorded and, under [`FailurePolicy::Stop`], the
+    /// remaining ones are skipped. Once cancelled, tables not yet started
+    /// fail as [`ExtractError::Cancelled`].
+    pub fn extract_many(&self, plan: &[TableSpec], on_failure: FailurePolicy) -> ExtractionReport {
+        let start = Instant::now();
+        let mut tables = Vec::with_capacity(plan.len());
+        let mut stopped = false;
+        for spec in plan {
+            let outcome = if stopped {
+                TableOutcome::Skipped
+            } else if self.cancel.is_cancelled() {
+                let e = anyhow::anyhow!("Extraction cancelled before {} started", spec.name);
+                TableOutcome::Failed(ExtractError::Cancelled(e))
+            } else {
+                info!("→ Table {} ({} of {})", spec.name, tables.len() + 1, plan.len());
+                match self.extract_spec(spec) {
+                    Ok(stats) => TableOutcome::Extracted(stats),
+                    Err(e) => {
+                        warn!("{}: FAILED - {:#}", spec.name, e);
+                        stopped = on_failure == FailurePolicy::Stop;
+                        TableOutcome::Failed(e)
+                    }
+                }
+            };
+            tables.push(TableReport { table: spec.name.clone(), outcome });
+        }
+        ExtractionReport { tables, duration_secs: start.elapsed().as_secs_f64(), pool: self.pool_stats() }
+    }
+
+    fn extract_spec(&self, spec: &TableSpec) -> Result<ExtractionStats, ExtractError> {
+        let overridden;
+        let this = match spec.overrides {
+            Some(ref overrides) => {
+                let mut this = self.clone();
+                this.config.tables.insert(spec.name.to_uppercase(), overrides.clone());
+                overridden = this;
+                &overridden
+            }
+            None => self,
+        };
+        match spec.plan {
+            Some(ref plan) => this.extract_table_with_plan(&spec.name, plan),
+            None => this.extract_table(&spec.name),
+        }
+    }
+}
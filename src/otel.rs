@@ -0,0 +1,134 @@
+//! OpenTelemetry export (`otel` feature)
+//!
+//! [`Telemetry`] sends the extractor's tracing spans (`extract_table`,
+//! `partition`, `fetch`, `write`, `merge`) and the counters of a [`Metrics`]
+//! registry to an OTLP/HTTP collector, such as the one in front of Tempo and
+//! Mimir. A `TRACEPARENT` in the environment, as set by an Airflow task with
+//! OpenTelemetry enabled, makes the run a child of the caller's trace.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use opentelemetry::{metrics::MeterProvider as _, propagation::TextMapPropagator, trace::TracerProvider as _};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
+    trace::{SdkTracerProvider, Tracer},
+    Resource,
+};
+use tracing::{info_span, warn, Span};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+
+use crate::metrics::{Metrics, MetricsSnapshot};
+
+const SERVICE_NAME: &str = "firebird_peregrine_falcon";
+
+/// How often counters are exported; the last values are flushed on drop.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Span and metric export to one collector. Dropping it flushes both.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Export to the collector at `endpoint` (`http://otel-collector:4318`),
+    /// reading counters from `metrics`.
+    pub fn init(endpoint: &str, metrics: Arc<Metrics>) -> Result<Self> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let resource = Resource::builder().with_service_name(SERVICE_NAME).build();
+
+        let spans = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint))
+            .build()
+            .context("Failed to create the OTLP span exporter")?;
+        let tracer_provider =
+            SdkTracerProvider::builder().with_batch_exporter(spans).with_resource(resource.clone()).build();
+
+        let exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/metrics", endpoint))
+            .build()
+            .context("Failed to create the OTLP metric exporter")?;
+        let reader = PeriodicReader::builder(exporter).with_interval(EXPORT_INTERVAL).build();
+        let meter_provider = SdkMeterProvider::builder().with_reader(reader).with_resource(resource).build();
+        register_instruments(&meter_provider, metrics);
+
+        Ok(Self { tracer_provider, meter_provider })
+    }
+
+    /// The layer that turns tracing spans into exported ones.
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer_provider.tracer(SERVICE_NAME))
+    }
+
+    /// A span for the whole run, parented to `TRACEPARENT` if it is set.
+    pub fn run_span(&self) -> Span {
+        let span = info_span!("run");
+        if let Ok(traceparent) = std::env::var("TRACEPARENT") {
+            let mut carrier = HashMap::from([("traceparent".to_string(), traceparent)]);
+            if let Ok(tracestate) = std::env::var("TRACESTATE") {
+                carrier.insert("tracestate".to_string(), tracestate);
+            }
+            span.set_parent(TraceContextPropagator::new().extract(&carrier));
+        }
+        span
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            warn!("Flushing OTLP spans failed: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            warn!("Flushing OTLP metrics failed: {}", e);
+        }
+    }
+}
+
+/// Observable instruments reading a snapshot of `metrics` at each export.
+fn register_instruments(provider: &SdkMeterProvider, metrics: Arc<Metrics>) {
+    let meter = provider.meter(SERVICE_NAME);
+    let counters: [(&str, &str, fn(&MetricsSnapshot) -> u64); 7] = [
+        ("peregrine.rows_extracted", "Rows handed to writers", |s| s.rows_extracted),
+        ("peregrine.output_bytes", "Bytes of finished output files", |s| s.output_bytes),
+        ("peregrine.failed_partitions", "Partitions that failed", |s| s.failed_partitions),
+        ("peregrine.tables_extracted", "Tables finished", |s| s.tables_extracted),
+        ("peregrine.pool.hits", "Idle connections reused", |s| s.pool.hits as u64),
+        ("peregrine.pool.misses", "New connections opened", |s| s.pool.misses as u64),
+        ("peregrine.pool.reconnects", "Dead connections replaced", |s| s.pool.reconnects as u64),
+    ];
+    for (name, description, value) in counters {
+        let metrics = Arc::clone(&metrics);
+        meter
+            .u64_observable_counter(name)
+            .with_description(description)
+            .with_callback(move |observer| observer.observe(value(&metrics.snapshot()), &[]))
+            .build();
+    }
+    let gauges: [(&str, &str, fn(&MetricsSnapshot) -> u64); 3] = [
+        ("peregrine.active_partitions", "Partitions being extracted", |s| s.active_partitions),
+        ("peregrine.pool.in_use", "Pooled connections handed out", |s| s.pool_in_use as u64),
+        ("peregrine.pool.size", "Configured connection pool size", |s| s.pool_size as u64),
+    ];
+    for (name, description, value) in gauges {
+        let metrics = Arc::clone(&metrics);
+        meter
+            .u64_observable_gauge(name)
+            .with_description(description)
+            .with_callback(move |observer| observer.observe(value(&metrics.snapshot()), &[]))
+            .build();
+    }
+    meter
+        .f64_observable_gauge("peregrine.rows_per_second")
+        .with_description("Rows per second since the process started")
+        .with_callback(move |observer| observer.observe(metrics.snapshot().rows_per_second, &[]))
+        .build();
+}
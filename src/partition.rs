@@ -0,0 +1,327 @@
+//! Partition plans: how a table is split into independently extracted slices
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// One unit of parallel work over the source table.
+///
+/// A spec narrows the table either with a WHERE predicate or with a
+/// `ROWS first TO last` window; an empty spec selects the whole table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionSpec {
+    /// WHERE predicate selecting this partition's rows
+    pub predicate: Option<String>,
+    /// `ROWS first TO last` window (1-based, inclusive); `last` is `i64::MAX`
+    /// for the open-ended last window of a plan
+    pub rows: Option<(i64, i64)>,
+    /// Rows the planner expects, from sampled key boundaries or counts;
+    /// larger partitions are started first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_rows: Option<i64>,
+    /// What the partition selects; plain unless it is the NULL key partition
+    #[serde(default, skip_serializing_if = "PartitionKind::is_plain")]
+    pub kind: PartitionKind,
+}
+
+/// What a [`PartitionSpec`] selects, beyond its predicate and window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionKind {
+    /// Whatever the predicate and window select
+    #[default]
+    Plain,
+    /// The rows whose partition column is NULL, which no range on it matches
+    NullKey,
+}
+
+impl PartitionKind {
+    fn is_plain(&self) -> bool {
+        *self == Self::Plain
+    }
+}
+
+impl PartitionSpec {
+    /// The whole table in a single partition.
+    pub fn full() -> Self {
+        Self { predicate: None, rows: None, estimated_rows: None, kind: PartitionKind::Plain }
+    }
+
+    /// Rows matching an arbitrary SQL predicate.
+    pub fn predicate(predicate: impl Into<String>) -> Self {
+        Self { predicate: Some(predicate.into()), rows: None, estimated_rows: None, kind: PartitionKind::Plain }
+    }
+
+    /// Rows `first..=last` of an unordered scan.
+    pub fn rows(first: i64, last: i64) -> Self {
+        Self { predicate: None, rows: Some((first, last)), estimated_rows: None, kind: PartitionKind::Plain }
+    }
+
+    /// This partition, expected to hold about `rows` rows.
+    pub fn with_estimate(mut self, rows: i64) -> Self {
+        self.estimated_rows = Some(rows);
+        self
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.predicate.is_none() && self.rows.is_none()
+    }
+
+    /// Rows whose `column` is NULL, which no range on it matches.
+    pub(crate) fn null_key(column: &str) -> Self {
+        Self { kind: PartitionKind::NullKey, ..Self::predicate(format!("{} IS NULL", quote_ident(column))) }
+    }
+
+    /// Whether this is a [`Self::null_key`] partition.
+    pub fn is_null_key(&self) -> bool {
+        self.kind == PartitionKind::NullKey
+    }
+
+    /// `[start, end)`, or `[start, end]` for the last range of a plan so
+    /// adjacent partitions never share a boundary row.
+    pub(crate) fn range(column: &str, start: i64, end: i64, last: bool) -> Self {
+        let upper = if last { "<=" } else { "<" };
+        let column = quote_ident(column);
+        Self::predicate(format!("{} >= {} AND {} {} {}", column, start, column, upper, end))
+    }
+
+    /// Rows whose key hashes to `bucket` out of `buckets`. Text keys go through
+    /// Firebird's `HASH()`; `ABS` keeps negative keys in `0..buckets`.
+    pub(crate) fn hash(column: &str, text_key: bool, buckets: usize, bucket: usize) -> Self {
+        let key = if text_key { format!("HASH({})", quote_ident(column)) } else { quote_ident(column) };
+        Self::predicate(format!("ABS(MOD({}, {})) = {}", key, buckets, bucket))
+    }
+
+    /// `[lower, upper)` over a string column; `None` leaves that side open.
+    pub(crate) fn text_range(column: &str, lower: Option<&str>, upper: Option<&str>) -> Self {
+        let column = quote_ident(column);
+        let mut clauses = Vec::with_capacity(2);
+        if let Some(lower) = lower {
+            clauses.push(format!("{} >= {}", column, sql_string_literal(lower)));
+        }
+        if let Some(upper) = upper {
+            clauses.push(format!("{} < {}", column, sql_string_literal(upper)));
+        }
+        if clauses.is_empty() {
+            Self::full()
+        } else {
+            Self::predicate(clauses.join(" AND "))
+        }
+    }
+
+    /// This partition further narrowed by `predicate`.
+    pub(crate) fn and(&self, predicate: &str) -> Self {
+        let predicate = match self.predicate {
+            Some(ref own) => format!("({}) AND ({})", own, predicate),
+            None => predicate.to_string(),
+        };
+        Self { predicate: Some(predicate), rows: self.rows, estimated_rows: self.estimated_rows, kind: self.kind }
+    }
+
+    /// The SELECT statement extracting this partition from `table` (quoted
+    /// here; `columns_sql` must come quoted already). NO ORDER BY.
+    pub(crate) fn select_sql(&self, columns_sql: &str, table: &str) -> String {
+        self.ordered_select_sql(columns_sql, table, &[])
+    }
+
+    /// [`Self::select_sql`] with `ORDER BY order_by` ahead of the `ROWS` window.
+    pub(crate) fn ordered_select_sql(&self, columns_sql: &str, table: &str, order_by: &[String]) -> String {
+        let mut sql = format!("SELECT {} FROM {}", columns_sql, quote_ident(table));
+        if let Some(ref predicate) = self.predicate {
+            sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        if !order_by.is_empty() {
+            let columns: Vec<String> =
+                order_by.iter().map(|c| if c == DB_KEY { c.clone() } else { quote_ident(c) }).collect();
+            sql.push_str(&format!(" ORDER BY {}", columns.join(", ")));
+        }
+        if let Some((first, last)) = self.rows {
+            sql.push_str(&format!(" ROWS {} TO {}", first, last));
+        }
+        sql
+    }
+}
+
+/// The predicate and `ROWS` window, e.g. for reports; `all rows` if neither.
+impl fmt::Display for PartitionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.predicate, self.rows) {
+            (None, None) => f.write_str("all rows"),
+            (Some(predicate), None) => f.write_str(predicate),
+            (None, Some((first, i64::MAX))) => write!(f, "ROWS {} TO end", first),
+            (None, Some((first, last))) => write!(f, "ROWS {} TO {}", first, last),
+            (Some(predicate), Some((first, last))) => write!(f, "{} ROWS {} TO {}", predicate, first, last),
+        }
+    }
+}
+
+/// Evenly spaced boundaries `min = b0 < b1 < ... < bn = max` for `parts`
+/// ranges. Fewer ranges come back when the span is narrower than `parts`.
+pub(crate) fn linear_boundaries(min: i64, max: i64, parts: usize) -> Vec<i64> {
+    let pk_range = max.saturating_sub(min);
+    let pk_step = if pk_range > 0 { pk_range as f64 / parts as f64 } else { 0.0 };
+
+    let mut boundaries: Vec<i64> = (0..parts).map(|i| min + (pk_step * i as f64) as i64).collect();
+    boundaries.push(max);
+    boundaries.dedup();
+    boundaries
+}
+
+/// Half-open ranges between consecutive boundaries, the last one closed.
+pub(crate) fn range_partitions(column: &str, boundaries: &[i64]) -> Vec<PartitionSpec> {
+    match boundaries {
+        [] => Vec::new(),
+        [only] => vec![PartitionSpec::range(column, *only, *only, true)],
+        _ => boundaries
+            .windows(2)
+            .enumerate()
+            .map(|(i, w)| PartitionSpec::range(column, w[0], w[1], i == boundaries.len() - 2))
+            .collect(),
+    }
+}
+
+/// One hash bucket per worker on `column`.
+pub(crate) fn hash_partitions(column: &str, text_key: bool, parts: usize) -> Vec<PartitionSpec> {
+    let parts = parts.max(1);
+    (0..parts)
+        .map(|i| PartitionSpec::hash(column, text_key, parts, i))
+        .collect()
+}
+
+/// The physical record address, which orders a table without a key.
+pub(crate) const DB_KEY: &str = "RDB$DB_KEY";
+
+/// Consecutive `ROWS` windows over `row_count` rows, the last one open-ended
+/// so rows beyond the count aren't lost. Each window is read in its own
+/// transaction, ordered by the primary key or [`DB_KEY`], so they only line
+/// up while the table isn't being modified.
+pub(crate) fn rows_paging_partitions(row_count: i64, parts: usize) -> Vec<PartitionSpec> {
+    let parts = parts.max(1) as i64;
+    let per_part = ((row_count + parts - 1) / parts).max(1);
+
+    (0..parts)
+        .map(|i| {
+            let rows = per_part.min(row_count - i * per_part).max(0);
+            let last = if i == parts - 1 { i64::MAX } else { (i + 1) * per_part };
+            PartitionSpec::rows(i * per_part + 1, last).with_estimate(rows)
+        })
+        .collect()
+}
+
+/// Estimates for partitions cut every `step` rows from `total`: `step` each,
+/// and what's left in the last.
+pub(crate) fn estimate_steps(partitions: &mut [PartitionSpec], step: i64, total: i64) {
+    let last = partitions.len().saturating_sub(1);
+    for (i, partition) in partitions.iter_mut().enumerate() {
+        let rows = if i < last { step } else { total.saturating_sub(step * last as i64).max(0) };
+        partition.estimated_rows = Some(rows);
+    }
+}
+
+/// Estimates for range partitions between sampled `boundaries` that lie
+/// `step` rows apart: `step` each, and for the last one the rows its span of
+/// keys holds at the density the sampled ranges showed. Unlike the remainder
+/// [`estimate_steps`] takes, this doesn't trust a row count that was itself
+/// estimated, nor one the walk ran out of rows before reaching.
+pub(crate) fn estimate_by_density(partitions: &mut [PartitionSpec], boundaries: &[i64], step: i64) {
+    let last = partitions.len().saturating_sub(1);
+    if last == 0 || boundaries.len() != partitions.len() + 1 {
+        return;
+    }
+    let sampled_span = boundaries[last].saturating_sub(boundaries[0]).max(1) as f64;
+    let density = (step.saturating_mul(last as i64)) as f64 / sampled_span;
+    let last_span = boundaries[last + 1].saturating_sub(boundaries[last]).saturating_add(1) as f64;
+    for (i, partition) in partitions.iter_mut().enumerate() {
+        let rows = if i < last { step } else { (last_span * density).round() as i64 };
+        partition.estimated_rows = Some(rows);
+    }
+}
+
+/// Positions in `plan` in the order to start them: largest estimate first,
+/// then those without one, each group in plan order.
+pub(crate) fn largest_first(plan: &[PartitionSpec]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..plan.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(plan[i].estimated_rows));
+    order
+}
+
+/// Quote an identifier exactly as stored in the system tables, doubling
+/// embedded quotes, so mixed case, spaces and reserved words survive.
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a value as a Firebird string literal, doubling embedded quotes.
+pub(crate) fn sql_string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_ident("ORDERS"), "\"ORDERS\"");
+        assert_eq!(quote_ident("Order Lines"), "\"Order Lines\"");
+        assert_eq!(quote_ident("a\"b"), "\"a\"\"b\"");
+        assert_eq!(sql_string_literal("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn rows_windows_cover_the_table() {
+        let plan = rows_paging_partitions(10, 3);
+        let windows: Vec<(i64, i64)> = plan.iter().map(|p| p.rows.unwrap()).collect();
+        assert_eq!(windows, [(1, 4), (5, 8), (9, i64::MAX)]);
+        let estimates: Vec<Option<i64>> = plan.iter().map(|p| p.estimated_rows).collect();
+        assert_eq!(estimates, [Some(4), Some(4), Some(2)]);
+        assert!(plan.iter().all(|p| p.predicate.is_none()));
+    }
+
+    #[test]
+    fn rows_windows_of_small_tables() {
+        // More workers than rows: trailing windows expect nothing
+        let plan = rows_paging_partitions(2, 4);
+        let windows: Vec<(i64, i64)> = plan.iter().map(|p| p.rows.unwrap()).collect();
+        assert_eq!(windows, [(1, 1), (2, 2), (3, 3), (4, i64::MAX)]);
+        assert_eq!(plan.iter().map(|p| p.estimated_rows.unwrap()).sum::<i64>(), 2);
+
+        let plan = rows_paging_partitions(0, 1);
+        assert_eq!(plan[0].rows, Some((1, i64::MAX)));
+        assert_eq!(plan[0].estimated_rows, Some(0));
+    }
+
+    #[test]
+    fn weights_the_last_range_by_density() {
+        // 100 rows per 1000 keys, then a last range ten times as wide
+        let boundaries = [0, 1000, 2000, 12_000];
+        let mut plan = range_partitions("ID", &boundaries);
+        estimate_by_density(&mut plan, &boundaries, 100);
+        let estimates: Vec<Option<i64>> = plan.iter().map(|p| p.estimated_rows).collect();
+        assert_eq!(estimates, [Some(100), Some(100), Some(1000)]);
+        assert_eq!(largest_first(&plan), [2, 0, 1]);
+    }
+
+    #[test]
+    fn null_key_partitions_are_marked_not_parsed() {
+        let null_key = PartitionSpec::null_key("REGION");
+        assert!(null_key.is_null_key());
+        // Narrowing by the table filter keeps the kind
+        assert!(null_key.and("\"ACTIVE\" = 1").is_null_key());
+        assert!(!PartitionSpec::predicate("\"REGION\" IS NULL").is_null_key());
+
+        let json = serde_json::to_string(&null_key).unwrap();
+        assert_eq!(serde_json::from_str::<PartitionSpec>(&json).unwrap(), null_key);
+        // Plain specs serialize as before
+        assert!(!serde_json::to_string(&PartitionSpec::full()).unwrap().contains("kind"));
+    }
+
+    #[test]
+    fn orders_rows_windows() {
+        let spec = PartitionSpec::rows(5, 8);
+        let sql = spec.ordered_select_sql("\"ID\"", "T", &[DB_KEY.to_string()]);
+        assert_eq!(sql, "SELECT \"ID\" FROM \"T\" ORDER BY RDB$DB_KEY ROWS 5 TO 8");
+        let sql = spec.ordered_select_sql("\"ID\"", "T", &["ID".to_string()]);
+        assert_eq!(sql, "SELECT \"ID\" FROM \"T\" ORDER BY \"ID\" ROWS 5 TO 8");
+    }
+}
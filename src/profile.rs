@@ -0,0 +1,87 @@
+//! Per-stage pipeline timing for `--profile`
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stage {
+    /// Waiting on Firebird for rows
+    Fetch,
+    /// Rows → Arrow batches
+    Convert,
+    /// Parquet encoding and file I/O
+    Write,
+    /// Combining partition files into the output
+    Merge,
+    /// Blocked on `--max-memory`
+    BudgetWait,
+    /// Blocked handing work to a full channel, i.e. the next stage is slower
+    QueueWait,
+}
+
+const STAGES: [(Stage, &str); 6] = [
+    (Stage::Fetch, "fetch"),
+    (Stage::Convert, "convert"),
+    (Stage::Write, "write"),
+    (Stage::Merge, "merge"),
+    (Stage::BudgetWait, "budget wait"),
+    (Stage::QueueWait, "queue wait"),
+];
+
+/// Time spent per stage, summed over every thread that worked on it. Cheap
+/// enough (one `Instant` per batch) to collect unconditionally.
+#[derive(Default)]
+pub(crate) struct StageTimes {
+    nanos: [AtomicU64; 6],
+}
+
+impl StageTimes {
+    pub(crate) fn add(&self, stage: Stage, elapsed: Duration) {
+        self.nanos[stage as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Run `op`, charging its duration to `stage`.
+    pub(crate) fn time<T>(&self, stage: Stage, op: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = op();
+        self.add(stage, started.elapsed());
+        result
+    }
+
+    pub(crate) fn secs(&self, stage: Stage) -> f64 {
+        self.nanos[stage as usize].load(Ordering::Relaxed) as f64 / 1e9
+    }
+
+    /// Fold another set of timings into this one.
+    pub(crate) fn absorb(&self, other: &StageTimes) {
+        for (stage, _) in STAGES {
+            self.nanos[stage as usize].fetch_add(other.nanos[stage as usize].load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+
+    /// One line of `stage 1.2s` pairs, skipping stages that never ran.
+    pub(crate) fn summary(&self) -> String {
+        STAGES
+            .iter()
+            .filter(|(stage, _)| self.nanos[*stage as usize].load(Ordering::Relaxed) > 0)
+            .map(|(stage, name)| format!("{} {:.2}s", name, self.secs(*stage)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Which of fetch / convert / write dominated, as advice on what to tune.
+    pub(crate) fn bottleneck(&self) -> &'static str {
+        let fetch = self.secs(Stage::Fetch);
+        let convert = self.secs(Stage::Convert);
+        let write = self.secs(Stage::Write);
+        if fetch >= convert && fetch >= write {
+            "database-bound (fetch): more partitions or a faster link help most"
+        } else if convert >= write {
+            "CPU-bound (Arrow conversion): more cores help most"
+        } else {
+            "I/O-bound (Parquet write): more --writer-threads or faster disk help most"
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Terminal progress bars: one aggregate bar per table (rows, rows/s, ETA)
+//! plus a spinner per running partition
+//!
+//! Off unless `ExtractorConfig::progress` is set; the CLI sets it only when
+//! stderr is a terminal, otherwise the plain progress log lines are used.
+//! The same row counts also go out as [`ProgressEvent::BatchWritten`] when a
+//! progress callback is registered.
+
+use std::sync::OnceLock;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::events::{ProgressCallback, ProgressEvent};
+
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+fn bars() -> &'static MultiProgress {
+    BARS.get_or_init(|| MultiProgress::with_draw_target(ProgressDrawTarget::stderr()))
+}
+
+/// Run `f` with any progress bars hidden, e.g. to write a log line to stderr
+/// without tearing them.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    match BARS.get() {
+        Some(bars) => bars.suspend(f),
+        None => f(),
+    }
+}
+
+pub(crate) struct TableProgress {
+    total: Option<ProgressBar>,
+    observer: Option<ProgressCallback>,
+}
+
+impl TableProgress {
+    /// A bar towards `rows` when the row count is known, else a counting spinner.
+    pub(crate) fn new(enabled: bool, table: &str, rows: Option<i64>, observer: Option<ProgressCallback>) -> Self {
+        if !enabled {
+            return Self { total: None, observer };
+        }
+        let bar = match rows {
+            Some(rows) => ProgressBar::new(rows.max(0) as u64).with_style(
+                ProgressStyle::with_template(
+                    "{prefix} [{elapsed_precise}] {wide_bar} {human_pos}/{human_len} rows ({per_sec}, ETA {eta})",
+                )
+                .expect("valid template"),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template("{prefix} [{elapsed_precise}] {spinner} {human_pos} rows ({per_sec})")
+                    .expect("valid template"),
+            ),
+        };
+        let bar = bars().add(bar.with_prefix(table.to_string()));
+        bar.enable_steady_tick(std::time::Duration::from_millis(200));
+        Self { total: Some(bar), observer }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.total.is_some()
+    }
+
+    /// A spinner for partition `index`, shown while it runs.
+    pub(crate) fn partition(&self, index: usize) -> PartitionProgress {
+        let observer = self.observer.clone();
+        let Some(ref total) = self.total else {
+            return PartitionProgress { bar: None, total: None, observer, index };
+        };
+        let bar = ProgressBar::new_spinner()
+            .with_style(
+                ProgressStyle::with_template("  partition {prefix:>3} {spinner} {human_pos} rows ({per_sec})")
+                    .expect("valid template"),
+            )
+            .with_prefix(index.to_string());
+        let bar = bars().add(bar);
+        PartitionProgress { bar: Some(bar), total: Some(total.clone()), observer, index }
+    }
+
+    /// Rows outside any partition (the sequential path).
+    pub(crate) fn inc(&self, rows: usize) {
+        if let Some(ref total) = self.total {
+            total.inc(rows as u64);
+        }
+        if let Some(ref observer) = self.observer {
+            observer(ProgressEvent::BatchWritten { partition: 0, rows });
+        }
+    }
+}
+
+impl Drop for TableProgress {
+    fn drop(&mut self) {
+        if let Some(total) = self.total.take() {
+            total.finish_and_clear();
+            bars().remove(&total);
+        }
+    }
+}
+
+pub(crate) struct PartitionProgress {
+    bar: Option<ProgressBar>,
+    total: Option<ProgressBar>,
+    observer: Option<ProgressCallback>,
+    index: usize,
+}
+
+impl PartitionProgress {
+    pub(crate) fn inc(&self, rows: usize) {
+        if let Some(ref bar) = self.bar {
+            bar.inc(rows as u64);
+        }
+        if let Some(ref total) = self.total {
+            total.inc(rows as u64);
+        }
+        if let Some(ref observer) = self.observer {
+            observer(ProgressEvent::BatchWritten { partition: self.index, rows });
+        }
+    }
+
+    /// Back out the rows of a failed attempt before a retry recounts them.
+    pub(crate) fn reset(&self) {
+        if let (Some(bar), Some(total)) = (&self.bar, &self.total) {
+            total.set_position(total.position().saturating_sub(bar.position()));
+            bar.reset();
+        }
+    }
+}
+
+impl Drop for PartitionProgress {
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            bars().remove(&bar);
+        }
+    }
+}
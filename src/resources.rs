@@ -0,0 +1,284 @@
+//! Host resource detection: CPU quota and available memory for default worker
+//! counts, free disk space for the pre-extraction check
+
+use std::path::Path;
+
+/// CPUs this process can actually use: the host count clamped by a cgroup CPU
+/// quota, so a 2-CPU pod on a 64-core node sizes for 2.
+pub fn effective_cpus() -> usize {
+    let host = num_cpus::get();
+    match cgroup_cpu_limit() {
+        Some(limit) => host.min(limit).max(1),
+        None => host,
+    }
+}
+
+/// CPU quota from cgroup v2 `cpu.max` (`"<quota> <period>"` or `"max ..."`),
+/// else cgroup v1 `cpu.cfs_quota_us` / `cpu.cfs_period_us` (quota -1 = none).
+#[cfg(target_os = "linux")]
+pub fn cgroup_cpu_limit() -> Option<usize> {
+    use std::fs::read_to_string;
+
+    if let Ok(cpu_max) = read_to_string("/sys/fs/cgroup/cpu.max") {
+        let mut fields = cpu_max.split_whitespace();
+        let quota = fields.next()?;
+        let period = fields.next()?;
+        if quota == "max" {
+            return None;
+        }
+        return quota_cpus(quota.parse().ok()?, period.parse().ok()?);
+    }
+
+    for dir in ["/sys/fs/cgroup/cpu", "/sys/fs/cgroup/cpu,cpuacct"] {
+        let read = |file: &str| -> Option<i64> { read_to_string(format!("{}/{}", dir, file)).ok()?.trim().parse().ok() };
+        if let (Some(quota), Some(period)) = (read("cpu.cfs_quota_us"), read("cpu.cfs_period_us")) {
+            return quota_cpus(quota, period);
+        }
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cgroup_cpu_limit() -> Option<usize> {
+    None
+}
+
+/// Whole CPUs granted by `quota` µs per `period` µs, rounded up.
+#[cfg(target_os = "linux")]
+fn quota_cpus(quota: i64, period: i64) -> Option<usize> {
+    if quota <= 0 || period <= 0 {
+        return None;
+    }
+    Some(((quota as f64 / period as f64).ceil() as usize).max(1))
+}
+
+/// Default worker count: two per usable CPU, reduced so each worker's
+/// `per_worker_bytes` fits in the memory that is actually available.
+pub fn default_parallelism(per_worker_bytes: usize) -> usize {
+    let by_cpu = effective_cpus() * 2;
+    match available_memory() {
+        Some(available) if per_worker_bytes > 0 => by_cpu.min((available / per_worker_bytes as u64) as usize).max(1),
+        _ => by_cpu,
+    }
+}
+
+/// Bytes this process can still allocate: free memory rather than total RAM,
+/// further limited by a container or job-object memory limit when present.
+#[cfg(target_os = "linux")]
+pub fn available_memory() -> Option<u64> {
+    use std::fs::read_to_string;
+
+    let read = |path: &str| -> Option<u64> { read_to_string(path).ok()?.trim().parse().ok() };
+
+    // MemAvailable counts reclaimable page cache, unlike MemFree
+    let host = read_to_string("/proc/meminfo").ok().and_then(|meminfo| {
+        let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    });
+
+    // v2 reports "max" when unlimited (parse fails), v1 a near-u64::MAX sentinel
+    let cgroup = match (read("/sys/fs/cgroup/memory.max"), read("/sys/fs/cgroup/memory.current")) {
+        (Some(limit), Some(used)) => Some(limit.saturating_sub(used)),
+        _ => match (
+            read("/sys/fs/cgroup/memory/memory.limit_in_bytes"),
+            read("/sys/fs/cgroup/memory/memory.usage_in_bytes"),
+        ) {
+            (Some(limit), Some(used)) if limit < (1 << 62) => Some(limit.saturating_sub(used)),
+            _ => None,
+        },
+    };
+
+    match (host, cgroup) {
+        (Some(host), Some(cgroup)) => Some(host.min(cgroup)),
+        (host, cgroup) => host.or(cgroup),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn available_memory() -> Option<u64> {
+    // Free plus inactive pages: inactive ones are reclaimed before anything swaps
+    let mut stats: libc::vm_statistics64 = unsafe { std::mem::zeroed() };
+    let mut count = libc::HOST_VM_INFO64_COUNT;
+    #[allow(deprecated)]
+    let host = unsafe { libc::mach_host_self() };
+    let status = unsafe {
+        libc::host_statistics64(host, libc::HOST_VM_INFO64, &mut stats as *mut _ as libc::host_info64_t, &mut count)
+    };
+    if status != libc::KERN_SUCCESS {
+        return None;
+    }
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some((stats.free_count as u64 + stats.inactive_count as u64) * page_size as u64)
+}
+
+#[cfg(windows)]
+pub fn available_memory() -> Option<u64> {
+    let available = windows::available_physical()?;
+    Some(match windows::job_memory_limit() {
+        Some(limit) => available.min(limit),
+        None => available,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn available_memory() -> Option<u64> {
+    None
+}
+
+/// Free bytes available to this user on the filesystem holding `path`.
+#[cfg(unix)]
+pub fn available_disk(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn available_disk(path: &Path) -> Option<u64> {
+    windows::available_disk(path)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn available_disk(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Whether two existing directories live on the same filesystem, so their
+/// space requirements add up.
+#[cfg(unix)]
+pub fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+/// Whether two existing directories live on the same volume (drive prefix).
+#[cfg(not(unix))]
+pub fn same_filesystem(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a.components().next() == b.components().next(),
+        _ => false,
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{ffi::c_void, mem::size_of, os::windows::ffi::OsStrExt, path::Path, ptr::null_mut};
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct MemoryStatusEx {
+        length: u32,
+        memory_load: u32,
+        total_phys: u64,
+        avail_phys: u64,
+        total_page_file: u64,
+        avail_page_file: u64,
+        total_virtual: u64,
+        avail_virtual: u64,
+        avail_extended_virtual: u64,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct BasicLimitInformation {
+        per_process_user_time_limit: i64,
+        per_job_user_time_limit: i64,
+        limit_flags: u32,
+        minimum_working_set_size: usize,
+        maximum_working_set_size: usize,
+        active_process_limit: u32,
+        affinity: usize,
+        priority_class: u32,
+        scheduling_class: u32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct ExtendedLimitInformation {
+        basic: BasicLimitInformation,
+        io_counters: [u64; 6],
+        process_memory_limit: usize,
+        job_memory_limit: usize,
+        peak_process_memory_used: usize,
+        peak_job_memory_used: usize,
+    }
+
+    const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION: i32 = 9;
+    const JOB_OBJECT_LIMIT_PROCESS_MEMORY: u32 = 0x100;
+    const JOB_OBJECT_LIMIT_JOB_MEMORY: u32 = 0x200;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalMemoryStatusEx(buffer: *mut MemoryStatusEx) -> i32;
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+        fn QueryInformationJobObject(
+            job: *mut c_void,
+            class: i32,
+            info: *mut c_void,
+            length: u32,
+            return_length: *mut u32,
+        ) -> i32;
+    }
+
+    pub(super) fn available_physical() -> Option<u64> {
+        let mut status: MemoryStatusEx = unsafe { std::mem::zeroed() };
+        status.length = size_of::<MemoryStatusEx>() as u32;
+        if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+            return None;
+        }
+        Some(status.avail_phys)
+    }
+
+    /// Memory limit of the job object this process runs in (e.g. a Windows
+    /// container), if it sets one.
+    pub(super) fn job_memory_limit() -> Option<u64> {
+        let mut info: ExtendedLimitInformation = unsafe { std::mem::zeroed() };
+        // A null handle queries the job containing the calling process
+        let ok = unsafe {
+            QueryInformationJobObject(
+                null_mut(),
+                JOB_OBJECT_EXTENDED_LIMIT_INFORMATION,
+                &mut info as *mut _ as *mut c_void,
+                size_of::<ExtendedLimitInformation>() as u32,
+                null_mut(),
+            )
+        };
+        if ok == 0 {
+            return None;
+        }
+        let flags = info.basic.limit_flags;
+        let limits = [
+            (flags & JOB_OBJECT_LIMIT_PROCESS_MEMORY != 0).then_some(info.process_memory_limit as u64),
+            (flags & JOB_OBJECT_LIMIT_JOB_MEMORY != 0).then_some(info.job_memory_limit as u64),
+        ];
+        limits.into_iter().flatten().min()
+    }
+
+    pub(super) fn available_disk(path: &Path) -> Option<u64> {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free_to_caller = 0u64;
+        let ok = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free_to_caller, null_mut(), null_mut()) };
+        if ok == 0 {
+            return None;
+        }
+        Some(free_to_caller)
+    }
+}
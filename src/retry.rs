@@ -0,0 +1,323 @@
+//! Retrying transient Firebird failures with exponential backoff and jitter
+//!
+//! Only errors that can plausibly go away on their own are retried: lost or
+//! refused connections, network read/write failures, shutdowns, lock
+//! conflicts and cancelled (timed-out) statements. Everything else, such as
+//! an unknown table, a bad password or a full disk, fails on the first attempt.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::ErrorKind,
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use rsfbclient::FbError;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::ExtractError;
+
+/// How often and how patiently transient failures are retried.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts after the first one; 0, the default, disables retrying
+    pub retries: usize,
+    /// Wait before the first retry; doubles with every further one
+    #[serde(with = "crate::config::secs")]
+    pub initial_backoff: Duration,
+    /// Upper bound on the wait between attempts
+    #[serde(with = "crate::config::secs")]
+    pub max_backoff: Duration,
+    /// Fraction (0.0-1.0) of each wait that's randomized, so partitions that
+    /// failed together don't all reconnect at the same instant
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.25,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self { retries: 0, ..Self::default() }
+    }
+
+    /// Run `op`, retrying retryable failures with backoff until `retries` is used up.
+    pub(crate) fn run<T>(&self, label: &str, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            let e = match op() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            match self.delay_after(label, attempt, &e) {
+                Some(delay) => thread::sleep(delay),
+                None => return Err(e),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// [`Self::run`] for async operations, waiting on the tokio timer.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async<T, F>(&self, label: &str, mut op: impl FnMut() -> F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let e = match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            match self.delay_after(label, attempt, &e) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(e),
+            }
+            attempt += 1;
+        }
+    }
+
+    /// The wait after `e` failed attempt `attempt` (from 0) of `label`, logged;
+    /// `None` when `e` isn't retryable or the retries are used up.
+    fn delay_after(&self, label: &str, attempt: usize, e: &anyhow::Error) -> Option<Duration> {
+        if attempt >= self.retries || !is_retryable(e) {
+            return None;
+        }
+        let backoff = self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt as u32)).min(self.max_backoff);
+        let delay = self.jittered(backoff);
+        warn!(
+            "  {} failed (attempt {}/{}): {:#} — retrying in {:.1}s",
+            label,
+            attempt + 1,
+            self.retries + 1,
+            e,
+            delay.as_secs_f64()
+        );
+        Some(delay)
+    }
+
+    /// `delay` with up to `jitter` of it replaced by a random amount.
+    fn jittered(&self, delay: Duration) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        // A fresh RandomState is randomly keyed, which is all the randomness needed here
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - jitter + jitter * random)
+    }
+}
+
+/// Firebird status codes of transient failures, as they appear in error messages.
+const RETRYABLE_GDS_CODES: &[&str] = &[
+    "335544336", // deadlock
+    "335544345", // lock_conflict
+    "335544528", // shutdown
+    "335544721", // network_error
+    "335544726", // net_read_err
+    "335544727", // net_write_err
+    "335544741", // lost_db_connection
+    "335544794", // cancelled
+    "335544856", // att_shutdown
+    "335545041", // cfg_stmt_timeout
+];
+
+/// Lower-case message fragments of transient failures, for clients that
+/// report the text without the status code.
+const RETRYABLE_MESSAGES: &[&str] = &[
+    "unable to complete network request",
+    "error reading data from the connection",
+    "error writing data to the connection",
+    "connection lost",
+    "connection shutdown",
+    "connection rejected by remote interface",
+    "lock conflict",
+    "deadlock",
+    "operation was cancelled",
+    "timeout",
+    "timed out",
+    "shutdown",
+    "unavailable database",
+];
+
+/// Refusals of a new attachment because the server or database allows no
+/// more: `max_att_exceeded`, and the messages of servers capping connections.
+const ATTACHMENT_LIMIT_GDS_CODES: &[&str] = &["335544744"];
+const ATTACHMENT_LIMIT_MESSAGES: &[&str] =
+    &["maximum user count exceeded", "too many attachments", "too many connections", "connections limit"];
+
+/// Whether `e` is the server refusing a connection for its attachment limit,
+/// which frees up when other attachments close rather than after a backoff.
+pub(crate) fn is_attachment_limit(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| match cause.downcast_ref::<FbError>() {
+        Some(FbError::Sql { msg, .. } | FbError::Other(msg)) => {
+            let msg = msg.to_lowercase();
+            ATTACHMENT_LIMIT_GDS_CODES.iter().any(|code| msg.contains(code))
+                || ATTACHMENT_LIMIT_MESSAGES.iter().any(|fragment| msg.contains(fragment))
+        }
+        _ => false,
+    })
+}
+
+/// Whether `e` is worth another attempt. Connection attempts are retried
+/// where they're made, so a connection that still couldn't be opened isn't
+/// retried again around it.
+pub(crate) fn is_retryable(e: &anyhow::Error) -> bool {
+    for cause in e.chain() {
+        if let Some(tagged) = cause.downcast_ref::<ExtractError>() {
+            return !matches!(tagged, ExtractError::Connection(_)) && is_retryable(tagged.inner());
+        }
+        if let Some(fb) = cause.downcast_ref::<FbError>() {
+            return match fb {
+                FbError::Io(io) => is_transient_io(io.kind()),
+                FbError::Sql { msg, code } => is_transient_sql(*code, msg),
+                FbError::Other(msg) => is_transient_message(msg),
+            };
+        }
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return is_transient_io(io.kind());
+        }
+    }
+    false
+}
+
+fn is_transient_io(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::TimedOut
+            | ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::Interrupted
+    )
+}
+
+/// SQLCODE -902 covers both lost connections and failed logins, -901 both
+/// cancellations and plain runtime errors and -904 both an unavailable
+/// database and hard limits, so those are told apart by the message; -913
+/// (deadlock) always retries.
+fn is_transient_sql(code: i32, msg: &str) -> bool {
+    match code {
+        -913 => true,
+        -904 | -902 | -901 => is_transient_message(msg),
+        _ => false,
+    }
+}
+
+fn is_transient_message(msg: &str) -> bool {
+    if RETRYABLE_GDS_CODES.iter().any(|code| msg.contains(code)) {
+        return true;
+    }
+    let msg = msg.to_lowercase();
+    // Logins fail with -902 as well and never fix themselves
+    if msg.contains("password") || msg.contains("not defined") || msg.contains("no permission") {
+        return false;
+    }
+    RETRYABLE_MESSAGES.iter().any(|fragment| msg.contains(fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql(code: i32, msg: &str) -> anyhow::Error {
+        FbError::Sql { msg: msg.to_string(), code }.into()
+    }
+
+    fn io(kind: ErrorKind) -> anyhow::Error {
+        FbError::Io(kind.into()).into()
+    }
+
+    fn other(msg: &str) -> anyhow::Error {
+        FbError::Other(msg.to_string()).into()
+    }
+
+    #[test]
+    fn classifies_sql_errors() {
+        let cases = [
+            (-913, "deadlock; update conflicts with concurrent update", true),
+            (-913, "", true),
+            (-902, "Unable to complete network request to host \"db\"", true),
+            (-902, "Error reading data from the connection.", true),
+            (-902, "connection shutdown", true),
+            (-902, "Your user name and password are not defined. Ask your database administrator", false),
+            (-902, "I/O error during \"open\" operation for file \"/db/missing.fdb\"", false),
+            (-901, "operation was cancelled; Attachment level timeout expired.", true),
+            (-901, "lock conflict on no wait transaction", true),
+            (-901, "no permission for SELECT access to TABLE ORDERS", false),
+            (-901, "Attempt to execute an unprepared dynamic SQL statement", false),
+            (-904, "unavailable database", true),
+            (-904, "Feature is not supported; encryption plugin unavailable", false),
+            (-904, "unsupported network protocol version", false),
+            (-204, "Table unknown ORDERS", false),
+            (-204, "Table unknown TIMEOUT_LOG", false),
+            (-803, "violation of PRIMARY or UNIQUE KEY constraint; 335544345", false),
+        ];
+        for (code, msg, retryable) in cases {
+            assert_eq!(is_retryable(&sql(code, msg)), retryable, "{}: {}", code, msg);
+        }
+    }
+
+    #[test]
+    fn classifies_io_and_other_errors() {
+        let cases = [
+            (io(ErrorKind::ConnectionReset), true),
+            (io(ErrorKind::ConnectionRefused), true),
+            (io(ErrorKind::TimedOut), true),
+            (io(ErrorKind::UnexpectedEof), true),
+            (io(ErrorKind::PermissionDenied), false),
+            (io(ErrorKind::NotFound), false),
+            (other("connection lost to database"), true),
+            (other("Error writing data to the connection."), true),
+            (other("statement timed out"), true),
+            (other("isc 335544726"), true),
+            (other("Network protocol not supported"), false),
+            (other("wire crypt plugin unavailable"), false),
+            (other("Your user name and password are not defined"), false),
+            (anyhow::Error::new(std::io::Error::from(ErrorKind::BrokenPipe)), true),
+            (anyhow::Error::new(std::io::Error::from(ErrorKind::InvalidData)), false),
+            (anyhow::anyhow!("connection lost"), false),
+        ];
+        for (i, (e, retryable)) in cases.into_iter().enumerate() {
+            assert_eq!(is_retryable(&e), retryable, "case {}: {:#}", i, e);
+        }
+    }
+
+    #[test]
+    fn follows_the_innermost_tag() {
+        let lost = || io(ErrorKind::ConnectionReset);
+        // Connections are retried where they're opened, never again around them
+        assert!(!is_retryable(&ExtractError::tag(ExtractError::Connection, lost())));
+        assert!(!is_retryable(&ExtractError::tag(ExtractError::Connection, lost()).context("Partition 3")));
+        assert!(is_retryable(&ExtractError::tag(ExtractError::PartitionFailed, lost())));
+        assert!(is_retryable(&lost().context("Partition 3")));
+        assert!(!is_retryable(&ExtractError::tag(ExtractError::Validation, other("duplicate key"))));
+    }
+
+    #[test]
+    fn detects_the_attachment_limit() {
+        assert!(is_attachment_limit(&sql(-904, "335544744")));
+        assert!(is_attachment_limit(&sql(-904, "Maximum user count exceeded. Contact your database administrator")));
+        assert!(is_attachment_limit(&other("too many connections")));
+        assert!(is_attachment_limit(&other("Too many attachments").context("Connecting to db")));
+        assert!(!is_attachment_limit(&sql(-902, "Unable to complete network request to host")));
+        assert!(!is_attachment_limit(&io(ErrorKind::ConnectionRefused)));
+        assert!(!is_attachment_limit(&anyhow::anyhow!("too many connections")));
+    }
+}
@@ -0,0 +1,222 @@
+//! When repeated runs start: `--schedule` (cron) and `--watch --interval`
+//!
+//! Cron expressions have the five classic fields (minute, hour, day of month,
+//! month, day of week) with `*`, values, ranges, lists and `/step`, matched
+//! against local time. As in Vixie cron, a day matches when either day field
+//! does if both are restricted.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike};
+
+/// How far ahead [`Schedule::next_after`] looks for a matching minute.
+const SEARCH_DAYS: i64 = 5 * 366;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    Cron(Cron),
+    /// Start to start
+    Every(Duration),
+}
+
+impl Schedule {
+    /// The first start strictly after `after`; `None` if a cron expression
+    /// never matches (`0 0 31 2 *`).
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Self::Every(interval) => Some(after + chrono::Duration::from_std(*interval).ok()?),
+            Self::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cron(cron) => write!(f, "cron '{}'", cron.source),
+            Self::Every(interval) => write!(f, "every {}s", interval.as_secs()),
+        }
+    }
+}
+
+/// A parsed five-field cron expression; each field is a bit set of the
+/// values it matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// `*` in day of month or day of week, which decides how they combine
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron expression '{}' must have 5 fields (minute hour day month weekday)", s));
+        };
+        // Sunday is 0 or 7
+        let weekdays = parse_field(weekday, 0, 7, "day of week")?;
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days: parse_field(day, 1, 31, "day of month")?,
+            months: parse_field(month, 1, 12, "month")?,
+            weekdays: (weekdays | weekdays >> 7) & 0x7f,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+/// Bits `min..=max` set by a field such as `*/15`, `1-5` or `0,30`.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid cron {} '{}' (expected {}-{})", name, field, min, max);
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (a.parse().map_err(|_| invalid())?, b.parse().map_err(|_| invalid())?),
+                // `5/15` means from 5 to the end
+                None if part.contains('/') => (range.parse().map_err(|_| invalid())?, max),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            },
+        };
+        if first < min || last > max || first > last {
+            return Err(invalid());
+        }
+        bits |= (first..=last).step_by(step as usize).fold(0, |bits, v| bits | 1 << v);
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    fn day_matches(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & 1 << time.day() != 0;
+        let weekday = self.weekdays & 1 << time.weekday().num_days_from_sunday() != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let end = start + chrono::Duration::days(SEARCH_DAYS);
+        let mut time = start;
+        while time < end {
+            if self.months & 1 << time.month() == 0 || !self.day_matches(&time) {
+                time = (time.date() + chrono::Duration::days(1)).and_hms_opt(0, 0, 0)?;
+            } else if self.hours & 1 << time.hour() == 0 {
+                time = time.with_minute(0)? + chrono::Duration::hours(1);
+            } else if self.minutes & 1 << time.minute() == 0 {
+                time += chrono::Duration::minutes(1);
+            } else {
+                // Times skipped by a DST change don't exist; doubled ones run once
+                match Local.from_local_datetime(&time).earliest() {
+                    Some(local) if local > after => return Some(local),
+                    _ => time += chrono::Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// `90`, `30s`, `15m`, `2h` or `1d`; a bare number is seconds.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number.trim().parse().map_err(|_| format!("invalid interval '{}'", s))?;
+    let seconds = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        other => return Err(format!("unknown interval unit '{}' in '{}'", other, s)),
+    };
+    if value == 0 {
+        return Err(format!("interval '{}' must be positive", s));
+    }
+    Ok(Duration::from_secs(value * seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn next(cron: &str, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        cron.parse::<Cron>().unwrap().next_after(after)
+    }
+
+    #[test]
+    fn parses_fields() {
+        let cron: Cron = "*/15 2-4 1,15 * 1-5".parse().unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.hours, 1 << 2 | 1 << 3 | 1 << 4);
+        assert_eq!(cron.days, 1 << 1 | 1 << 15);
+        assert_eq!(cron.weekdays, 0b0111110);
+        assert!(!cron.any_day && !cron.any_weekday);
+        // Sunday as 7
+        assert_eq!("0 0 * * 7".parse::<Cron>().unwrap().weekdays, 1);
+        assert_eq!("5/20 * * * *".parse::<Cron>().unwrap().minutes, 1 << 5 | 1 << 25 | 1 << 45);
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for bad in ["* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "5-1 * * * *", "*/0 * * * *"] {
+            assert!(bad.parse::<Cron>().is_err(), "{} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn finds_the_next_start() {
+        assert_eq!(next("0 2 * * *", at(2026, 3, 10, 1, 30)), Some(at(2026, 3, 10, 2, 0)));
+        // Strictly after
+        assert_eq!(next("0 2 * * *", at(2026, 3, 10, 2, 0)), Some(at(2026, 3, 11, 2, 0)));
+        assert_eq!(next("30 * * * *", at(2026, 12, 31, 23, 45)), Some(at(2027, 1, 1, 0, 30)));
+        assert_eq!(next("0 0 31 2 *", at(2026, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn restricted_days_match_either() {
+        // 2026-03-02 is a Monday: the weekday matches before the 15th does
+        assert_eq!(next("0 9 15 * 1", at(2026, 3, 1, 0, 0)), Some(at(2026, 3, 2, 9, 0)));
+        // With the weekday unrestricted only the day of month counts
+        assert_eq!(next("0 9 15 * *", at(2026, 3, 1, 0, 0)), Some(at(2026, 3, 15, 9, 0)));
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_interval("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_interval("15min"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_interval(" 2H "), Ok(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(parse_interval("1d"), Ok(Duration::from_secs(24 * 60 * 60)));
+        for bad in ["", "0", "0s", "5w", "m", "-5s"] {
+            assert!(parse_interval(bad).is_err(), "{} parsed", bad);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Cross-table scheduling: many tables extracted concurrently under one worker budget
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use anyhow::Result;
+use tracing::{debug, info};
+
+use crate::config::PartitionStrategy;
+use crate::error::ExtractError;
+use crate::extractor::{resolve_table, ExtractionStats, Extractor};
+
+/// Tables below this many rows are extracted by a single sequential worker.
+const SMALL_TABLE_ROWS: i64 = 1_000_000;
+
+struct TableJob {
+    table: String,
+    rows: i64,
+    workers: usize,
+}
+
+impl Extractor {
+    /// Extract several tables concurrently, sharing the configured
+    /// `parallelism` as one worker budget across all of them.
+    ///
+    /// Small tables get a single sequential worker each; large tables get
+    /// partitions in proportion to their share of the total row count. Tables
+    /// start largest first, and whenever workers free up the largest pending
+    /// table that fits is started, so small tables fill the gaps around big
+    /// ones. A failed table doesn't stop the others; results come back in the
+    /// order given. Once cancelled, tables not yet started fail as
+    /// [`ExtractError::Cancelled`] without connecting.
+    pub fn extract_tables(&self, tables: &[String]) -> Vec<(String, Result<ExtractionStats, ExtractError>)> {
+        let total_workers = self.config.parallelism.max(1);
+
+        let mut jobs = Vec::with_capacity(tables.len());
+        let mut results: Vec<Option<Result<ExtractionStats, ExtractError>>> = (0..tables.len()).map(|_| None).collect();
+        for (i, table) in tables.iter().enumerate() {
+            match self.table_size_hint(table) {
+                Ok(rows) => jobs.push((i, TableJob { table: table.clone(), rows, workers: 1 })),
+                Err(e) => {
+                    let e = e.context(format!("Failed to size table {}", table));
+                    results[i] = Some(Err(ExtractError::classify(e, ExtractError::Metadata)));
+                }
+            }
+        }
+
+        let total_rows: i64 = jobs.iter().map(|(_, job)| job.rows.max(0)).sum();
+        for (_, job) in &mut jobs {
+            if job.rows >= SMALL_TABLE_ROWS && total_rows > 0 {
+                let share = (total_workers as f64 * job.rows as f64 / total_rows as f64).ceil() as usize;
+                job.workers = share.clamp(1, total_workers);
+            }
+            // A table's own configured parallelism is what it will actually use
+            if let Some(parallelism) = self.config.table_overrides(&job.table).and_then(|o| o.parallelism) {
+                job.workers = parallelism.clamp(1, total_workers);
+            }
+        }
+        jobs.sort_by(|a, b| b.1.rows.cmp(&a.1.rows));
+
+        info!("→ Scheduling {} tables over {} workers", jobs.len(), total_workers);
+        for (_, job) in &jobs {
+            debug!("  {}: ~{} rows, {} workers", job.table, job.rows, job.workers);
+        }
+
+        let free = (Mutex::new(total_workers), Condvar::new());
+        let finished = Mutex::new(Vec::with_capacity(jobs.len()));
+
+        thread::scope(|scope| {
+            let mut pending = jobs;
+            while !pending.is_empty() {
+                let (lock, cvar) = &free;
+                let mut available = lock.lock().unwrap();
+                let next = loop {
+                    if self.cancel.is_cancelled() {
+                        break None;
+                    }
+                    if let Some(pos) = pending.iter().position(|(_, job)| job.workers <= *available) {
+                        break Some(pos);
+                    }
+                    available = cvar.wait(available).unwrap();
+                };
+                let Some(next) = next else {
+                    let mut finished = finished.lock().unwrap();
+                    for (index, job) in pending.drain(..) {
+                        let e = anyhow::anyhow!("Extraction cancelled before {} started", job.table);
+                        finished.push((index, Err(ExtractError::Cancelled(e))));
+                    }
+                    break;
+                };
+                let (index, job) = pending.remove(next);
+                *available -= job.workers;
+                drop(available);
+
+                let extractor = self.for_table_workers(job.workers);
+                let free = &free;
+                let finished = &finished;
+                scope.spawn(move || {
+                    let result = extractor.extract_table(&job.table);
+                    finished.lock().unwrap().push((index, result));
+
+                    let (lock, cvar) = free;
+                    *lock.lock().unwrap() += job.workers;
+                    cvar.notify_all();
+                });
+            }
+        });
+
+        for (index, result) in finished.into_inner().unwrap() {
+            results[index] = Some(result);
+        }
+        tables
+            .iter()
+            .cloned()
+            .zip(results.into_iter().map(|r| r.expect("every table is scheduled or failed sizing")))
+            .collect()
+    }
+
+    /// Rows used to weigh a table: PK index statistics when present, else an
+    /// exact count unless `skip_count` is set (unknown tables count as small).
+    fn table_size_hint(&self, table: &str) -> Result<i64> {
+        let (table, estimate) = {
+            let mut conn = self.source.connect()?;
+            let table = resolve_table(&mut *conn, table)?;
+            let estimate = conn.estimate_row_count(&table)?;
+            (table, estimate)
+        };
+        match estimate {
+            Some(rows) => Ok(rows),
+            None if self.config.skip_count => Ok(0),
+            None => self.count_rows(&table),
+        }
+    }
+
+    /// An extractor sharing this one's connection pool and memory budget but
+    /// limited to `workers` partitions.
+    fn for_table_workers(&self, workers: usize) -> Extractor {
+        let mut config = self.config.clone();
+        config.parallelism = workers;
+        if workers == 1 {
+            config.partition_strategy = PartitionStrategy::None;
+        }
+        Extractor {
+            config,
+            pool: Arc::clone(&self.pool),
+            source: Arc::clone(&self.source),
+            budget: Arc::clone(&self.budget),
+            observer: self.observer.clone(),
+            cancel: self.cancel.clone(),
+            transform: self.transform.clone(),
+            type_mapper: Arc::clone(&self.type_mapper),
+            throttle: Arc::clone(&self.throttle),
+        }
+    }
+}
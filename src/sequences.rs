@@ -0,0 +1,92 @@
+//! Generator (sequence) export
+//!
+//! Rebuilding a database elsewhere, or continuing to assign IDs after a
+//! migration, needs each generator's current value besides the table data.
+//! [`Extractor::list_sequences`] reads the user generators with `GEN_ID(g, 0)`,
+//! which leaves them unchanged, and [`write_sequences`] saves them as JSON or
+//! Parquet. Values are read one generator at a time, so a busy database can
+//! move on between the first and the last.
+
+use std::{fs::File, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{ArrayRef, Int64Array, StringArray},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+use rsfbclient::Queryable;
+use serde::Serialize;
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+use crate::partition::quote_ident;
+
+/// A generator and its value when read.
+#[derive(Clone, Debug, Serialize)]
+pub struct Sequence {
+    pub name: String,
+    /// The last value handed out; the next `NEXT VALUE FOR` returns
+    /// `value + increment`
+    pub value: i64,
+    pub increment: i64,
+    /// `START WITH` of its definition
+    pub initial_value: i64,
+}
+
+impl Extractor {
+    /// The database's user generators, by name, with their current values.
+    pub fn list_sequences(&self) -> Result<Vec<Sequence>, ExtractError> {
+        let query = || -> Result<Vec<Sequence>> {
+            let sql = r#"
+                SELECT TRIM(rdb$generator_name),
+                    COALESCE(rdb$generator_increment, 1), COALESCE(rdb$initial_value, 0)
+                FROM rdb$generators
+                WHERE COALESCE(rdb$system_flag, 0) = 0
+                ORDER BY 1
+            "#;
+            let mut conn = self.pool.acquire()?;
+            let generators: Vec<(String, i64, i64)> = conn.query(sql, ())?;
+            let mut sequences = Vec::with_capacity(generators.len());
+            for (name, increment, initial_value) in generators {
+                let sql = format!("SELECT GEN_ID({}, 0) FROM rdb$database", quote_ident(&name));
+                let values: Vec<(i64,)> = conn.query(&sql, ()).with_context(|| format!("Failed to read {}", name))?;
+                let value = values.first().map_or(0, |v| v.0);
+                sequences.push(Sequence { name, value, increment, initial_value });
+            }
+            Ok(sequences)
+        };
+        query().map_err(|e| ExtractError::classify(e, ExtractError::Metadata))
+    }
+}
+
+/// Write `sequences` to `path`: Parquet for a `.parquet` path, JSON otherwise.
+pub fn write_sequences(sequences: &[Sequence], path: &Path) -> Result<()> {
+    let parquet = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"));
+    if !parquet {
+        let json = serde_json::to_string_pretty(sequences)? + "\n";
+        return std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()));
+    }
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("value", DataType::Int64, false),
+        Field::new("increment", DataType::Int64, false),
+        Field::new("initial_value", DataType::Int64, false),
+    ]));
+    let int_column = |get: fn(&Sequence) -> i64| -> ArrayRef {
+        Arc::new(Int64Array::from_iter_values(sequences.iter().map(get)))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(sequences.iter().map(|s| &s.name))),
+        int_column(|s| s.value),
+        int_column(|s| s.increment),
+        int_column(|s| s.initial_value),
+    ];
+    let batch = RecordBatch::try_new(Arc::clone(&schema), columns)?;
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, schema, None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
@@ -0,0 +1,403 @@
+//! `serve`: extraction jobs over a small HTTP API (`server` feature)
+//!
+//! `POST /extract` queues a job and answers `202` with its id, `GET /jobs/<id>`
+//! reports its status and per-table stats or errors, and `DELETE /jobs/<id>`
+//! cancels it, whether queued or running. Jobs run one at a time, in
+//! submission order, each as an [`Extractor::extract_many`] with the server's
+//! extractor, so they share its connection pool and settings. Like the
+//! metrics endpoint it speaks plain HTTP/1.1 over std sockets; put a proxy in
+//! front for TLS and authentication.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use crate::cancel::CancellationToken;
+use crate::config::OutputMode;
+use crate::extractor::{ExtractionStats, Extractor};
+use crate::orchestrate::{ExtractionReport, FailurePolicy, TableOutcome, TableSpec};
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Longest request or header line accepted, and most header lines.
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// Requests answered at once; more are turned away with `503`.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a finished job stays queryable, and most finished jobs kept.
+const JOB_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_FINISHED_JOBS: usize = 1000;
+
+/// How often an idle job runner checks whether the server is stopping.
+const STOP_POLL: Duration = Duration::from_millis(200);
+
+/// Body of `POST /extract`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobRequest {
+    tables: Vec<String>,
+    /// A directory under the server's output directory for this job, as a
+    /// relative path without `..`
+    #[serde(default)]
+    out_dir: Option<PathBuf>,
+    /// Replaces the server's output mode for this job
+    #[serde(default)]
+    output_mode: Option<OutputMode>,
+    /// Skip the remaining tables after one fails
+    #[serde(default)]
+    stop_on_failure: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    Running,
+    /// Every table was extracted
+    Succeeded,
+    /// At least one table failed
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// What `GET /jobs/<id>` returns.
+#[derive(Serialize)]
+struct Job {
+    id: u64,
+    status: JobStatus,
+    tables: Vec<String>,
+    submitted_unix: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    results: Vec<JobTable>,
+    #[serde(skip)]
+    request: JobRequest,
+    #[serde(skip)]
+    cancel: CancellationToken,
+    /// When the job finished, for expiry
+    #[serde(skip)]
+    finished: Option<Instant>,
+}
+
+#[derive(Serialize)]
+struct JobTable {
+    table: String,
+    /// `extracted`, `failed` or `skipped`
+    outcome: &'static str,
+    #[serde(flatten)]
+    stats: Option<ExtractionStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl Jobs {
+    /// Drop finished jobs past [`JOB_RETENTION`], then the oldest beyond
+    /// [`MAX_FINISHED_JOBS`].
+    fn expire(&mut self) {
+        self.jobs.retain(|_, job| job.finished.map_or(true, |at| at.elapsed() < JOB_RETENTION));
+        let finished: Vec<u64> = self.jobs.values().filter(|job| job.finished.is_some()).map(|job| job.id).collect();
+        for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_JOBS)) {
+            self.jobs.remove(id);
+        }
+    }
+}
+
+/// Queues and runs extraction jobs submitted over HTTP.
+pub struct Server {
+    extractor: Extractor,
+    jobs: Mutex<Jobs>,
+    queue: (Sender<u64>, Receiver<u64>),
+    stopping: CancellationToken,
+    /// Requests being answered
+    connections: Arc<AtomicUsize>,
+}
+
+impl Server {
+    pub fn new(extractor: Extractor) -> Arc<Self> {
+        Arc::new(Self {
+            extractor,
+            jobs: Mutex::new(Jobs { next_id: 1, jobs: BTreeMap::new() }),
+            queue: crossbeam_channel::unbounded(),
+            stopping: CancellationToken::new(),
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Answer requests on `addr` and run jobs on the calling thread until
+    /// [`Server::stop`] is called and the running job has finished.
+    pub fn run(self: &Arc<Self>, addr: impl ToSocketAddrs) -> Result<()> {
+        let listener = TcpListener::bind(addr).context("Failed to bind the server address")?;
+        info!("Serving on http://{}", listener.local_addr()?);
+        let server = Arc::clone(self);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if server.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    server.connections.fetch_sub(1, Ordering::SeqCst);
+                    let busy = json!({ "error": "Too many requests at once" });
+                    let _ = write_response(&mut stream, "503 Service Unavailable", &busy);
+                    continue;
+                }
+                let server = Arc::clone(&server);
+                thread::spawn(move || {
+                    if let Err(e) = server.respond(stream) {
+                        debug!("Request failed: {:#}", e);
+                    }
+                    server.connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        let queue = &self.queue.1;
+        while !self.stopping.is_cancelled() {
+            match queue.recv_timeout(STOP_POLL) {
+                Ok(id) => self.run_job(id),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancel every queued and running job and make [`Server::run`] return.
+    pub fn stop(&self) {
+        self.stopping.cancel();
+        let mut jobs = self.jobs.lock().unwrap();
+        for job in jobs.jobs.values_mut().filter(|job| !job.status.is_finished()) {
+            job.cancel.cancel();
+            if job.status == JobStatus::Queued {
+                job.status = JobStatus::Cancelled;
+                job.finished = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.is_cancelled()
+    }
+
+    fn run_job(&self, id: u64) {
+        let (extractor, plan, on_failure) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.jobs.get_mut(&id) else {
+                return;
+            };
+            // Cancelled while queued
+            if job.status != JobStatus::Queued {
+                return;
+            }
+            job.status = JobStatus::Running;
+            let mut extractor = self.extractor.clone().with_cancellation(job.cancel.clone());
+            if let Some(ref out_dir) = job.request.out_dir {
+                extractor.config.out_dir = out_dir.clone();
+            }
+            if let Some(mode) = job.request.output_mode {
+                extractor.config.output_mode = mode;
+            }
+            let plan: Vec<TableSpec> = job.request.tables.iter().map(TableSpec::new).collect();
+            let on_failure = if job.request.stop_on_failure { FailurePolicy::Stop } else { FailurePolicy::Continue };
+            (extractor, plan, on_failure)
+        };
+
+        info!("Job {}: {}", id, plan.iter().map(|t| t.name.as_str()).collect::<Vec<_>>().join(", "));
+        let report = extractor.extract_many(&plan, on_failure);
+        self.finish_job(id, report);
+    }
+
+    fn finish_job(&self, id: u64, report: ExtractionReport) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.jobs.get_mut(&id) else {
+            return;
+        };
+        job.status = if job.cancel.is_cancelled() {
+            JobStatus::Cancelled
+        } else if report.is_success() {
+            JobStatus::Succeeded
+        } else {
+            JobStatus::Failed
+        };
+        job.finished = Some(Instant::now());
+        job.duration_secs = Some(report.duration_secs);
+        job.results = report
+            .tables
+            .into_iter()
+            .map(|t| {
+                let (outcome, stats, error) = match t.outcome {
+                    TableOutcome::Extracted(stats) => ("extracted", Some(stats), None),
+                    TableOutcome::Failed(e) => ("failed", None, Some(format!("{:#}", e))),
+                    TableOutcome::Skipped => ("skipped", None, None),
+                };
+                JobTable { table: t.table, outcome, stats, error }
+            })
+            .collect();
+        match job.status {
+            JobStatus::Failed => warn!("Job {}: failed", id),
+            status => info!("Job {}: {:?}", id, status),
+        }
+    }
+
+    fn submit(&self, body: &[u8]) -> Result<u64> {
+        let mut request: JobRequest = serde_json::from_slice(body).context("Invalid job")?;
+        if request.tables.is_empty() || request.tables.iter().any(|t| t.trim().is_empty()) {
+            anyhow::bail!("Invalid job: tables must name at least one table");
+        }
+        if let Some(ref out_dir) = request.out_dir {
+            let out_dir = job_out_dir(&self.extractor.config.out_dir, out_dir)?;
+            std::fs::create_dir_all(&out_dir).with_context(|| format!("Failed to create {}", out_dir.display()))?;
+            request.out_dir = Some(out_dir);
+        }
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.expire();
+        let id = jobs.next_id;
+        jobs.next_id += 1;
+        let submitted_unix = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let job = Job {
+            id,
+            status: JobStatus::Queued,
+            tables: request.tables.clone(),
+            submitted_unix,
+            duration_secs: None,
+            results: Vec::new(),
+            request,
+            cancel: CancellationToken::new(),
+            finished: None,
+        };
+        jobs.jobs.insert(id, job);
+        self.queue.0.send(id)?;
+        Ok(id)
+    }
+
+    /// Cancel job `id`; `None` if there's no such job.
+    fn cancel(&self, id: u64) -> Option<serde_json::Value> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.jobs.get_mut(&id)?;
+        if !job.status.is_finished() {
+            job.cancel.cancel();
+            // A running job is marked once it has stopped and cleaned up
+            if job.status == JobStatus::Queued {
+                job.status = JobStatus::Cancelled;
+                job.finished = Some(Instant::now());
+            }
+        }
+        serde_json::to_value(&*job).ok()
+    }
+
+    fn route(&self, method: &str, path: &str, body: &[u8]) -> (&'static str, serde_json::Value) {
+        let job_id = path.strip_prefix("/jobs/").map(|id| id.parse::<u64>());
+        match (method, path, job_id) {
+            ("POST", "/extract", _) => match self.submit(body) {
+                Ok(id) => ("202 Accepted", json!({ "id": id, "status": JobStatus::Queued })),
+                Err(e) => ("400 Bad Request", json!({ "error": format!("{:#}", e) })),
+            },
+            ("GET", _, Some(Ok(id))) => match self.jobs.lock().unwrap().jobs.get(&id) {
+                Some(job) => ("200 OK", json!(job)),
+                None => ("404 Not Found", json!({ "error": format!("No job {}", id) })),
+            },
+            ("DELETE", _, Some(Ok(id))) => match self.cancel(id) {
+                Some(job) => ("202 Accepted", job),
+                None => ("404 Not Found", json!({ "error": format!("No job {}", id) })),
+            },
+            (_, "/extract", _) | (_, _, Some(Ok(_))) => {
+                ("405 Method Not Allowed", json!({ "error": format!("{} not allowed on {}", method, path) }))
+            }
+            _ => ("404 Not Found", json!({ "error": format!("No route for {}", path) })),
+        }
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        read_line(&mut reader, &mut request_line)?;
+        let mut content_length = 0;
+        let mut header = String::new();
+        let mut headers = 0;
+        while read_line(&mut reader, &mut header)? > 2 {
+            headers += 1;
+            anyhow::ensure!(headers <= MAX_HEADERS, "More than {} header lines", MAX_HEADERS);
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().context("Invalid Content-Length")?;
+                }
+            }
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let (status, body) = if content_length > MAX_BODY {
+            ("413 Payload Too Large", json!({ "error": format!("Bodies are limited to {} bytes", MAX_BODY) }))
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            self.route(method, path.trim_end_matches('/'), &body)
+        };
+        write_response(&mut stream, status, &body)
+    }
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &serde_json::Value) -> Result<()> {
+    let body = serde_json::to_string_pretty(body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// One line of at most [`MAX_LINE`] bytes into `line`; longer lines fail
+/// instead of growing the buffer.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    anyhow::ensure!(read <= MAX_LINE, "Request line or header longer than {} bytes", MAX_LINE);
+    Ok(read)
+}
+
+/// `requested` under the server's `out_dir`; absolute paths and `..` are
+/// refused so a job can't write elsewhere.
+fn job_out_dir(out_dir: &Path, requested: &Path) -> Result<PathBuf> {
+    let mut resolved = out_dir.to_path_buf();
+    for component in requested.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => anyhow::bail!(
+                "Invalid job: out_dir {} must be a relative path under the server's output directory, without ..",
+                requested.display()
+            ),
+        }
+    }
+    Ok(resolved)
+}
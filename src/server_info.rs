@@ -0,0 +1,122 @@
+//! Server version detection and the features it gates
+//!
+//! The first connection reads the engine version from
+//! `RDB$GET_CONTEXT('SYSTEM', 'ENGINE_VERSION')`, and every connection is
+//! then set up for it. On Firebird 4+, `SET BIND` has the server send INT128
+//! and DECFLOAT values as text and TIME ZONE types as their zoneless
+//! counterparts, which the driver can read. Options needing a newer server,
+//! like `query_timeout`, fail with the version they need rather than with
+//! the server's syntax error.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use rsfbclient::{Execute, Queryable, SimpleConnection};
+use serde::Serialize;
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+
+/// The Firebird server an extractor talks to.
+#[derive(Clone, Debug, Serialize)]
+pub struct ServerInfo {
+    /// As reported, e.g. `4.0.2`
+    pub version: String,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+/// A capability some Firebird versions lack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServerFeature {
+    /// The INT128 type
+    Int128,
+    /// DECFLOAT(16) and DECFLOAT(34)
+    DecFloat,
+    /// TIME and TIMESTAMP WITH TIME ZONE
+    TimeZones,
+    /// `SET STATEMENT TIMEOUT`, behind `query_timeout`
+    StatementTimeout,
+    /// Cursors that move backwards or to a position
+    ScrollableCursors,
+}
+
+impl ServerFeature {
+    /// The first `(major, minor)` version with the feature.
+    pub fn since(self) -> (u32, u32) {
+        match self {
+            Self::Int128 | Self::DecFloat | Self::TimeZones | Self::StatementTimeout => (4, 0),
+            Self::ScrollableCursors => (5, 0),
+        }
+    }
+}
+
+impl fmt::Display for ServerFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Int128 => "INT128",
+            Self::DecFloat => "DECFLOAT",
+            Self::TimeZones => "TIME ZONE types",
+            Self::StatementTimeout => "statement timeouts",
+            Self::ScrollableCursors => "scrollable cursors",
+        })
+    }
+}
+
+impl ServerInfo {
+    /// Read the engine version over `conn`.
+    pub(crate) fn query(conn: &mut SimpleConnection) -> Result<Self> {
+        let sql = "SELECT RDB$GET_CONTEXT('SYSTEM', 'ENGINE_VERSION') FROM rdb$database";
+        let rows: Vec<(String,)> = conn.query(sql, ()).context("Failed to read the server version")?;
+        let version = rows.into_iter().next().map(|r| r.0.trim().to_string()).unwrap_or_default();
+        let mut parts = version.split('.').map(|p| p.parse::<u32>().unwrap_or(0));
+        let (major, minor, patch) = (parts.next().unwrap_or(0), parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+        Ok(Self { version, major, minor, patch })
+    }
+
+    pub fn supports(&self, feature: ServerFeature) -> bool {
+        (self.major, self.minor) >= feature.since()
+    }
+
+    /// Fail unless the server has `feature`; `option` names what asked for it.
+    pub(crate) fn require(&self, feature: ServerFeature, option: &str) -> Result<()> {
+        if self.supports(feature) {
+            return Ok(());
+        }
+        let (major, minor) = feature.since();
+        let e = anyhow::anyhow!(
+            "{} needs Firebird {}.{}+ for {}; the server is Firebird {}",
+            option,
+            major,
+            minor,
+            feature,
+            self.version
+        );
+        Err(ExtractError::tag(ExtractError::Validation, e))
+    }
+
+    /// Set up a new connection to this server so the driver can read every type.
+    pub(crate) fn prepare(&self, conn: &mut SimpleConnection) -> Result<()> {
+        if self.supports(ServerFeature::Int128) {
+            for sql in
+                ["SET BIND OF INT128 TO VARCHAR", "SET BIND OF DECFLOAT TO VARCHAR", "SET BIND OF TIME ZONE TO LEGACY"]
+            {
+                conn.execute(sql, ()).with_context(|| format!("Failed to run {}", sql))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Extractor {
+    /// The server's version, read when the first connection was opened
+    /// (which this opens if none was yet).
+    pub fn server_info(&self) -> Result<ServerInfo, ExtractError> {
+        let read = || -> Result<ServerInfo> {
+            drop(self.pool.acquire()?);
+            Ok(self.pool.server_info().expect("read on connect").clone())
+        };
+        read().map_err(|e| ExtractError::classify(e, ExtractError::Connection))
+    }
+}
@@ -0,0 +1,160 @@
+//! Blob values written to sidecar files instead of the Parquet column
+//!
+//! Under `blob_mode = external`, each non-null blob goes to its own file,
+//! `out_dir/blobs/<table>/<column>/<key>.<ext>`, named by the row's primary
+//! key (the SHA-256 of the value when the key isn't extracted; composite
+//! keys and keys with characters unfit for a file name get a hash suffix),
+//! and the column becomes a struct of the file's path relative to `out_dir`,
+//! its size and its SHA-256. Multi-megabyte blobs then stay out of row groups,
+//! and scans that don't need them read a few bytes per row. The extension is
+//! `txt` for text blobs and guessed from the first bytes for binary ones.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{ArrayRef, Int64Builder, StringBuilder, StructArray},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Fields},
+};
+use sha2::{Digest, Sha256};
+
+use crate::extractor::{output_stem, ColumnMetadata};
+use crate::source::{Row, Value};
+
+/// Where a table's blob files go.
+#[derive(Clone, Debug)]
+pub(crate) struct BlobFiles {
+    /// `out_dir/blobs/<table>`
+    pub(crate) dir: PathBuf,
+    /// `dir` relative to `out_dir`, with `/` separators
+    pub(crate) relative: String,
+    /// Positions of the PK columns among the extracted columns; empty
+    /// without a PK or when a key column isn't extracted
+    pub(crate) key_columns: Vec<usize>,
+}
+
+impl BlobFiles {
+    pub(crate) fn new(out_dir: &Path, table: &str, key_columns: Vec<usize>) -> Self {
+        let relative = format!("blobs/{}", output_stem(table));
+        Self { dir: out_dir.join(&relative), relative, key_columns }
+    }
+}
+
+/// The type of an externalized blob column.
+pub(crate) fn sidecar_type() -> DataType {
+    DataType::Struct(sidecar_fields())
+}
+
+fn sidecar_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("size", DataType::Int64, false),
+        Field::new("sha256", DataType::Utf8, false),
+    ])
+}
+
+/// Write the blobs of column `col_index` of `rows` to their files and return
+/// the column of references to them.
+pub(crate) fn externalize(
+    files: &BlobFiles,
+    column: &ColumnMetadata,
+    rows: &[Row],
+    col_index: usize,
+) -> Result<ArrayRef> {
+    let column_dir = output_stem(&column.output_name);
+    let dir = files.dir.join(&column_dir);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut paths = StringBuilder::with_capacity(rows.len(), rows.len() * 48);
+    let mut sizes = Int64Builder::with_capacity(rows.len());
+    let mut sums = StringBuilder::with_capacity(rows.len(), rows.len() * 64);
+    let mut valid = Vec::with_capacity(rows.len());
+    for row in rows {
+        let bytes: &[u8] = match row.get(col_index) {
+            Some(Value::Binary(b)) => b,
+            Some(Value::Text(t)) => t.as_bytes(),
+            _ => {
+                // Placeholders under a null struct entry
+                paths.append_value("");
+                sizes.append_value(0);
+                sums.append_value("");
+                valid.push(false);
+                continue;
+            }
+        };
+        let sum = hex(&Sha256::digest(bytes));
+        let name = match key(row, &files.key_columns) {
+            Some(key) => key,
+            None => sum.clone(),
+        };
+        let file_name = format!("{}.{}", name, extension(column, bytes));
+        let path = dir.join(&file_name);
+        fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+        paths.append_value(format!("{}/{}/{}", files.relative, column_dir, file_name));
+        sizes.append_value(bytes.len() as i64);
+        sums.append_value(&sum);
+        valid.push(true);
+    }
+    let children: Vec<ArrayRef> = vec![Arc::new(paths.finish()), Arc::new(sizes.finish()), Arc::new(sums.finish())];
+    Ok(Arc::new(StructArray::try_new(sidecar_fields(), children, Some(NullBuffer::from(valid)))?))
+}
+
+/// A file name for the row's primary key, or `None` without one.
+fn key(row: &Row, key_columns: &[usize]) -> Option<String> {
+    if key_columns.is_empty() {
+        return None;
+    }
+    let mut parts = Vec::with_capacity(key_columns.len());
+    for &index in key_columns {
+        parts.push(match row.get(index) {
+            Some(Value::Integer(v)) => v.to_string(),
+            Some(Value::Text(t)) => t.trim().to_string(),
+            Some(Value::Floating(v)) => v.to_string(),
+            _ => return None,
+        });
+    }
+    let raw = parts.join("_");
+    let safe: String =
+        raw.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' }).collect();
+    // Keys that needed replacing get a hash, so `a/b` and `a_b` stay apart,
+    // and so do composite ones, where (`a_b`, `c`) and (`a`, `b_c`) join alike
+    Some(match parts.len() == 1 && safe == raw && !raw.starts_with('.') {
+        true => safe,
+        false => format!("{}-{}", safe, &hex(&key_digest(&parts))[..16]),
+    })
+}
+
+/// SHA-256 of the key parts, each prefixed with its length so no two keys
+/// encode alike.
+fn key_digest(parts: &[String]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.finalize().to_vec()
+}
+
+fn extension(column: &ColumnMetadata, bytes: &[u8]) -> &'static str {
+    if column.is_text_blob {
+        return "txt";
+    }
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG", "png"),
+        (b"\xFF\xD8\xFF", "jpg"),
+        (b"GIF8", "gif"),
+        (b"%PDF", "pdf"),
+        (b"PK\x03\x04", "zip"),
+        (b"\x1F\x8B", "gz"),
+    ];
+    MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)).map_or("bin", |&(_, ext)| ext)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
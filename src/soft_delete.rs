@@ -0,0 +1,98 @@
+//! Soft deletes: rows a flag or timestamp column marks as deleted
+//!
+//! A table's `soft_delete` override names the column. A row counts as deleted
+//! when a BOOLEAN is true, a number isn't 0, a date or timestamp is set, or
+//! text is `Y`, `YES`, `T`, `TRUE` or `1` in any case. Deleted rows are left
+//! out of the output (`exclude`, the default), kept (`include`), or left out
+//! with their primary key and the column written to
+//! `<output>_deletes.parquet` (`tombstone`) for downstream merges to apply;
+//! `merge_target` applies them itself.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use arrow::record_batch::RecordBatchReader;
+use parquet::arrow::ArrowWriter;
+use tracing::info;
+
+use crate::extractor::{Extractor, TableMetadata};
+use crate::partition::quote_ident;
+use crate::stream::stream_error;
+use crate::types::FieldInfo;
+
+/// Predicate matching the rows `column` of `table` marks as deleted; nulls
+/// never do.
+pub(crate) fn deleted_predicate(table: &str, fields: &[FieldInfo], column: &str) -> Result<String> {
+    let field = fields
+        .iter()
+        .find(|f| f.column.eq_ignore_ascii_case(column))
+        .with_context(|| format!("Soft delete column {} not found in {}", column, table))?;
+    let quoted = quote_ident(&field.column);
+    Ok(match field.field_type {
+        // BOOLEAN
+        23 => format!("COALESCE({}, FALSE)", quoted),
+        // SMALLINT, INTEGER, BIGINT, INT128, FLOAT, DOUBLE
+        7 | 8 | 16 | 26 | 10 | 27 => format!("COALESCE({}, 0) <> 0", quoted),
+        // DATE, TIME, TIMESTAMP, with and without time zone
+        12 | 13 | 35 | 28 | 29 => format!("{} IS NOT NULL", quoted),
+        // CHAR, VARCHAR
+        14 | 37 => format!("COALESCE(UPPER(TRIM({})), '') IN ('Y', 'YES', 'T', 'TRUE', '1')", quoted),
+        other => anyhow::bail!(
+            "Soft delete column {} of {} has Firebird type {}, which can't mark deletions",
+            field.column,
+            table,
+            other
+        ),
+    })
+}
+
+impl Extractor {
+    /// Write the `key` columns and the soft delete column of `meta`'s
+    /// soft-deleted rows to the tombstone file beside `output_path`, with the
+    /// other filters and column overrides applied; returns the file and its
+    /// row count.
+    pub(crate) fn write_tombstones(
+        &self,
+        table_name: &str,
+        meta: &TableMetadata,
+        key: &[String],
+        output_path: &Path,
+    ) -> Result<(PathBuf, usize)> {
+        let deleted = meta.tombstone_filter.as_deref().expect("tombstones enabled");
+        let mut overrides = self.config.table_overrides(table_name).cloned().unwrap_or_default();
+        let soft_delete = overrides.soft_delete.take().expect("soft delete configured");
+        let mut columns = key.to_vec();
+        if !columns.iter().any(|c| c.eq_ignore_ascii_case(&soft_delete.column)) {
+            columns.push(soft_delete.column);
+        }
+        overrides.columns = Some(columns);
+        overrides.filter = Some(match overrides.filter {
+            Some(filter) => format!("({}) AND ({})", filter, deleted),
+            None => deleted.to_string(),
+        });
+        let mut this = self.clone();
+        this.config.set_table_overrides(table_name, overrides);
+        // Transforms expect the full row
+        this.transform = None;
+
+        let stem = output_path.file_stem().unwrap().to_string_lossy();
+        let path = output_path.with_file_name(format!("{}_deletes.parquet", stem));
+        let stream = this.batch_stream(table_name)?;
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let schema = stream.schema();
+        let mut writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(self.output_writer_props(&schema)))?;
+        let mut rows = 0;
+        for batch in stream {
+            let batch = batch.map_err(stream_error)?;
+            rows += batch.num_rows();
+            writer.write(&batch)?;
+        }
+        writer.close()?;
+        info!("  {} soft-deleted rows written to {}", rows, path.display());
+        Ok((path, rows))
+    }
+}
@@ -0,0 +1,385 @@
+//! Where rows come from
+//!
+//! The extraction engine (planning, conversion, writing) reaches the database
+//! through a [`Source`] for table metadata, row counts and row fetching. The
+//! Firebird connection pool is the built-in source; tests can substitute an
+//! in-memory fake and other databases can bring their own.
+//!
+//! Rows come back as the crate's own [`Row`] of [`Value`]s, whatever driver
+//! produced them. Key boundary sampling, blob lookups and table listing go
+//! through a source too; only the `validate` aggregates still query Firebird
+//! directly. Wire encryption and auth plugin checks belong to the Firebird
+//! pool, which runs them on every connection it opens.
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use rsfbclient::{Queryable, SimpleConnection, SqlType};
+
+use crate::cancel::CancellationToken;
+use crate::config::TimestampZone;
+use crate::extractor::{ConnectionPool, PooledConnection, TableSummary};
+use crate::partition::{quote_ident, PartitionSpec};
+use crate::types::FieldInfo;
+
+/// One value of a fetched row.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Floating(f64),
+    Text(String),
+    Binary(Vec<u8>),
+    Boolean(bool),
+    Timestamp(NaiveDateTime),
+}
+
+/// A fetched row: one value per selected column, in [`Select::columns`]
+/// order (then `RDB$DB_KEY`, when selected).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Row {
+    pub values: Vec<Value>,
+}
+
+impl Row {
+    pub fn new(values: Vec<Value>) -> Self {
+        Self { values }
+    }
+
+    /// The value of column `index`; `None` past the end.
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+}
+
+impl From<SqlType> for Value {
+    fn from(value: SqlType) -> Self {
+        match value {
+            SqlType::Null => Self::Null,
+            SqlType::Integer(v) => Self::Integer(v),
+            SqlType::Floating(v) => Self::Floating(v),
+            SqlType::Text(t) => Self::Text(t),
+            SqlType::Binary(b) => Self::Binary(b),
+            SqlType::Boolean(b) => Self::Boolean(b),
+            SqlType::Timestamp(ts) => Self::Timestamp(ts),
+        }
+    }
+}
+
+impl From<rsfbclient::Row> for Row {
+    fn from(row: rsfbclient::Row) -> Self {
+        Self { values: row.cols.into_iter().map(|col| col.value.into()).collect() }
+    }
+}
+
+/// Hands out connections; shared by every worker of an extraction.
+pub trait Source: Send + Sync {
+    fn connect(&self) -> Result<Box<dyn SourceConnection>>;
+
+    /// [`Self::connect`] for fetching rows; a source that may wait for a free
+    /// connection stops waiting once `cancel` fires.
+    fn connect_until(&self, cancel: &CancellationToken) -> Result<Box<dyn SourceConnection>> {
+        let _ = cancel;
+        self.connect()
+    }
+}
+
+/// One connection. Dropping it returns it to its source.
+///
+/// Table and column names are passed exactly as stored, as [`Self::has_table`]
+/// and [`Self::fields`] report them; quoting them in SQL is up to the source.
+pub trait SourceConnection: Send {
+    /// Whether a table or view named exactly `name` exists.
+    fn has_table(&mut self, name: &str) -> Result<bool>;
+
+    /// The table's columns in table order.
+    fn fields(&mut self, table: &str) -> Result<Vec<FieldInfo>>;
+
+    /// Primary key columns in key order; empty without a PK.
+    fn primary_key(&mut self, table: &str) -> Result<Vec<String>>;
+
+    /// `MIN` and `MAX` of an integer column, over the rows matching
+    /// `predicate` (SQL, identifiers quoted) if one is given.
+    fn int_range(&mut self, table: &str, column: &str, predicate: Option<&str>) -> Result<(Option<i64>, Option<i64>)>;
+
+    /// First and last value of an integer key column, for sources that can
+    /// read them off its indices cheaper than `MIN`/`MAX`; [`Self::int_range`]
+    /// by default. Non-key columns always use [`Self::int_range`].
+    fn key_range(&mut self, table: &str, column: &str) -> Result<(Option<i64>, Option<i64>)> {
+        self.int_range(table, column, None)
+    }
+
+    fn count_rows(&mut self, table: &str) -> Result<i64>;
+
+    /// Rows `select` picks, ignoring its columns.
+    fn count_selected(&mut self, select: &Select) -> Result<i64>;
+
+    /// A cheap row count estimate, e.g. from index statistics; `None` when
+    /// there's nothing to estimate from.
+    fn estimate_row_count(&mut self, table: &str) -> Result<Option<i64>>;
+
+    /// User tables, not views or system tables, ordered by name.
+    fn list_tables(&mut self) -> Result<Vec<TableSummary>>;
+
+    /// User views, ordered by name.
+    fn list_views(&mut self) -> Result<Vec<String>>;
+
+    /// Stream the rows `select` picks to `on_row`, in any order, stopping
+    /// early once it returns `Ok(false)`. Values must come in `select.columns`
+    /// order.
+    fn fetch(&mut self, select: &Select, on_row: &mut dyn FnMut(Row) -> Result<bool>) -> Result<()>;
+
+    /// Run the calls that follow in one transaction, and so at concurrency
+    /// isolation against one snapshot, until [`Self::end_transaction`].
+    /// Does nothing by default, for sources without transactions.
+    fn begin_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// End the transaction [`Self::begin_transaction`] started.
+    fn end_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Every row `select` picks, collected.
+    fn fetch_all(&mut self, select: &Select) -> Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        self.fetch(select, &mut |row| {
+            rows.push(row);
+            Ok(true)
+        })?;
+        Ok(rows)
+    }
+}
+
+/// The rows of one partition of a table.
+#[derive(Clone, Debug)]
+pub struct Select {
+    pub table: String,
+    /// Column names in output order
+    pub columns: Vec<String>,
+    /// Select `RDB$DB_KEY` after the columns, for blob workers to fetch the
+    /// blob columns left out of them by key
+    pub db_key: bool,
+    /// Predicate and `ROWS` window, with the table's configured filter folded in
+    pub partition: PartitionSpec,
+    /// Sort columns; empty (no ORDER BY) unless extracting deterministically or
+    /// reading a `ROWS` window, which is ordered by the primary key or by
+    /// `RDB$DB_KEY`, Firebird's physical record address
+    pub order_by: Vec<String>,
+}
+
+impl Select {
+    /// The Firebird `SELECT` statement, identifiers quoted.
+    pub fn sql(&self) -> String {
+        let mut columns: Vec<String> = self.columns.iter().map(|c| quote_ident(c)).collect();
+        if self.db_key {
+            columns.push("RDB$DB_KEY".to_string());
+        }
+        self.partition.ordered_select_sql(&columns.join(", "), &self.table, &self.order_by)
+    }
+}
+
+impl Source for ConnectionPool {
+    fn connect(&self) -> Result<Box<dyn SourceConnection>> {
+        Ok(Box::new(self.acquire()?))
+    }
+
+    fn connect_until(&self, cancel: &CancellationToken) -> Result<Box<dyn SourceConnection>> {
+        Ok(Box::new(self.acquire_until(cancel)?))
+    }
+}
+
+impl SourceConnection for PooledConnection {
+    fn has_table(&mut self, name: &str) -> Result<bool> {
+        let sql = "SELECT COUNT(*) FROM rdb$relations WHERE rdb$relation_name = ?";
+        let counts: Vec<(i64,)> = self.query(sql, (name.to_string(),))?;
+        Ok(counts.first().map_or(false, |c| c.0 > 0))
+    }
+
+    fn fields(&mut self, table: &str) -> Result<Vec<FieldInfo>> {
+        // Get field names first
+        let name_sql = r#"
+            SELECT rdb$field_name
+            FROM rdb$relation_fields
+            WHERE rdb$relation_name = ?
+            ORDER BY rdb$field_position
+        "#;
+        let field_names: Vec<(String,)> = self.query(name_sql, (table.to_string(),))?;
+
+        // For each field, get its type from rdb$fields
+        let type_sql = r#"
+            SELECT f.rdb$field_type, COALESCE(f.rdb$field_sub_type, 0), COALESCE(f.rdb$field_scale, 0),
+                TRIM(rf.rdb$field_source)
+            FROM rdb$fields f
+            INNER JOIN rdb$relation_fields rf ON f.rdb$field_name = rf.rdb$field_source
+            WHERE rf.rdb$relation_name = ? AND rf.rdb$field_name = ?
+        "#;
+        let mut fields = Vec::with_capacity(field_names.len());
+        for (field_name,) in field_names {
+            let column = field_name.trim().to_string();
+            let types: Vec<(i16, i16, i16, String)> =
+                self.query(type_sql, (table.to_string(), column.clone()))?;
+            let (field_type, sub_type, scale, domain) =
+                types.into_iter().next().unwrap_or((37, 0, 0, String::new())); // Default to VARCHAR
+            fields.push(FieldInfo {
+                table: table.to_string(),
+                column,
+                field_type,
+                sub_type,
+                scale,
+                domain,
+                timestamp_zone: TimestampZone::Naive,
+            });
+        }
+        Ok(fields)
+    }
+
+    fn primary_key(&mut self, table: &str) -> Result<Vec<String>> {
+        // Find PK index
+        let sql = r#"
+            SELECT ri.rdb$index_name
+            FROM rdb$indices ri
+            WHERE ri.rdb$relation_name = ? 
+            AND ri.rdb$index_type = 1
+        "#;
+        let indices: Vec<(String,)> = self.query(sql, (table.to_string(),))?;
+        let pk_index_name = match indices.first() {
+            Some((idx,)) => idx.trim().to_string(),
+            None => return Ok(Vec::new()),
+        };
+
+        // Get PK columns
+        let col_sql = r#"
+            SELECT seg.rdb$field_name
+            FROM rdb$index_segments seg
+            WHERE seg.rdb$index_name = ?
+            ORDER BY seg.rdb$field_position
+        "#;
+        let pk_cols: Vec<(String,)> = self.query(col_sql, (pk_index_name,))?;
+        Ok(pk_cols.iter().map(|(c,)| c.trim().to_string()).collect())
+    }
+
+    fn int_range(&mut self, table: &str, column: &str, predicate: Option<&str>) -> Result<(Option<i64>, Option<i64>)> {
+        let column = quote_ident(column);
+        let mut stats_sql = format!("SELECT MIN({}), MAX({}) FROM {}", column, column, quote_ident(table));
+        if let Some(predicate) = predicate {
+            stats_sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        let stats: Vec<(Option<i64>, Option<i64>)> = self.query(&stats_sql, ())?;
+        Ok(stats.first().copied().unwrap_or((None, None)))
+    }
+
+    /// `ORDER BY key ROWS 1` each way when active ascending and descending
+    /// indices both lead with the column, so either end is one index
+    /// navigation. Otherwise `MIN`/`MAX`: an ordered read without the
+    /// descending index would sort the table where `MAX` only scans it.
+    fn key_range(&mut self, table: &str, column: &str) -> Result<(Option<i64>, Option<i64>)> {
+        let index_sql = r#"
+            SELECT DISTINCT COALESCE(i.rdb$index_type, 0)
+            FROM rdb$indices i
+            JOIN rdb$index_segments s ON s.rdb$index_name = i.rdb$index_name
+            WHERE i.rdb$relation_name = ? AND s.rdb$field_name = ? AND s.rdb$field_position = 0
+            AND COALESCE(i.rdb$index_inactive, 0) = 0
+        "#;
+        let directions: Vec<(i16,)> = self.query(index_sql, (table.to_string(), column.to_string()))?;
+        if !(directions.contains(&(0,)) && directions.contains(&(1,))) {
+            return self.int_range(table, column, None);
+        }
+
+        let (table, column) = (quote_ident(table), quote_ident(column));
+        let mut end = |direction: &str| -> Result<Option<i64>> {
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {} IS NOT NULL ORDER BY {} {} ROWS 1",
+                column, table, column, column, direction
+            );
+            let keys: Vec<(i64,)> = self.query(&sql, ())?;
+            Ok(keys.first().map(|k| k.0))
+        };
+        Ok((end("ASC")?, end("DESC")?))
+    }
+
+    fn count_rows(&mut self, table: &str) -> Result<i64> {
+        let count_sql = format!("SELECT COUNT(*) FROM {}", quote_ident(table));
+        let counts: Vec<(i64,)> = self.query(&count_sql, ())?;
+        Ok(counts.first().map(|c| c.0).unwrap_or(0))
+    }
+
+    fn count_selected(&mut self, select: &Select) -> Result<i64> {
+        let counts: Vec<(i64,)> = self.query(&select.partition.select_sql("COUNT(*)", &select.table), ())?;
+        Ok(counts.first().map(|c| c.0).unwrap_or(0))
+    }
+
+    /// Row count implied by the PK index selectivity (1 / distinct keys).
+    /// `None` when there is no PK or its statistics were never computed.
+    fn estimate_row_count(&mut self, table: &str) -> Result<Option<i64>> {
+        let sql = r#"
+            SELECT ri.rdb$statistics
+            FROM rdb$relation_constraints rc
+            INNER JOIN rdb$indices ri ON ri.rdb$index_name = rc.rdb$index_name
+            WHERE rc.rdb$relation_name = ?
+            AND rc.rdb$constraint_type = 'PRIMARY KEY'
+        "#;
+        let stats: Vec<(Option<f64>,)> = self.query(sql, (table.to_string(),))?;
+        Ok(stats
+            .first()
+            .and_then(|s| s.0)
+            .filter(|selectivity| *selectivity > 0.0)
+            .map(|selectivity| (1.0 / selectivity).round() as i64))
+    }
+
+    fn list_tables(&mut self) -> Result<Vec<TableSummary>> {
+        let sql = r#"
+            SELECT TRIM(r.rdb$relation_name), MAX(ri.rdb$statistics), COUNT(rc.rdb$constraint_name)
+            FROM rdb$relations r
+            LEFT JOIN rdb$relation_constraints rc
+                ON rc.rdb$relation_name = r.rdb$relation_name
+                AND rc.rdb$constraint_type = 'PRIMARY KEY'
+            LEFT JOIN rdb$indices ri ON ri.rdb$index_name = rc.rdb$index_name
+            WHERE COALESCE(r.rdb$system_flag, 0) = 0
+            AND r.rdb$view_blr IS NULL
+            GROUP BY 1
+            ORDER BY 1
+        "#;
+        let rows: Vec<(String, Option<f64>, i64)> = self.query(sql, ())?;
+        Ok(rows
+            .into_iter()
+            .map(|(name, selectivity, pk_constraints)| TableSummary {
+                name,
+                estimated_rows: selectivity.filter(|s| *s > 0.0).map(|s| (1.0 / s).round() as i64),
+                has_pk: pk_constraints > 0,
+            })
+            .collect())
+    }
+
+    fn list_views(&mut self) -> Result<Vec<String>> {
+        let sql = r#"
+            SELECT TRIM(rdb$relation_name)
+            FROM rdb$relations
+            WHERE COALESCE(rdb$system_flag, 0) = 0
+            AND rdb$view_blr IS NOT NULL
+            ORDER BY 1
+        "#;
+        let rows: Vec<(String,)> = self.query(sql, ())?;
+        Ok(rows.into_iter().map(|(name,)| name).collect())
+    }
+
+    fn begin_transaction(&mut self) -> Result<()> {
+        // The trait method would shadow the connection's own
+        Ok(SimpleConnection::begin_transaction(self)?)
+    }
+
+    fn end_transaction(&mut self) -> Result<()> {
+        // Read only, so committing and rolling back are the same
+        Ok(SimpleConnection::commit(self)?)
+    }
+
+    fn fetch(&mut self, select: &Select, on_row: &mut dyn FnMut(Row) -> Result<bool>) -> Result<()> {
+        for row in self.query_iter(&select.sql(), ())? {
+            let row: rsfbclient::Row = row?;
+            if !on_row(row.into())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
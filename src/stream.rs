@@ -0,0 +1,143 @@
+//! Extraction as an in-process stream of record batches
+//!
+//! [`Extractor::batch_stream`] runs the fetch and convert stages of a
+//! sequential extraction on a background thread and hands each batch to the
+//! caller instead of a Parquet writer, so nothing is written to disk.
+
+use std::{sync::Arc, thread};
+
+use anyhow::Result;
+use arrow::{
+    datatypes::SchemaRef,
+    error::ArrowError,
+    record_batch::{RecordBatch, RecordBatchReader},
+};
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+use crate::blob::BlobFetcher;
+use crate::cancel::CancellationToken;
+use crate::error::ExtractError;
+use crate::extractor::{build_arrow_batch, Extractor, TableMetadata};
+use crate::memory::{estimate_rows_bytes, BatchSizer};
+use crate::partition::PartitionSpec;
+use crate::source::{Row, Source};
+use crate::transform::{self, BatchTransform};
+
+/// Batches of one table, in cursor order. Implements arrow's
+/// [`RecordBatchReader`]; a failure ends the stream with an
+/// [`ArrowError::ExternalError`] wrapping the [`ExtractError`].
+///
+/// Dropping the stream stops the fetch thread at its next batch and returns
+/// its connection to the pool.
+pub struct BatchStream {
+    schema: SchemaRef,
+    batches: Receiver<Result<RecordBatch, ArrowError>>,
+}
+
+impl Iterator for BatchStream {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.batches.recv().ok()
+    }
+}
+
+impl RecordBatchReader for BatchStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+impl Extractor {
+    /// Stream a table's rows as Arrow record batches instead of writing them
+    /// out. Metadata is loaded up front, so an unknown table fails here; the
+    /// query itself runs as the stream is consumed, at most `prefetch_depth`
+    /// (default 2) batches ahead of the caller.
+    pub fn batch_stream(&self, table_name: &str) -> Result<BatchStream, ExtractError> {
+        let this = self.for_table(table_name);
+        let meta = this
+            .load_metadata(table_name)
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))?;
+        let schema = this
+            .output_schema(&meta)
+            .map_err(|e| ExtractError::classify(e, ExtractError::Metadata))?;
+        let (tx, batches) = bounded(this.config.prefetch_depth.unwrap_or(2));
+
+        let source = Arc::clone(&this.source);
+        let sizer = this.batch_sizer(&meta);
+        let blobs = this.blob_fetcher(&meta).map_err(|e| ExtractError::classify(e, ExtractError::Connection))?;
+        let cancel = this.cancel.clone();
+        let transform = this.transform.clone();
+        thread::spawn(move || {
+            if let Err(e) = stream_rows(&*source, &meta, blobs.as_ref(), transform.as_ref(), sizer, &cancel, &tx) {
+                let e = ExtractError::classify(e, ExtractError::PartitionFailed);
+                let _ = tx.send(Err(ArrowError::ExternalError(Box::new(e))));
+            }
+        });
+        Ok(BatchStream { schema, batches })
+    }
+}
+
+/// Unwrap the [`ExtractError`] a [`BatchStream`] failure carries.
+pub(crate) fn stream_error(e: ArrowError) -> ExtractError {
+    match e {
+        ArrowError::ExternalError(inner) => match inner.downcast::<ExtractError>() {
+            Ok(e) => *e,
+            Err(other) => ExtractError::PartitionFailed(anyhow::anyhow!(other)),
+        },
+        other => ExtractError::PartitionFailed(other.into()),
+    }
+}
+
+/// Fetch the whole table in batch-sized chunks and send them converted and
+/// transformed, until done or the receiver is gone.
+fn stream_rows(
+    source: &dyn Source,
+    meta: &TableMetadata,
+    blobs: Option<&BlobFetcher>,
+    transform: Option<&BatchTransform>,
+    mut sizer: BatchSizer,
+    cancel: &CancellationToken,
+    tx: &Sender<Result<RecordBatch, ArrowError>>,
+) -> Result<()> {
+    let mut conn = source.connect_until(cancel)?;
+    let select = meta.select(blobs.is_some(), &PartitionSpec::full());
+
+    let mut chunk: Vec<Row> = Vec::with_capacity(sizer.rows());
+    let mut receiver_gone = false;
+    conn.fetch(&select, &mut |row| {
+        cancel.check()?;
+        chunk.push(row);
+        if chunk.len() >= sizer.rows() {
+            let full = std::mem::replace(&mut chunk, Vec::with_capacity(sizer.rows()));
+            if let Some(batch) = convert(meta, blobs, transform, full, &mut sizer)? {
+                receiver_gone = tx.send(Ok(batch)).is_err();
+            }
+        }
+        Ok(!receiver_gone)
+    })?;
+    if receiver_gone {
+        return Ok(());
+    }
+    if !chunk.is_empty() {
+        if let Some(batch) = convert(meta, blobs, transform, chunk, &mut sizer)? {
+            let _ = tx.send(Ok(batch));
+        }
+    }
+    Ok(())
+}
+
+fn convert(
+    meta: &TableMetadata,
+    blobs: Option<&BlobFetcher>,
+    transform: Option<&BatchTransform>,
+    rows: Vec<Row>,
+    sizer: &mut BatchSizer,
+) -> Result<Option<RecordBatch>> {
+    let rows = match blobs {
+        Some(blobs) => blobs.complete_rows(meta, rows)?,
+        None => rows,
+    };
+    sizer.observe(rows.len(), estimate_rows_bytes(&rows));
+    transform::apply(transform, build_arrow_batch(meta, &rows)?)
+}
@@ -0,0 +1,202 @@
+//! Referentially consistent subsets of several tables
+//!
+//! Extracting a few related tables with `where` filters for a development
+//! database leaves orders whose customer was filtered out, or customers
+//! without their orders. [`Extractor::subset_plan`] follows the foreign keys
+//! among the extracted tables and rewrites each table's `where` override:
+//!
+//! - [`FollowFks::Children`] keeps only the rows of a table that reference
+//!   kept rows through each foreign key to a filtered table, so orders follow
+//!   their filtered customers;
+//! - [`FollowFks::Parents`] adds to a filtered table the rows its kept
+//!   children reference, and limits an unfiltered table with filtered
+//!   children to the rows they reference;
+//! - [`FollowFks::Both`] does the first, then the second.
+//!
+//! The predicates are `EXISTS` subqueries nesting the other tables' ones, so
+//! they grow with the depth of the key graph. Self-references and keys
+//! closing a cycle aren't followed. The plan lists children before their
+//! parents: a parent can't be deleted while rows reference it, so a parent
+//! read after its children still has every row they reference.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use tracing::{info, warn};
+
+use crate::error::ExtractError;
+use crate::extractor::Extractor;
+use crate::orchestrate::TableSpec;
+use crate::partition::quote_ident;
+
+/// Which way [`Extractor::subset_plan`] follows foreign keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowFks {
+    /// From filtered tables to the tables referencing them
+    Children,
+    /// From kept rows to the rows they reference
+    Parents,
+    Both,
+}
+
+impl fmt::Display for FollowFks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Children => "children",
+            Self::Parents => "parents",
+            Self::Both => "both",
+        })
+    }
+}
+
+impl FromStr for FollowFks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "children" => Ok(Self::Children),
+            "parents" => Ok(Self::Parents),
+            "both" => Ok(Self::Both),
+            other => Err(format!("unknown foreign key direction '{}' (expected children, parents or both)", other)),
+        }
+    }
+}
+
+/// A foreign key between two of the planned tables, by their positions.
+struct Edge {
+    child: usize,
+    parent: usize,
+    columns: Vec<String>,
+    references: Vec<String>,
+}
+
+impl Edge {
+    /// `EXISTS` over the table at `from`, one end of the key, matching the
+    /// rows of the other end, which the subquery is nested in.
+    fn exists(&self, names: &[String], from: usize, filter: Option<&str>) -> String {
+        let (child, parent) = (quote_ident(&names[self.child]), quote_ident(&names[self.parent]));
+        let mut conditions: Vec<String> = self
+            .columns
+            .iter()
+            .zip(&self.references)
+            .map(|(c, r)| format!("{}.{} = {}.{}", child, quote_ident(c), parent, quote_ident(r)))
+            .collect();
+        conditions.extend(filter.map(|f| format!("({})", f)));
+        format!("EXISTS (SELECT 1 FROM {} WHERE {})", quote_ident(&names[from]), conditions.join(" AND "))
+    }
+}
+
+impl Extractor {
+    /// An [`Extractor::extract_many`] plan for `tables` whose `where`
+    /// overrides follow the foreign keys among them as `follow` says, so the
+    /// extracted rows form a referentially consistent subset.
+    pub fn subset_plan(&self, tables: &[String], follow: FollowFks) -> Result<Vec<TableSpec>, ExtractError> {
+        let mut names = Vec::with_capacity(tables.len());
+        let mut foreign_keys = Vec::with_capacity(tables.len());
+        for table in tables {
+            let ddl = self.table_ddl(table)?;
+            names.push(ddl.table);
+            foreign_keys.push(ddl.foreign_keys);
+        }
+        let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (n.as_str(), i)).collect();
+        let mut edges = Vec::new();
+        for (child, keys) in foreign_keys.iter().enumerate() {
+            for fk in keys {
+                match index.get(fk.references_table.as_str()) {
+                    Some(&parent) if parent == child => {
+                        warn!("  ⚠ {}: self-reference {} not followed", names[child], fk.name);
+                    }
+                    Some(&parent) => edges.push(Edge {
+                        child,
+                        parent,
+                        columns: fk.columns.clone(),
+                        references: fk.references_columns.clone(),
+                    }),
+                    None => {}
+                }
+            }
+        }
+        let order = parents_first(&names, &mut edges);
+
+        let overrides: Vec<_> =
+            tables.iter().map(|t| self.config.table_overrides(t).cloned().unwrap_or_default()).collect();
+        // The `where` overrides, rewritten below; the row filters still apply
+        let mut filters: Vec<Option<String>> = overrides.iter().map(|o| o.filter.clone()).collect();
+        // Everything selecting a table's rows, for nesting into other tables'
+        let full = |filters: &[Option<String>], i: usize| -> Option<String> {
+            let parts: Vec<&String> = overrides[i].row_filter.iter().chain(&filters[i]).collect();
+            match parts.len() {
+                0 => None,
+                1 => Some(parts[0].clone()),
+                _ => Some(format!("({}) AND ({})", parts[0], parts[1])),
+            }
+        };
+
+        if matches!(follow, FollowFks::Children | FollowFks::Both) {
+            for &t in &order {
+                let mut parts: Vec<String> = filters[t].iter().map(|f| format!("({})", f)).collect();
+                for edge in edges.iter().filter(|e| e.child == t) {
+                    if let Some(parent) = full(&filters, edge.parent) {
+                        parts.push(edge.exists(&names, edge.parent, Some(&parent)));
+                    }
+                }
+                if parts.len() > filters[t].iter().count() {
+                    filters[t] = Some(parts.join(" AND "));
+                }
+            }
+        }
+        if matches!(follow, FollowFks::Parents | FollowFks::Both) {
+            for &t in order.iter().rev() {
+                let children: Vec<&Edge> = edges.iter().filter(|e| e.parent == t).collect();
+                let filtered = children.iter().any(|e| full(&filters, e.child).is_some());
+                if children.is_empty() || (filters[t].is_none() && !filtered) {
+                    continue;
+                }
+                let mut parts: Vec<String> = filters[t].iter().map(|f| format!("({})", f)).collect();
+                for edge in children {
+                    parts.push(edge.exists(&names, edge.child, full(&filters, edge.child).as_deref()));
+                }
+                filters[t] = Some(parts.join(" OR "));
+            }
+        }
+
+        let mut plan = Vec::with_capacity(tables.len());
+        for &t in order.iter().rev() {
+            let mut table_overrides = overrides[t].clone();
+            if table_overrides.filter != filters[t] {
+                info!("{}: WHERE {}", tables[t], filters[t].as_deref().unwrap_or_default());
+                table_overrides.filter = filters[t].take();
+            }
+            plan.push(TableSpec::new(tables[t].clone()).with_overrides(table_overrides));
+        }
+        Ok(plan)
+    }
+}
+
+/// The tables' positions with every parent before its children, in the given
+/// order where keys allow. Keys closing a cycle are dropped from `edges`.
+fn parents_first(names: &[String], edges: &mut Vec<Edge>) -> Vec<usize> {
+    let mut order = Vec::with_capacity(names.len());
+    let mut placed = HashSet::new();
+    while order.len() < names.len() {
+        let ready = (0..names.len()).find(|&t| {
+            !placed.contains(&t) && edges.iter().all(|e| e.child != t || placed.contains(&e.parent))
+        });
+        // In a cycle, the first table left goes next without its parents
+        let next = ready.unwrap_or_else(|| (0..names.len()).find(|t| !placed.contains(t)).expect("tables left"));
+        placed.insert(next);
+        order.push(next);
+    }
+    let position: HashMap<usize, usize> = order.iter().enumerate().map(|(p, &t)| (t, p)).collect();
+    edges.retain(|e| {
+        let kept = position[&e.parent] < position[&e.child];
+        if !kept {
+            warn!("  ⚠ {} → {}: foreign key closes a cycle; not followed", names[e.child], names[e.parent]);
+        }
+        kept
+    });
+    order
+}
@@ -0,0 +1,203 @@
+//! Integration-test fixtures: a Firebird server in a container
+//!
+//! Behind the `test-support` feature. [`FirebirdContainer::start`] runs the
+//! official `firebirdsql/firebird` image through testcontainers (a Docker
+//! daemon must be reachable) with an empty database, and waits until it
+//! accepts connections. [`FirebirdContainer::config`] points an
+//! [`ExtractorConfig`] at it using the pure-Rust backend, so no Firebird
+//! client library is needed on the test machine; [`FirebirdContainer::execute`]
+//! creates and fills tables. The `assert_*` and `read_*` helpers check the
+//! Parquet files an extraction wrote. The container is removed on drop.
+//!
+//! ```no_run
+//! use firebird_peregrine_falcon::test_support::{assert_row_count, FirebirdContainer};
+//! use firebird_peregrine_falcon::Extractor;
+//!
+//! let fb = FirebirdContainer::start()?;
+//! fb.execute("CREATE TABLE T (ID INTEGER NOT NULL PRIMARY KEY, NAME VARCHAR(20))")?;
+//! fb.execute("INSERT INTO T VALUES (1, 'one')")?;
+//! let out = std::env::temp_dir().join("extract_t");
+//! Extractor::new(fb.config(&out).build()?)?.extract_table("T")?;
+//! assert_row_count(out.join("t.parquet"), 1)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{ArrayRef, Int64Array, StringArray},
+    compute::cast,
+    datatypes::DataType,
+    record_batch::RecordBatch,
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rsfbclient::{Execute, SimpleConnection};
+use testcontainers::{
+    core::{IntoContainerPort, WaitFor},
+    runners::SyncRunner,
+    Container, GenericImage, ImageExt,
+};
+
+use crate::config::{Backend, ExtractorConfig, ExtractorConfigBuilder};
+use crate::extractor::ConnectionPool;
+use crate::partition::quote_ident;
+
+/// Default image tag of [`FirebirdContainer::start`].
+pub const DEFAULT_TAG: &str = "5";
+const PASSWORD: &str = "masterkey";
+const DATABASE: &str = "/var/lib/firebird/data/test.fdb";
+/// How long the server may take to accept connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A running Firebird server with one empty database, `SYSDBA`/`masterkey`.
+pub struct FirebirdContainer {
+    container: Container<GenericImage>,
+    pub host: String,
+    pub port: u16,
+    /// Path of the database on the server
+    pub database: String,
+}
+
+impl FirebirdContainer {
+    /// Start `firebirdsql/firebird:5`.
+    pub fn start() -> Result<Self> {
+        Self::start_tag(DEFAULT_TAG)
+    }
+
+    /// Start `firebirdsql/firebird:<tag>`, e.g. `4` or `5.0.1`.
+    pub fn start_tag(tag: &str) -> Result<Self> {
+        let container = GenericImage::new("firebirdsql/firebird", tag)
+            .with_exposed_port(3050.tcp())
+            .with_wait_for(WaitFor::Nothing)
+            .with_env_var("FIREBIRD_ROOT_PASSWORD", PASSWORD)
+            .with_env_var("FIREBIRD_DATABASE", "test.fdb")
+            .start()
+            .context("Failed to start the Firebird container")?;
+        let host = container.get_host()?.to_string();
+        let port = container.get_host_port_ipv4(3050.tcp())?;
+        let fb = Self { container, host, port, database: DATABASE.to_string() };
+        fb.wait_until_ready()?;
+        Ok(fb)
+    }
+
+    /// The server logs readiness inconsistently across versions, so poll.
+    fn wait_until_ready(&self) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            match self.connect() {
+                Ok(_) => return Ok(()),
+                Err(e) if start.elapsed() > STARTUP_TIMEOUT => {
+                    return Err(e.context(format!("Firebird in {} never accepted a connection", self.container.id())))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(500)),
+            }
+        }
+    }
+
+    /// A config builder for this server writing to `out_dir`, on the pure-Rust
+    /// backend with a small pool; adjust it and `build()`.
+    pub fn config(&self, out_dir: impl Into<PathBuf>) -> ExtractorConfigBuilder {
+        ExtractorConfig::builder(self.database.clone(), out_dir)
+            .host(self.host.clone())
+            .port(self.port)
+            .user("SYSDBA")
+            .password(PASSWORD)
+            .backend(Backend::PureRust)
+            .parallelism(2)
+    }
+
+    fn connect(&self) -> Result<SimpleConnection> {
+        let config = self.config(std::env::temp_dir()).build()?;
+        ConnectionPool::connect_read_write(&config)
+    }
+
+    /// Run `sql` (DDL or DML) and commit it.
+    pub fn execute(&self, sql: &str) -> Result<()> {
+        let mut conn = self.connect()?;
+        conn.execute(sql, ()).with_context(|| format!("`{}` failed", sql))?;
+        Ok(())
+    }
+
+    /// Run `statements` in order, each committed on its own.
+    pub fn execute_all(&self, statements: &[&str]) -> Result<()> {
+        let mut conn = self.connect()?;
+        for sql in statements {
+            conn.execute(sql, ()).with_context(|| format!("`{}` failed", sql))?;
+        }
+        Ok(())
+    }
+
+    /// Create `table` with `columns` (e.g. `ID INTEGER NOT NULL PRIMARY KEY,
+    /// NAME VARCHAR(20)`) and insert `rows` rows numbered from 1 with
+    /// `values`, an expression list over the loop variable `:i`. The name is
+    /// quoted, so it's stored exactly as given (`ORDERS`, `Order Lines`).
+    pub fn create_table(&self, table: &str, columns: &str, rows: u64, values: &str) -> Result<()> {
+        let table = quote_ident(table);
+        self.execute(&format!("CREATE TABLE {} ({})", table, columns))?;
+        if rows > 0 {
+            self.execute(&format!(
+                "EXECUTE BLOCK AS DECLARE i BIGINT = 1; BEGIN WHILE (i <= {}) DO BEGIN \
+                 INSERT INTO {} VALUES ({}); i = i + 1; END END",
+                rows, table, values
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// Every batch of the Parquet file at `path`.
+pub fn read_parquet(path: impl AsRef<Path>) -> Result<Vec<RecordBatch>> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+    Ok(reader.collect::<Result<_, _>>()?)
+}
+
+/// Column `column` of the file at `path` as integers, in file order.
+pub fn read_i64_column(path: impl AsRef<Path>, column: &str) -> Result<Vec<Option<i64>>> {
+    let mut values = Vec::new();
+    for batch in read_parquet(path)? {
+        let array = cast(column_of(&batch, column)?, &DataType::Int64)?;
+        let array = array.as_any().downcast_ref::<Int64Array>().expect("cast to Int64");
+        values.extend(array.iter());
+    }
+    Ok(values)
+}
+
+/// Column `column` of the file at `path` as text, in file order.
+pub fn read_string_column(path: impl AsRef<Path>, column: &str) -> Result<Vec<Option<String>>> {
+    let mut values = Vec::new();
+    for batch in read_parquet(path)? {
+        let array = cast(column_of(&batch, column)?, &DataType::Utf8)?;
+        let array = array.as_any().downcast_ref::<StringArray>().expect("cast to Utf8");
+        values.extend(array.iter().map(|v| v.map(str::to_string)));
+    }
+    Ok(values)
+}
+
+fn column_of<'a>(batch: &'a RecordBatch, column: &str) -> Result<&'a ArrayRef> {
+    batch.column_by_name(column).with_context(|| format!("No column {} in the output", column))
+}
+
+/// Fail unless the file at `path` holds `expected` rows.
+pub fn assert_row_count(path: impl AsRef<Path>, expected: usize) -> Result<()> {
+    let rows: usize = read_parquet(&path)?.iter().map(RecordBatch::num_rows).sum();
+    anyhow::ensure!(rows == expected, "{} holds {} rows, expected {}", path.as_ref().display(), rows, expected);
+    Ok(())
+}
+
+/// Fail unless the file at `path` has exactly the columns `expected`, in order.
+pub fn assert_columns(path: impl AsRef<Path>, expected: &[&str]) -> Result<()> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let schema = ParquetRecordBatchReaderBuilder::try_new(file)?.schema().clone();
+    let columns: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+    anyhow::ensure!(columns == expected, "{} has columns {:?}, expected {:?}", path.display(), columns, expected);
+    Ok(())
+}
@@ -0,0 +1,410 @@
+//! Source protection: caps on rows fetched per second and on queries running
+//! at once, shared by every worker of an extractor
+//!
+//! Workers that would go over either limit wait, holding their cursor open,
+//! so a production server keeps capacity for application queries. The row
+//! rate is a token bucket with [`BURST`] of slack; queries are counted while
+//! a [`ThrottledSource`] connection call runs, and for planning and
+//! validation queries on the pool. Blob lookups by `RDB$DB_KEY` aren't
+//! counted, since they run while the row fetch that needs them holds a slot.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::cancel::CancellationToken;
+use crate::extractor::TableSummary;
+use crate::source::{Row, Select, Source, SourceConnection};
+use crate::types::FieldInfo;
+
+/// Rows that may go over the rate briefly, as time at the limit.
+const BURST: Duration = Duration::from_millis(250);
+
+/// Rows a connection fetches before it accounts for them.
+const MAX_CHUNK: u64 = 256;
+
+/// The limits and how much they held workers back.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ThrottleStats {
+    pub max_rows_per_sec: Option<u64>,
+    pub max_concurrent_queries: Option<usize>,
+    /// Rows counted against the rate limit
+    pub rows: u64,
+    /// Time workers spent waiting for either limit, summed over workers
+    pub waited_secs: f64,
+}
+
+pub(crate) struct Throttle {
+    max_rows_per_sec: Option<u64>,
+    max_queries: Option<usize>,
+    state: Mutex<State>,
+    freed: Condvar,
+    rows: AtomicU64,
+    waited_micros: AtomicU64,
+}
+
+struct State {
+    running: usize,
+    /// When the rows accounted so far would have been fetched at the limit
+    rows_due: Instant,
+}
+
+impl Throttle {
+    pub(crate) fn new(max_rows_per_sec: Option<u64>, max_queries: Option<usize>) -> Self {
+        Self {
+            max_rows_per_sec,
+            max_queries,
+            state: Mutex::new(State { running: 0, rows_due: Instant::now() }),
+            freed: Condvar::new(),
+            rows: AtomicU64::new(0),
+            waited_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn is_limited(&self) -> bool {
+        self.max_rows_per_sec.is_some() || self.max_queries.is_some()
+    }
+
+    /// Wait for a query slot, held until the permit is dropped.
+    pub(crate) fn query(&self) -> QueryPermit<'_> {
+        let Some(max) = self.max_queries else {
+            return QueryPermit { throttle: None };
+        };
+        let start = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        while state.running >= max {
+            state = self.freed.wait(state).unwrap();
+        }
+        state.running += 1;
+        drop(state);
+        self.waited(start.elapsed());
+        QueryPermit { throttle: Some(self) }
+    }
+
+    /// Account for `rows` fetched, sleeping as long as they put the caller
+    /// ahead of the rate.
+    pub(crate) fn rows(&self, rows: u64) {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        let Some(rate) = self.max_rows_per_sec else {
+            return;
+        };
+        let cost = Duration::from_secs_f64(rows as f64 / rate as f64);
+        let now = Instant::now();
+        let due = {
+            let mut state = self.state.lock().unwrap();
+            state.rows_due = state.rows_due.max(now) + cost;
+            state.rows_due
+        };
+        let ahead = due.saturating_duration_since(now);
+        if ahead > BURST {
+            thread::sleep(ahead - BURST);
+            self.waited(ahead - BURST);
+        }
+    }
+
+    /// Rows to fetch between calls to [`Throttle::rows`]: about a tenth of a
+    /// second's worth, so a slow rate doesn't mean long sleeps.
+    fn chunk(&self) -> u64 {
+        self.max_rows_per_sec.map_or(MAX_CHUNK, |rate| (rate / 10).clamp(1, MAX_CHUNK))
+    }
+
+    fn waited(&self, duration: Duration) {
+        if !duration.is_zero() {
+            self.waited_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ThrottleStats {
+        ThrottleStats {
+            max_rows_per_sec: self.max_rows_per_sec,
+            max_concurrent_queries: self.max_queries,
+            rows: self.rows.load(Ordering::Relaxed),
+            waited_secs: self.waited_micros.load(Ordering::Relaxed) as f64 / 1e6,
+        }
+    }
+}
+
+pub(crate) struct QueryPermit<'a> {
+    throttle: Option<&'a Throttle>,
+}
+
+impl Drop for QueryPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(throttle) = self.throttle {
+            throttle.state.lock().unwrap().running -= 1;
+            throttle.freed.notify_one();
+        }
+    }
+}
+
+/// `source` throttled by `throttle`, or `source` itself if nothing is limited.
+pub(crate) fn throttled(source: Arc<dyn Source>, throttle: &Arc<Throttle>) -> Arc<dyn Source> {
+    if !throttle.is_limited() {
+        return source;
+    }
+    Arc::new(ThrottledSource { source, throttle: Arc::clone(throttle) })
+}
+
+pub(crate) struct ThrottledSource {
+    source: Arc<dyn Source>,
+    throttle: Arc<Throttle>,
+}
+
+impl Source for ThrottledSource {
+    fn connect(&self) -> Result<Box<dyn SourceConnection>> {
+        Ok(Box::new(ThrottledConnection { conn: self.source.connect()?, throttle: Arc::clone(&self.throttle) }))
+    }
+
+    fn connect_until(&self, cancel: &CancellationToken) -> Result<Box<dyn SourceConnection>> {
+        let conn = self.source.connect_until(cancel)?;
+        Ok(Box::new(ThrottledConnection { conn, throttle: Arc::clone(&self.throttle) }))
+    }
+}
+
+struct ThrottledConnection {
+    conn: Box<dyn SourceConnection>,
+    throttle: Arc<Throttle>,
+}
+
+impl SourceConnection for ThrottledConnection {
+    fn has_table(&mut self, name: &str) -> Result<bool> {
+        let _query = self.throttle.query();
+        self.conn.has_table(name)
+    }
+
+    fn fields(&mut self, table: &str) -> Result<Vec<FieldInfo>> {
+        let _query = self.throttle.query();
+        self.conn.fields(table)
+    }
+
+    fn primary_key(&mut self, table: &str) -> Result<Vec<String>> {
+        let _query = self.throttle.query();
+        self.conn.primary_key(table)
+    }
+
+    fn int_range(&mut self, table: &str, column: &str, predicate: Option<&str>) -> Result<(Option<i64>, Option<i64>)> {
+        let _query = self.throttle.query();
+        self.conn.int_range(table, column, predicate)
+    }
+
+    fn key_range(&mut self, table: &str, column: &str) -> Result<(Option<i64>, Option<i64>)> {
+        let _query = self.throttle.query();
+        self.conn.key_range(table, column)
+    }
+
+    fn count_rows(&mut self, table: &str) -> Result<i64> {
+        let _query = self.throttle.query();
+        self.conn.count_rows(table)
+    }
+
+    fn count_selected(&mut self, select: &Select) -> Result<i64> {
+        let _query = self.throttle.query();
+        self.conn.count_selected(select)
+    }
+
+    fn estimate_row_count(&mut self, table: &str) -> Result<Option<i64>> {
+        let _query = self.throttle.query();
+        self.conn.estimate_row_count(table)
+    }
+
+    fn list_tables(&mut self) -> Result<Vec<TableSummary>> {
+        let _query = self.throttle.query();
+        self.conn.list_tables()
+    }
+
+    fn list_views(&mut self) -> Result<Vec<String>> {
+        let _query = self.throttle.query();
+        self.conn.list_views()
+    }
+
+    fn begin_transaction(&mut self) -> Result<()> {
+        self.conn.begin_transaction()
+    }
+
+    fn end_transaction(&mut self) -> Result<()> {
+        self.conn.end_transaction()
+    }
+
+    fn fetch(&mut self, select: &Select, on_row: &mut dyn FnMut(Row) -> Result<bool>) -> Result<()> {
+        let throttle = Arc::clone(&self.throttle);
+        let _query = throttle.query();
+        let chunk = throttle.chunk();
+        let mut pending = 0;
+        let result = self.conn.fetch(select, &mut |row| {
+            pending += 1;
+            if pending == chunk {
+                throttle.rows(pending);
+                pending = 0;
+            }
+            on_row(row)
+        });
+        throttle.rows(pending);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::partition::PartitionSpec;
+    use crate::source::Value;
+
+    /// A connection whose every call fails; `fetch` streams `rows` rows first.
+    struct Failing {
+        rows: usize,
+    }
+
+    impl SourceConnection for Failing {
+        fn has_table(&mut self, _: &str) -> Result<bool> {
+            anyhow::bail!("has_table failed")
+        }
+
+        fn fields(&mut self, _: &str) -> Result<Vec<FieldInfo>> {
+            anyhow::bail!("fields failed")
+        }
+
+        fn primary_key(&mut self, _: &str) -> Result<Vec<String>> {
+            anyhow::bail!("primary_key failed")
+        }
+
+        fn int_range(&mut self, _: &str, _: &str, _: Option<&str>) -> Result<(Option<i64>, Option<i64>)> {
+            anyhow::bail!("int_range failed")
+        }
+
+        fn count_rows(&mut self, _: &str) -> Result<i64> {
+            anyhow::bail!("count_rows failed")
+        }
+
+        fn count_selected(&mut self, _: &Select) -> Result<i64> {
+            anyhow::bail!("count_selected failed")
+        }
+
+        fn estimate_row_count(&mut self, _: &str) -> Result<Option<i64>> {
+            anyhow::bail!("estimate_row_count failed")
+        }
+
+        fn list_tables(&mut self) -> Result<Vec<TableSummary>> {
+            anyhow::bail!("list_tables failed")
+        }
+
+        fn list_views(&mut self) -> Result<Vec<String>> {
+            anyhow::bail!("list_views failed")
+        }
+
+        fn fetch(&mut self, _: &Select, on_row: &mut dyn FnMut(Row) -> Result<bool>) -> Result<()> {
+            for i in 0..self.rows {
+                if !on_row(Row::new(vec![Value::Integer(i as i64)]))? {
+                    return Ok(());
+                }
+            }
+            anyhow::bail!("fetch failed")
+        }
+    }
+
+    fn running(throttle: &Throttle) -> usize {
+        throttle.state.lock().unwrap().running
+    }
+
+    #[test]
+    fn paces_rows_to_the_rate() {
+        let throttle = Throttle::new(Some(4_000), None);
+        let start = Instant::now();
+        // A burst's worth goes through at once
+        throttle.rows(1_000);
+        assert!(start.elapsed() < Duration::from_millis(100), "{:?}", start.elapsed());
+        for _ in 0..10 {
+            throttle.rows(400);
+        }
+        // 5,000 rows at 4,000/s take 1.25s, less the 250ms burst
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(950), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(3), "{:?}", elapsed);
+        let stats = throttle.stats();
+        assert_eq!(stats.rows, 5_000);
+        assert!(stats.waited_secs >= 0.9, "{}", stats.waited_secs);
+    }
+
+    #[test]
+    fn counts_rows_without_a_rate() {
+        let throttle = Throttle::new(None, None);
+        assert!(!throttle.is_limited());
+        let start = Instant::now();
+        throttle.rows(10_000_000);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        let stats = throttle.stats();
+        assert_eq!(stats.rows, 10_000_000);
+        assert_eq!(stats.waited_secs, 0.0);
+    }
+
+    #[test]
+    fn accounts_rows_in_chunks() {
+        assert_eq!(Throttle::new(None, Some(4)).chunk(), MAX_CHUNK);
+        assert_eq!(Throttle::new(Some(1_000_000), None).chunk(), MAX_CHUNK);
+        assert_eq!(Throttle::new(Some(500), None).chunk(), 50);
+        assert_eq!(Throttle::new(Some(5), None).chunk(), 1);
+    }
+
+    #[test]
+    fn gates_concurrent_queries() {
+        let throttle = Throttle::new(None, Some(2));
+        let first = throttle.query();
+        let second = throttle.query();
+        assert_eq!(running(&throttle), 2);
+        let (done_tx, done_rx) = mpsc::channel();
+        thread::scope(|scope| {
+            let throttle = &throttle;
+            scope.spawn(move || {
+                let _third = throttle.query();
+                done_tx.send(()).unwrap();
+            });
+            assert!(done_rx.recv_timeout(Duration::from_millis(100)).is_err(), "third query didn't wait");
+            drop(first);
+            done_rx.recv_timeout(Duration::from_secs(5)).expect("third query ran after a slot was freed");
+        });
+        drop(second);
+        assert_eq!(running(&throttle), 0);
+        assert!(throttle.stats().waited_secs > 0.0);
+    }
+
+    #[test]
+    fn releases_query_slots_on_errors() {
+        let throttle = Arc::new(Throttle::new(Some(1_000_000), Some(1)));
+        let mut conn = ThrottledConnection { conn: Box::new(Failing { rows: 300 }), throttle: Arc::clone(&throttle) };
+        let select = Select {
+            table: "T".to_string(),
+            columns: vec!["ID".to_string()],
+            db_key: false,
+            partition: PartitionSpec::full(),
+            order_by: Vec::new(),
+        };
+        assert!(conn.has_table("T").is_err());
+        assert!(conn.fields("T").is_err());
+        assert!(conn.primary_key("T").is_err());
+        assert!(conn.int_range("T", "ID", None).is_err());
+        assert!(conn.key_range("T", "ID").is_err());
+        assert!(conn.count_rows("T").is_err());
+        assert!(conn.count_selected(&select).is_err());
+        assert!(conn.estimate_row_count("T").is_err());
+        assert!(conn.list_tables().is_err());
+        assert!(conn.list_views().is_err());
+        assert_eq!(running(&throttle), 0);
+
+        // The server failing mid-fetch, and the caller's row callback failing
+        assert!(conn.fetch(&select, &mut |_| Ok(true)).is_err());
+        assert_eq!(running(&throttle), 0);
+        assert!(conn.fetch(&select, &mut |_| anyhow::bail!("sink failed")).is_err());
+        assert_eq!(running(&throttle), 0);
+        // Rows fetched before a failure still count against the rate
+        assert_eq!(throttle.stats().rows, 301);
+    }
+}
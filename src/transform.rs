@@ -0,0 +1,49 @@
+//! Batch transform hook for embedders
+//!
+//! A transform sees every converted batch before it's written or streamed,
+//! and can drop rows, derive columns or redact values in flight. It also runs
+//! once on an empty batch before the extraction starts, to learn the schema
+//! the output files are created with, so it must accept zero-row batches and
+//! map every batch of a table to the same schema.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+
+use crate::extractor::{Extractor, TableMetadata};
+
+pub(crate) type BatchTransform = Arc<dyn Fn(RecordBatch) -> Result<Option<RecordBatch>> + Send + Sync>;
+
+impl Extractor {
+    /// Pass every batch through `transform` before it's written; returning
+    /// `Ok(None)` drops the batch, an error fails the partition like a fetch
+    /// error would.
+    pub fn with_transform(
+        mut self,
+        transform: impl Fn(RecordBatch) -> Result<Option<RecordBatch>> + Send + Sync + 'static,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// The schema batches have once transformed: the table's own without a
+    /// transform, else whatever the transform makes of an empty batch.
+    pub(crate) fn output_schema(&self, meta: &TableMetadata) -> Result<SchemaRef> {
+        let schema = meta.arrow_schema();
+        let Some(ref transform) = self.transform else {
+            return Ok(schema);
+        };
+        let probe = transform(RecordBatch::new_empty(Arc::clone(&schema)))
+            .with_context(|| format!("Transform failed on an empty batch of {}", meta.table_name))?;
+        Ok(probe.map_or(schema, |batch| batch.schema()))
+    }
+}
+
+/// `batch` through the transform, if there is one.
+pub(crate) fn apply(transform: Option<&BatchTransform>, batch: RecordBatch) -> Result<Option<RecordBatch>> {
+    match transform {
+        Some(transform) => transform(batch),
+        None => Ok(Some(batch)),
+    }
+}
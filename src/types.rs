@@ -0,0 +1,113 @@
+//! Mapping Firebird column types to Arrow
+//!
+//! Every column's Arrow type comes from a [`TypeMapper`], and so can its
+//! conversion. The [`DefaultTypeMapper`] maps integers to `Int64`, floating
+//! point to `Float64`, DATE/TIME/TIMESTAMP to `Date32`/`Time64`/`Timestamp`
+//! (with a zone when the extraction's `timestamp_zone` isn't naive),
+//! BOOLEAN to `Boolean`, text to `Utf8` and binary blobs to `Binary`; a custom
+//! mapper can pick other types for particular domains or columns (say
+//! epoch seconds stored as INTEGER → `Timestamp`) and build those arrays
+//! itself. Per-table `types` overrides still apply on top.
+
+use anyhow::Result;
+use arrow::{
+    array::ArrayRef,
+    datatypes::{DataType, TimeUnit},
+};
+use crate::config::TimestampZone;
+use crate::source::{Row, Value};
+
+/// What Firebird says about a column, from `rdb$relation_fields` and `rdb$fields`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct FieldInfo {
+    pub table: String,
+    pub column: String,
+    /// `rdb$field_type`, e.g. 8 INTEGER, 16 BIGINT, 37 VARCHAR, 35 TIMESTAMP, 261 BLOB
+    pub field_type: i16,
+    /// `rdb$field_sub_type`; 1 marks a text blob
+    pub sub_type: i16,
+    /// `rdb$field_scale`; negative for NUMERIC/DECIMAL
+    pub scale: i16,
+    /// The field's domain (`rdb$field_source`); `RDB$<n>` for columns declared without one
+    pub domain: String,
+    /// How a TIMESTAMP field is written: the extraction's `timestamp_zone`,
+    /// which the extractor fills in; naive as a source reports it
+    pub timestamp_zone: TimestampZone,
+}
+
+/// One column of a fetched chunk, as the source returned it.
+pub struct ColumnValues<'a> {
+    rows: &'a [Row],
+    index: usize,
+}
+
+impl<'a> ColumnValues<'a> {
+    pub(crate) fn new(rows: &'a [Row], index: usize) -> Self {
+        Self { rows, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The column's value in each row; `None` for NULL.
+    pub fn iter(&self) -> impl Iterator<Item = Option<&'a Value>> + 'a {
+        let index = self.index;
+        self.rows.iter().map(move |row| row.get(index).filter(|v| !matches!(v, Value::Null)))
+    }
+}
+
+/// Chooses each column's Arrow type and, for types the built-in conversion
+/// doesn't produce, builds the array. Set with
+/// [`Extractor::with_type_mapper`](crate::Extractor::with_type_mapper).
+pub trait TypeMapper: Send + Sync {
+    /// Arrow type to write `field` as.
+    fn arrow_type(&self, field: &FieldInfo) -> DataType {
+        default_arrow_type(field)
+    }
+
+    /// Build the array for a column mapped to `data_type`, or `None` to use
+    /// the built-in conversion, which handles the types [`default_arrow_type`]
+    /// picks. Other types must be converted here or end up all null.
+    fn convert(&self, field: &FieldInfo, data_type: &DataType, values: ColumnValues<'_>) -> Option<Result<ArrayRef>> {
+        let _ = (field, data_type, values);
+        None
+    }
+}
+
+/// The built-in mapping.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTypeMapper;
+
+impl TypeMapper for DefaultTypeMapper {}
+
+/// The built-in Arrow type for a Firebird field type.
+pub fn default_arrow_type(field: &FieldInfo) -> DataType {
+    match field.field_type {
+        7 => DataType::Int64,   // SMALLINT
+        8 => DataType::Int64,   // INTEGER
+        16 => DataType::Int64,  // BIGINT
+        10 => DataType::Float64, // FLOAT
+        27 => DataType::Float64, // DOUBLE
+        12 => DataType::Date32, // DATE
+        // TIME, TIMESTAMP; WITH TIME ZONE arrive zoneless through SET BIND
+        13 | 28 => DataType::Time64(TimeUnit::Microsecond),
+        35 | 29 => DataType::Timestamp(TimeUnit::Microsecond, field.timestamp_zone.arrow_timezone()),
+        23 => DataType::Boolean, // BOOLEAN
+        261 => {
+            if field.sub_type == 1 {
+                DataType::Utf8  // BLOB SUB_TYPE TEXT
+            } else {
+                DataType::Binary  // BLOB
+            }
+        }
+        14 => DataType::Utf8,  // CHAR
+        37 => DataType::Utf8,  // VARCHAR
+        _ => DataType::Utf8,   // Default to string
+    }
+}
@@ -0,0 +1,298 @@
+//! `--validate`: per-column aggregates of the source checked against the output
+//!
+//! Every column's non-null count is compared. Numeric columns add SUM, MIN
+//! and MAX; CHAR and VARCHAR columns add the total character count of their
+//! trimmed values. A silent type coercion (a scaled NUMERIC truncated to an
+//! integer) or charset damage (characters dropped or replaced) changes at
+//! least one of them. The source side is one aggregate query over the table,
+//! narrowed by its filter; the output side re-reads the Parquet file.
+//! Masked columns are skipped, as their output differs by design.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use arrow::{
+    array::{Array, Float64Array, Int64Array, StringArray},
+    compute::cast,
+    datatypes::DataType,
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use rsfbclient::{Queryable, Row, SqlType};
+use serde::Serialize;
+
+use crate::extractor::{ColumnMetadata, TableMetadata};
+use crate::partition::{quote_ident, PartitionSpec};
+
+/// Relative difference tolerated between floating point aggregates, whose
+/// summation order differs between Firebird and here.
+const FLOAT_TOLERANCE: f64 = 1e-9;
+
+/// Written as `<table>.validation.json` beside the output.
+#[derive(Debug, Serialize)]
+pub(crate) struct ValidationReport {
+    pub(crate) table: String,
+    pub(crate) matches: bool,
+    pub(crate) checks: Vec<Check>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Check {
+    pub(crate) column: String,
+    pub(crate) check: &'static str,
+    pub(crate) source: Option<Value>,
+    pub(crate) output: Option<Value>,
+    pub(crate) matches: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    fn from_sql(value: &SqlType) -> Option<Self> {
+        match value {
+            SqlType::Integer(v) => Some(Self::Int(*v)),
+            SqlType::Floating(v) => Some(Self::Float(*v)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(v) => v as f64,
+            Self::Float(v) => v,
+        }
+    }
+
+    fn matches(a: Option<Self>, b: Option<Self>) -> bool {
+        match (a, b) {
+            (None, None) => true,
+            (Some(Self::Int(a)), Some(Self::Int(b))) => a == b,
+            (Some(a), Some(b)) => {
+                let (a, b) = (a.as_f64(), b.as_f64());
+                (a - b).abs() <= FLOAT_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Which aggregates a column gets.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Numeric,
+    Text,
+    /// Blobs and custom-mapped types: non-null count only
+    Other,
+}
+
+fn kind(column: &ColumnMetadata) -> Kind {
+    let field_type = column.field.field_type;
+    match column.data_type {
+        // SMALLINT, INTEGER, BIGINT, FLOAT, DOUBLE
+        DataType::Int64 | DataType::Float64 if matches!(field_type, 7 | 8 | 16 | 10 | 27) => Kind::Numeric,
+        // CHAR, VARCHAR
+        DataType::Utf8 | DataType::Dictionary(..) if matches!(field_type, 14 | 37) => Kind::Text,
+        _ => Kind::Other,
+    }
+}
+
+/// Check names in the order of their aggregates in the source query.
+fn check_names(kind: Kind) -> &'static [&'static str] {
+    match kind {
+        Kind::Numeric => &["non_null", "sum", "min", "max"],
+        Kind::Text => &["non_null", "char_length"],
+        Kind::Other => &["non_null"],
+    }
+}
+
+/// `SELECT` of every column's aggregates, in column order.
+fn source_sql(meta: &TableMetadata) -> String {
+    let aggregates: Vec<String> = meta
+        .columns
+        .iter()
+        .map(|c| {
+            let name = quote_ident(&c.name);
+            match kind(c) {
+                Kind::Numeric => format!("COUNT({0}), SUM(CAST({0} AS DOUBLE PRECISION)), MIN({0}), MAX({0})", name),
+                Kind::Text => format!("COUNT({0}), SUM(CHAR_LENGTH(TRIM({0})))", name),
+                Kind::Other => format!("COUNT({})", name),
+            }
+        })
+        .collect();
+    let spec = match meta.filter {
+        Some(ref filter) => PartitionSpec::predicate(filter.clone()),
+        None => PartitionSpec::full(),
+    };
+    spec.select_sql(&aggregates.join(", "), &meta.table_name)
+}
+
+/// Aggregates of one column over the written output.
+#[derive(Default)]
+struct Accumulator {
+    non_null: i64,
+    int_sum: i128,
+    float_sum: f64,
+    min: Option<Value>,
+    max: Option<Value>,
+    chars: i64,
+}
+
+impl Accumulator {
+    fn add_int(&mut self, v: i64) {
+        self.int_sum += v as i128;
+        let (min, max) = match (self.min, self.max) {
+            (Some(Value::Int(min)), Some(Value::Int(max))) => (min.min(v), max.max(v)),
+            _ => (v, v),
+        };
+        self.min = Some(Value::Int(min));
+        self.max = Some(Value::Int(max));
+    }
+
+    fn add_float(&mut self, v: f64) {
+        if v.is_nan() {
+            return;
+        }
+        self.float_sum += v;
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) => (min.as_f64().min(v), max.as_f64().max(v)),
+            _ => (v, v),
+        };
+        self.min = Some(Value::Float(min));
+        self.max = Some(Value::Float(max));
+    }
+
+    /// Values in the order of [`check_names`].
+    fn values(&self, kind: Kind, data_type: &DataType) -> Vec<Option<Value>> {
+        let non_null = Some(Value::Int(self.non_null));
+        match kind {
+            Kind::Numeric => {
+                let sum = if self.non_null == 0 {
+                    None
+                } else if *data_type == DataType::Int64 {
+                    Some(Value::Float(self.int_sum as f64))
+                } else {
+                    Some(Value::Float(self.float_sum))
+                };
+                vec![non_null, sum, self.min, self.max]
+            }
+            Kind::Text => {
+                let chars = if self.non_null == 0 { None } else { Some(Value::Int(self.chars)) };
+                vec![non_null, chars]
+            }
+            Kind::Other => vec![non_null],
+        }
+    }
+}
+
+fn output_aggregates(meta: &TableMetadata, output_path: &Path) -> Result<Vec<Option<Accumulator>>> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(output_path)?)?;
+    let indices: Vec<Option<usize>> =
+        meta.columns.iter().map(|c| builder.schema().index_of(&c.output_name).ok()).collect();
+    let reader = builder.with_batch_size(100_000).build()?;
+
+    let mut accumulators: Vec<Option<Accumulator>> =
+        indices.iter().map(|i| i.map(|_| Accumulator::default())).collect();
+    for batch in reader {
+        let batch = batch?;
+        for (column, (index, acc)) in meta.columns.iter().zip(indices.iter().zip(&mut accumulators)) {
+            let (Some(index), Some(acc)) = (index, acc) else {
+                continue;
+            };
+            let array = batch.column(*index);
+            acc.non_null += (array.len() - array.null_count()) as i64;
+            match kind(column) {
+                Kind::Numeric => {
+                    if let Some(ints) = array.as_any().downcast_ref::<Int64Array>() {
+                        ints.iter().flatten().for_each(|v| acc.add_int(v));
+                    } else if let Some(floats) = array.as_any().downcast_ref::<Float64Array>() {
+                        floats.iter().flatten().for_each(|v| acc.add_float(v));
+                    }
+                }
+                Kind::Text => {
+                    let array = match array.data_type() {
+                        DataType::Dictionary(..) => cast(array, &DataType::Utf8)?,
+                        _ => Arc::clone(array),
+                    };
+                    if let Some(strings) = array.as_any().downcast_ref::<StringArray>() {
+                        acc.chars += strings.iter().flatten().map(|s| s.chars().count() as i64).sum::<i64>();
+                    }
+                }
+                Kind::Other => {}
+            }
+        }
+    }
+    Ok(accumulators)
+}
+
+/// Compare the source's aggregates with the output's over `conn`.
+pub(crate) fn validate(
+    conn: &mut impl Queryable,
+    meta: &TableMetadata,
+    output_path: &Path,
+) -> Result<ValidationReport> {
+    let rows: Vec<Row> = conn.query(&source_sql(meta), ()).context("Source aggregate query failed")?;
+    let row = rows.into_iter().next().context("Source aggregate query returned no row")?;
+    let mut source = row.cols.iter().map(|c| Value::from_sql(&c.value));
+    let output = output_aggregates(meta, output_path)
+        .with_context(|| format!("Failed to read {} for validation", output_path.display()))?;
+
+    let mut checks = Vec::new();
+    for (column, acc) in meta.columns.iter().zip(output) {
+        let kind = kind(column);
+        let names = check_names(kind);
+        let source_values: Vec<Option<Value>> = (0..names.len()).map(|_| source.next().flatten()).collect();
+        if column.mask.is_some() {
+            continue;
+        }
+        let output_values = match acc {
+            Some(ref acc) => acc.values(kind, &column.data_type),
+            // Not in the output (a transform dropped it): everything mismatches
+            None => vec![None; names.len()],
+        };
+        for ((name, source), output) in names.iter().zip(source_values).zip(output_values) {
+            let matches = acc.is_some() && Value::matches(source, output);
+            checks.push(Check { column: column.name.clone(), check: *name, source, output, matches });
+        }
+    }
+    Ok(ValidationReport {
+        table: meta.table_name.clone(),
+        matches: checks.iter().all(|c| c.matches),
+        checks,
+    })
+}
+
+impl ValidationReport {
+    /// `orders.parquet` → `orders.validation.json`
+    pub(crate) fn path_for(output_file: &Path) -> PathBuf {
+        output_file.with_extension("validation.json")
+    }
+
+    pub(crate) fn write(&self, output_file: &Path) -> Result<PathBuf> {
+        let path = Self::path_for(output_file);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write validation report {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The failed checks, e.g. `AMOUNT sum: source 1234.5, output 1234`.
+    pub(crate) fn mismatches(&self) -> Vec<String> {
+        let show = |value: Option<Value>| match value {
+            Some(Value::Int(v)) => v.to_string(),
+            Some(Value::Float(v)) => v.to_string(),
+            None => "null".to_string(),
+        };
+        self.checks
+            .iter()
+            .filter(|c| !c.matches)
+            .map(|c| format!("{} {}: source {}, output {}", c.column, c.check, show(c.source), show(c.output)))
+            .collect()
+    }
+}
@@ -0,0 +1,197 @@
+//! Shared Parquet writer pool for partitioned extraction
+//!
+//! Converters submit record batches to a bounded queue drained by a fixed
+//! number of writer threads, so Parquet encoding scales independently of the
+//! number of database readers. Each partition still gets its own file; writes
+//! to the same file are serialized by a per-file lock. Once the extraction is
+//! cancelled, queued batches are dropped unwritten.
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::Result;
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+use tracing::{debug_span, Span};
+
+use crate::cancel::{self, CancellationToken};
+use crate::memory::MemoryBudget;
+use crate::profile::{Stage, StageTimes};
+
+type SharedWriter = Arc<Mutex<ArrowWriter<BufWriter<File>>>>;
+
+struct WriteJob {
+    writer: SharedWriter,
+    batch: RecordBatch,
+    ack: Sender<Result<()>>,
+    times: Arc<StageTimes>,
+    /// The submitting partition's span, as writer threads serve all partitions
+    span: Span,
+}
+
+pub(crate) struct WriterPool {
+    jobs: Option<Sender<WriteJob>>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl WriterPool {
+    /// `threads` writers draining a queue of up to `queue_depth` batches.
+    pub(crate) fn new(
+        threads: usize,
+        queue_depth: usize,
+        budget: Arc<MemoryBudget>,
+        cancel: CancellationToken,
+    ) -> Self {
+        let threads = threads.max(1);
+        let (jobs, queue): (Sender<WriteJob>, Receiver<WriteJob>) = bounded(queue_depth.max(1));
+        let handles = (0..threads)
+            .map(|_| {
+                let queue = queue.clone();
+                let budget = Arc::clone(&budget);
+                let cancel = cancel.clone();
+                thread::spawn(move || {
+                    for job in queue.iter() {
+                        let span = debug_span!(parent: &job.span, "write", rows = job.batch.num_rows());
+                        let _entered = span.enter();
+                        let bytes = job.batch.get_array_memory_size();
+                        let result = if cancel.is_cancelled() {
+                            Err(cancel::cancelled())
+                        } else {
+                            job.times.time(Stage::Write, || match job.writer.lock() {
+                                Ok(mut writer) => writer.write(&job.batch).map_err(Into::into),
+                                Err(_) => Err(anyhow::anyhow!("writer lock poisoned")),
+                            })
+                        };
+                        drop(job.batch);
+                        budget.release(bytes);
+                        let _ = job.ack.send(result);
+                    }
+                })
+            })
+            .collect();
+
+        Self { jobs: Some(jobs), handles }
+    }
+
+    /// Start a sink writing one partition's batches to `path`, charging write
+    /// and queue time to `times`.
+    pub(crate) fn sink(
+        &self,
+        path: PathBuf,
+        schema: SchemaRef,
+        props: WriterProperties,
+        times: Arc<StageTimes>,
+    ) -> PartitionSink<'_> {
+        let (ack_tx, ack_rx) = unbounded();
+        PartitionSink {
+            pool: self,
+            path,
+            schema,
+            props,
+            writer: None,
+            ack_tx,
+            ack_rx,
+            pending: 0,
+            times,
+        }
+    }
+}
+
+impl Drop for WriterPool {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// One partition's view of the pool. The file is created on the first batch,
+/// so empty partitions leave nothing behind.
+pub(crate) struct PartitionSink<'a> {
+    pool: &'a WriterPool,
+    path: PathBuf,
+    schema: SchemaRef,
+    props: WriterProperties,
+    writer: Option<SharedWriter>,
+    ack_tx: Sender<Result<()>>,
+    ack_rx: Receiver<Result<()>>,
+    pending: usize,
+    times: Arc<StageTimes>,
+}
+
+impl PartitionSink<'_> {
+    /// Queue a batch; blocks while the writer queue is full.
+    pub(crate) fn write(&mut self, batch: RecordBatch) -> Result<()> {
+        let writer = match self.writer {
+            Some(ref writer) => Arc::clone(writer),
+            None => {
+                let file = File::create(&self.path)?;
+                let buf = BufWriter::with_capacity(128 * 1024 * 1024, file);
+                let writer = Arc::new(Mutex::new(ArrowWriter::try_new(buf, Arc::clone(&self.schema), Some(self.props.clone()))?));
+                self.writer = Some(Arc::clone(&writer));
+                writer
+            }
+        };
+
+        let jobs = self.pool.jobs.as_ref().expect("writer pool shut down");
+        let job = WriteJob {
+            writer,
+            batch,
+            ack: self.ack_tx.clone(),
+            times: Arc::clone(&self.times),
+            span: Span::current(),
+        };
+        self.times
+            .time(Stage::QueueWait, || jobs.send(job))
+            .map_err(|_| anyhow::anyhow!("writer threads exited"))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Wait for all queued batches, then close the file.
+    pub(crate) fn finish(mut self) -> Result<()> {
+        let times = Arc::clone(&self.times);
+        if let Some(e) = times.time(Stage::QueueWait, || self.drain()) {
+            return Err(e);
+        }
+        if let Some(writer) = self.writer.take() {
+            let writer = Arc::try_unwrap(writer)
+                .map_err(|_| anyhow::anyhow!("partition writer still in use"))?
+                .into_inner()
+                .map_err(|_| anyhow::anyhow!("writer lock poisoned"))?;
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    /// Block until every queued batch is written; returns the first failure.
+    fn drain(&mut self) -> Option<anyhow::Error> {
+        let mut first_error = None;
+        while self.pending > 0 {
+            match self.ack_rx.recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    first_error.get_or_insert(e);
+                }
+                Err(_) => break,
+            }
+            self.pending -= 1;
+        }
+        first_error
+    }
+}
+
+impl Drop for PartitionSink<'_> {
+    fn drop(&mut self) {
+        // A failed partition must not leave writes in flight to a file that a
+        // retry is about to recreate
+        self.drain();
+    }
+}
@@ -0,0 +1,52 @@
+//! `--blob-mode external` against a real server (needs Docker)
+#![cfg(feature = "test-support")]
+
+use std::fs;
+
+use arrow::{
+    array::{Array, AsArray},
+    datatypes::{DataType, Int64Type},
+};
+use firebird_peregrine_falcon::test_support::{read_parquet, FirebirdContainer};
+use firebird_peregrine_falcon::{BlobMode, Extractor};
+use sha2::{Digest, Sha256};
+
+#[test]
+fn binary_blobs_become_sidecar_references() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    fb.create_table("DOCS", "ID INTEGER NOT NULL PRIMARY KEY, BODY BLOB SUB_TYPE 0", 3, ":i, 'payload-' || :i")?;
+    let out = std::env::temp_dir().join(format!("blob_external_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out);
+
+    let config = fb.config(&out).blob_mode(BlobMode::External).build()?;
+    Extractor::new(config)?.extract_table("DOCS")?;
+
+    let batches = read_parquet(out.join("docs.parquet"))?;
+    let mut seen = 0;
+    for batch in &batches {
+        let ids = batch.column_by_name("ID").unwrap().as_primitive::<Int64Type>();
+        let body = batch.column_by_name("BODY").unwrap();
+        let DataType::Struct(fields) = body.data_type() else {
+            panic!("BODY is {}, not the sidecar struct", body.data_type());
+        };
+        let names: Vec<&str> = fields.iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ["path", "size", "sha256"]);
+
+        let body = body.as_struct();
+        let paths = body.column(0).as_string::<i32>();
+        let sizes = body.column(1).as_primitive::<Int64Type>();
+        let sums = body.column(2).as_string::<i32>();
+        for row in 0..batch.num_rows() {
+            let expected = format!("payload-{}", ids.value(row));
+            assert_eq!(paths.value(row), format!("blobs/docs/body/{}.bin", ids.value(row)));
+            assert_eq!(sizes.value(row), expected.len() as i64);
+            let sum: String = Sha256::digest(expected.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(sums.value(row), sum);
+            assert_eq!(fs::read(out.join(paths.value(row)))?, expected.as_bytes());
+            seen += 1;
+        }
+    }
+    assert_eq!(seen, 3);
+    let _ = fs::remove_dir_all(&out);
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! Whole-table extraction against a real server (needs Docker)
+#![cfg(feature = "test-support")]
+
+use std::fs;
+
+use firebird_peregrine_falcon::config::OutputMode;
+use firebird_peregrine_falcon::test_support::{
+    assert_columns, assert_row_count, read_i64_column, read_string_column, FirebirdContainer,
+};
+use firebird_peregrine_falcon::{Extractor, Manifest, PartitionStrategy};
+
+const ROWS: usize = 1000;
+
+fn sorted<T: Ord>(mut values: Vec<T>) -> Vec<T> {
+    values.sort();
+    values
+}
+
+#[test]
+fn every_strategy_extracts_every_row() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    fb.create_table(
+        "ORDERS",
+        "ID INTEGER NOT NULL PRIMARY KEY, CUSTOMER VARCHAR(20), AMOUNT BIGINT",
+        ROWS as u64,
+        ":i, 'customer ' || MOD(:i, 7), :i * 100",
+    )?;
+    let ids: Vec<Option<i64>> = (1..=ROWS as i64).map(Some).collect();
+
+    for strategy in [PartitionStrategy::RangePk, PartitionStrategy::HashPk, PartitionStrategy::RowsPaging] {
+        let out = std::env::temp_dir().join(format!("extract_{}_{}", strategy, std::process::id()));
+        let _ = fs::remove_dir_all(&out);
+        let config = fb.config(&out).parallelism(4).partition_strategy(strategy.clone()).build()?;
+        let stats = Extractor::new(config)?.extract_table("ORDERS")?;
+        assert_eq!(stats.rows_extracted, ROWS, "{}", strategy);
+
+        let path = out.join("orders.parquet");
+        assert_row_count(&path, ROWS)?;
+        assert_columns(&path, &["ID", "CUSTOMER", "AMOUNT"])?;
+        assert_eq!(sorted(read_i64_column(&path, "ID")?), ids, "{}", strategy);
+        let amounts: Vec<Option<i64>> = ids.iter().map(|id| id.map(|id| id * 100)).collect();
+        assert_eq!(sorted(read_i64_column(&path, "AMOUNT")?), amounts, "{}", strategy);
+        let customers = read_string_column(&path, "CUSTOMER")?;
+        assert!(customers.iter().all(|c| c.as_deref().is_some_and(|c| c.starts_with("customer "))));
+        let _ = fs::remove_dir_all(&out);
+    }
+    Ok(())
+}
+
+#[test]
+fn keeps_nulls_and_quoted_names() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    // Every third row has no note; the mixed-case name only works quoted
+    fb.create_table(
+        "Order Lines",
+        "ID INTEGER NOT NULL PRIMARY KEY, NOTE VARCHAR(20)",
+        9,
+        ":i, CASE WHEN MOD(:i, 3) = 0 THEN NULL ELSE 'line ' || :i END",
+    )?;
+    let out = std::env::temp_dir().join(format!("extract_quoted_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out);
+
+    Extractor::new(fb.config(&out).build()?)?.extract_table("Order Lines")?;
+
+    let path = out.join("order_lines.parquet");
+    assert_row_count(&path, 9)?;
+    let ids = read_i64_column(&path, "ID")?;
+    let notes = read_string_column(&path, "NOTE")?;
+    for (id, note) in ids.iter().zip(&notes) {
+        let id = id.expect("ID is NOT NULL");
+        let expected = (id % 3 != 0).then(|| format!("line {}", id));
+        assert_eq!(note, &expected);
+    }
+    assert_eq!(notes.iter().filter(|n| n.is_none()).count(), 3);
+    let _ = fs::remove_dir_all(&out);
+    Ok(())
+}
+
+#[test]
+fn overwrite_drops_appended_files() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    fb.create_table("EVENTS", "ID INTEGER NOT NULL PRIMARY KEY", 5, ":i")?;
+    let out = std::env::temp_dir().join(format!("extract_overwrite_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out);
+    let run = |mode: OutputMode| -> anyhow::Result<()> {
+        Extractor::new(fb.config(&out).output_mode(mode).build()?)?.extract_table("EVENTS")?;
+        Ok(())
+    };
+
+    run(OutputMode::FailIfExists)?;
+    run(OutputMode::Append)?;
+    let appended = out.join("events.1.parquet");
+    assert_eq!(Manifest::read(&out.join("events.manifest.json"))?.appended_files, [appended.clone()]);
+
+    run(OutputMode::Overwrite)?;
+    assert!(Manifest::read(&out.join("events.manifest.json"))?.appended_files.is_empty());
+    assert!(!appended.exists());
+    assert!(!out.join("events.1.manifest.json").exists());
+    assert_row_count(&out.join("events.parquet"), 5)?;
+    let _ = fs::remove_dir_all(&out);
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! `--large-blobs` against a real server (needs Docker)
+#![cfg(feature = "test-support")]
+
+use std::fs;
+
+use arrow::datatypes::DataType;
+use firebird_peregrine_falcon::test_support::{read_parquet, read_string_column, FirebirdContainer};
+use firebird_peregrine_falcon::Extractor;
+
+#[test]
+fn blobs_get_64_bit_offsets() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    fb.create_table(
+        "NOTES",
+        "ID INTEGER NOT NULL PRIMARY KEY, BODY BLOB SUB_TYPE TEXT, RAW BLOB SUB_TYPE 0, TITLE VARCHAR(20)",
+        2,
+        ":i, 'note ' || :i, 'raw ' || :i, 'title ' || :i",
+    )?;
+    let out = std::env::temp_dir().join(format!("large_blobs_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out);
+
+    Extractor::new(fb.config(&out).large_blobs(true).build()?)?.extract_table("NOTES")?;
+
+    let path = out.join("notes.parquet");
+    let batches = read_parquet(&path)?;
+    let schema = batches[0].schema();
+    assert_eq!(schema.field_with_name("BODY")?.data_type(), &DataType::LargeUtf8);
+    assert_eq!(schema.field_with_name("RAW")?.data_type(), &DataType::LargeBinary);
+    // Only blobs change
+    assert_eq!(schema.field_with_name("TITLE")?.data_type(), &DataType::Utf8);
+    let mut bodies = read_string_column(&path, "BODY")?;
+    bodies.sort();
+    assert_eq!(bodies, [Some("note 1".to_string()), Some("note 2".to_string())]);
+    let _ = fs::remove_dir_all(&out);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! `--assume-timezone` / `--timestamp-as-utc` against a real server (needs Docker)
+#![cfg(feature = "test-support")]
+
+use std::fs;
+
+use arrow::{
+    array::AsArray,
+    datatypes::{DataType, TimeUnit, TimestampMicrosecondType},
+};
+use firebird_peregrine_falcon::test_support::{read_parquet, FirebirdContainer};
+use firebird_peregrine_falcon::{Extractor, TimestampZone};
+
+/// 2024-01-15 12:00:00 as microseconds since the epoch, read as UTC
+const NOON_UTC: i64 = 1_705_320_000_000_000;
+const HOUR: i64 = 3_600_000_000;
+
+#[test]
+fn timestamps_follow_the_zone_policy() -> anyhow::Result<()> {
+    let fb = FirebirdContainer::start()?;
+    fb.create_table(
+        "EVENTS",
+        "ID INTEGER NOT NULL PRIMARY KEY, AT TIMESTAMP",
+        1,
+        ":i, TIMESTAMP '2024-01-15 12:00:00'",
+    )?;
+
+    let berlin: TimestampZone = "Europe/Berlin".parse().map_err(anyhow::Error::msg)?;
+    let cases = [
+        (TimestampZone::Naive, None, NOON_UTC),
+        (TimestampZone::Utc, Some("UTC"), NOON_UTC),
+        // CET is UTC+1 in January
+        (berlin, Some("Europe/Berlin"), NOON_UTC - HOUR),
+    ];
+    for (zone, arrow_zone, micros) in cases {
+        let dir = format!("timestamps_{}_{}", zone.to_string().replace('/', "_"), std::process::id());
+        let out = std::env::temp_dir().join(dir);
+        let _ = fs::remove_dir_all(&out);
+        Extractor::new(fb.config(&out).timestamp_zone(zone).build()?)?.extract_table("EVENTS")?;
+
+        let batches = read_parquet(out.join("events.parquet"))?;
+        let column = batches[0].column_by_name("AT").unwrap();
+        assert_eq!(column.data_type(), &DataType::Timestamp(TimeUnit::Microsecond, arrow_zone.map(Into::into)));
+        assert_eq!(column.as_primitive::<TimestampMicrosecondType>().value(0), micros, "{}", zone);
+        let _ = fs::remove_dir_all(&out);
+    }
+    Ok(())
+}